@@ -1,7 +1,7 @@
 use crate::api::assets::credit_asset_to_string;
 use crate::error::Result;
 use crate::page::Page;
-use crate::request::{Order, PageRequest, Request, UrlPageRequestExt};
+use crate::request::{Order, PageRequest, Request, StreamRequest, UrlPageRequestExt};
 use crate::resources;
 use stellar_base::asset::CreditAsset;
 use stellar_base::crypto::PublicKey;
@@ -64,6 +64,10 @@ impl Request for SingleAccountRequest {
     }
 }
 
+impl StreamRequest for SingleAccountRequest {
+    type Resource = resources::Account;
+}
+
 impl Request for AllAccountsRequest {
     type Response = Page<resources::Account>;
 
@@ -11,9 +11,11 @@ pub fn all() -> AllClaimableBalancesRequest {
     AllClaimableBalancesRequest::default()
 }
 
-/// Creates a request to retrieve a single offer.
-pub fn single(balance_id: ClaimableBalanceId) -> SingleClaimableBalanceRequest {
-    SingleClaimableBalanceRequest { balance_id }
+/// Creates a request to retrieve a single claimable balance.
+pub fn single(balance_id: &ClaimableBalanceId) -> SingleClaimableBalanceRequest {
+    SingleClaimableBalanceRequest {
+        balance_id: balance_id.clone(),
+    }
 }
 
 /// Request all open offers.
@@ -123,9 +125,10 @@ mod tests {
 
     #[test]
     fn test_single_claimable_balance_request_uri() {
-        let req = single(
-            "00000000c582697b67cbec7f9ce64f4dc67bfb2bfd26318bb9f964f4d70e3f41f650b1e6".to_string(),
-        );
+        let balance_id = "00000000c582697b67cbec7f9ce64f4dc67bfb2bfd26318bb9f964f4d70e3f41f650b1e6"
+            .parse()
+            .unwrap();
+        let req = single(&balance_id);
         let uri = req.uri(&host()).unwrap();
         assert!(uri
             .to_string()
@@ -1,7 +1,7 @@
 use crate::error::Result;
 use crate::page::Page;
 use crate::request::{Order, PageRequest, Request, StreamRequest, UrlPageRequestExt};
-use crate::resources::{self, LedgerId};
+use crate::resources::{self, LedgerSequence, LiquidityPoolId, OperationId, TransactionHash};
 use stellar_base::PublicKey;
 use url::Url;
 
@@ -11,12 +11,9 @@ pub fn all() -> AllEffectsRequest {
 }
 
 /// Create a request to retrieve effects for a transaction.
-pub fn for_transaction<S>(tx_hash: S) -> EffectsForTransactionRequest
-where
-    S: Into<String>,
-{
+pub fn for_transaction(tx_hash: &TransactionHash) -> EffectsForTransactionRequest {
     EffectsForTransactionRequest {
-        tx_hash: tx_hash.into(),
+        tx_hash: tx_hash.clone(),
         limit: None,
         cursor: None,
         order: None,
@@ -24,12 +21,9 @@ where
 }
 
 /// Create a request to retrieve effects for an operation.
-pub fn for_operation<S>(operation_id: S) -> EffectsForOperationRequest
-where
-    S: Into<String>,
-{
+pub fn for_operation(operation_id: OperationId) -> EffectsForOperationRequest {
     EffectsForOperationRequest {
-        operation_id: operation_id.into(),
+        operation_id,
         limit: None,
         cursor: None,
         order: None,
@@ -37,7 +31,7 @@ where
 }
 
 /// Create a request to retrieve effects for a ledger.
-pub fn for_ledger(ledger: LedgerId) -> EffectsForLedgerRequest {
+pub fn for_ledger(ledger: LedgerSequence) -> EffectsForLedgerRequest {
     EffectsForLedgerRequest {
         ledger,
         limit: None,
@@ -56,13 +50,10 @@ pub fn for_account(account: &PublicKey) -> EffectsForAccountRequest {
     }
 }
 
-/// Create a request to retrieve effects for an operation.
-pub fn for_liquidity_pool<S>(liquidity_pool_id: S) -> EffectsForLiquidityPoolRequest
-where
-    S: Into<String>,
-{
+/// Create a request to retrieve effects for a liquidity pool.
+pub fn for_liquidity_pool(liquidity_pool_id: &LiquidityPoolId) -> EffectsForLiquidityPoolRequest {
     EffectsForLiquidityPoolRequest {
-        liquidity_pool_id: liquidity_pool_id.into(),
+        liquidity_pool_id: liquidity_pool_id.clone(),
         limit: None,
         cursor: None,
         order: None,
@@ -80,7 +71,7 @@ pub struct AllEffectsRequest {
 /// Request effects for a ledger.
 #[derive(Debug, Clone)]
 pub struct EffectsForLedgerRequest {
-    ledger: LedgerId,
+    ledger: LedgerSequence,
     limit: Option<u64>,
     cursor: Option<String>,
     order: Option<Order>,
@@ -89,7 +80,7 @@ pub struct EffectsForLedgerRequest {
 /// Request effects for a transaction.
 #[derive(Debug, Clone)]
 pub struct EffectsForTransactionRequest {
-    tx_hash: String,
+    tx_hash: TransactionHash,
     limit: Option<u64>,
     cursor: Option<String>,
     order: Option<Order>,
@@ -98,7 +89,7 @@ pub struct EffectsForTransactionRequest {
 /// Request effects for an operation.
 #[derive(Debug, Clone)]
 pub struct EffectsForOperationRequest {
-    operation_id: String,
+    operation_id: OperationId,
     limit: Option<u64>,
     cursor: Option<String>,
     order: Option<Order>,
@@ -116,7 +107,7 @@ pub struct EffectsForAccountRequest {
 /// Request effects for a liquidity pool.
 #[derive(Debug, Clone)]
 pub struct EffectsForLiquidityPoolRequest {
-    liquidity_pool_id: String,
+    liquidity_pool_id: LiquidityPoolId,
     limit: Option<u64>,
     cursor: Option<String>,
     order: Option<Order>,
@@ -163,6 +154,10 @@ impl Request for EffectsForTransactionRequest {
 
 impl_page_request!(EffectsForTransactionRequest);
 
+impl StreamRequest for EffectsForTransactionRequest {
+    type Resource = resources::Effect;
+}
+
 impl Request for EffectsForOperationRequest {
     type Response = Page<resources::Effect>;
 
@@ -174,6 +169,10 @@ impl Request for EffectsForOperationRequest {
 
 impl_page_request!(EffectsForOperationRequest);
 
+impl StreamRequest for EffectsForOperationRequest {
+    type Resource = resources::Effect;
+}
+
 impl Request for EffectsForAccountRequest {
     type Response = Page<resources::Effect>;
 
@@ -212,6 +211,7 @@ mod tests {
     use super::{all, for_account, for_ledger, for_operation, for_transaction};
     use crate::api::effects::for_liquidity_pool;
     use crate::request::{Order, PageRequest, Request};
+    use crate::resources::{LedgerSequence, OperationId};
     use std::collections::HashMap;
     use stellar_base::crypto::PublicKey;
     use url::Url;
@@ -247,7 +247,7 @@ mod tests {
 
     #[test]
     fn test_effects_for_ledger_request_uri() {
-        let req = for_ledger(123).with_order(&Order::Ascending);
+        let req = for_ledger(LedgerSequence::new(123)).with_order(&Order::Ascending);
         let uri = req.uri(&host()).unwrap();
         assert!(uri
             .to_string()
@@ -256,7 +256,7 @@ mod tests {
 
     #[test]
     fn test_effects_for_operation_request_uri() {
-        let req = for_operation("12345");
+        let req = for_operation(OperationId::new(12345).unwrap());
         let uri = req.uri(&host()).unwrap();
         assert!(uri
             .to_string()
@@ -265,8 +265,10 @@ mod tests {
 
     #[test]
     fn test_effects_for_transaction_request_uri() {
-        let req =
-            for_transaction("23bf920c4a000b78268589df224c1ba4c883a905687f5a5b3bdba721ee1f481e");
+        let tx_hash = "23bf920c4a000b78268589df224c1ba4c883a905687f5a5b3bdba721ee1f481e"
+            .parse()
+            .unwrap();
+        let req = for_transaction(&tx_hash);
         let uri = req.uri(&host()).unwrap();
         assert!(uri
             .to_string()
@@ -277,8 +279,10 @@ mod tests {
     fn test_effects_for_liquidity_pool_request_uri() {
         let expected_uri = "https://horizon.stellar.org/liquidity_pools/006881bb9a17b0c0f4000cb12eaeb2b954390707b03a676b87f824dc6af9f207/effects?";
 
-        let req =
-            for_liquidity_pool("006881bb9a17b0c0f4000cb12eaeb2b954390707b03a676b87f824dc6af9f207");
+        let liquidity_pool_id = "006881bb9a17b0c0f4000cb12eaeb2b954390707b03a676b87f824dc6af9f207"
+            .parse()
+            .unwrap();
+        let req = for_liquidity_pool(&liquidity_pool_id);
         let uri = req.uri(&host()).unwrap();
 
         assert_eq!(expected_uri, uri.as_str());
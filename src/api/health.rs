@@ -0,0 +1,25 @@
+use crate::error::Result;
+use crate::request::Request;
+use crate::resources;
+use url::Url;
+
+pub fn health() -> HealthRequest {
+    HealthRequest {}
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct HealthRequest {}
+
+impl Request for HealthRequest {
+    type Response = resources::Health;
+
+    fn uri(&self, host: &Url) -> Result<Url> {
+        Ok(host.join("health")?)
+    }
+
+    // Unhealthy horizon servers answer with the health of each
+    // component and a `503 Service Unavailable` status.
+    fn accepts_status(&self, status: u16) -> bool {
+        status == 503
+    }
+}
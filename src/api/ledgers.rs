@@ -1,11 +1,11 @@
 use crate::error::Result;
 use crate::page::Page;
 use crate::request::{Order, PageRequest, Request, StreamRequest, UrlPageRequestExt};
-use crate::resources::{self, LedgerId};
+use crate::resources::{self, LedgerSequence};
 use url::Url;
 
 /// Creates a request to retrieve a single ledger.
-pub fn single(ledger_sequence: LedgerId) -> SingleLedgerRequest {
+pub fn single(ledger_sequence: LedgerSequence) -> SingleLedgerRequest {
     SingleLedgerRequest { ledger_sequence }
 }
 
@@ -21,7 +21,7 @@ pub fn all() -> AllLedgersRequest {
 /// Request a single ledger.
 #[derive(Debug, Clone)]
 pub struct SingleLedgerRequest {
-    ledger_sequence: LedgerId,
+    ledger_sequence: LedgerSequence,
 }
 
 /// Request all ledgers.
@@ -77,7 +77,7 @@ mod tests {
 
     #[test]
     fn test_single_ledger_request_uri() {
-        let req = single(888);
+        let req = single(LedgerSequence::new(888));
         let uri = req.uri(&host()).unwrap();
         assert!(uri
             .to_string()
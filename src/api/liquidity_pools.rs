@@ -5,7 +5,7 @@ use crate::api::assets::asset_to_string;
 use crate::error::Result;
 use crate::page::Page;
 use crate::request::{Order, PageRequest, Request, UrlPageRequestExt};
-use crate::resources;
+use crate::resources::{self, LiquidityPoolId};
 
 /// Creates a request to retrieve all liquidity pools.
 pub fn all() -> AllLiquidityPoolsRequest {
@@ -19,8 +19,8 @@ pub fn all() -> AllLiquidityPoolsRequest {
 }
 
 /// Creates a request to retrieve a single liquidity pool.
-pub fn single(id: String) -> SingleLiquidityPoolRequest {
-    SingleLiquidityPoolRequest { id }
+pub fn single(id: &LiquidityPoolId) -> SingleLiquidityPoolRequest {
+    SingleLiquidityPoolRequest { id: id.clone() }
 }
 
 /// Request all liquidity pools.
@@ -81,7 +81,7 @@ impl_page_request!(AllLiquidityPoolsRequest);
 /// Request a single liquidity pool.
 #[derive(Debug, Clone)]
 pub struct SingleLiquidityPoolRequest {
-    id: String,
+    id: LiquidityPoolId,
 }
 
 impl Request for SingleLiquidityPoolRequest {
@@ -138,14 +138,16 @@ mod tests {
 
     #[test]
     fn test_single_liquidity_pools_request_uri() {
-        let liquidity_pool_id =
-            "67260c4c1807b262ff851b0a3fe141194936bb0215b2f77447f1df11998eabb9".to_string();
+        let liquidity_pool_id: LiquidityPoolId =
+            "67260c4c1807b262ff851b0a3fe141194936bb0215b2f77447f1df11998eabb9"
+                .parse()
+                .unwrap();
         let expected_uri = format!(
             "https://horizon.stellar.org/liquidity_pools/{}",
             liquidity_pool_id
         );
 
-        let req = single(liquidity_pool_id.clone());
+        let req = single(&liquidity_pool_id);
 
         let uri = req.uri(&host()).unwrap();
         assert_eq!(expected_uri, uri.to_string());
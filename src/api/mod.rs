@@ -5,6 +5,7 @@ pub mod assets;
 pub mod claimable_balances;
 pub mod data;
 pub mod effects;
+pub mod health;
 pub mod ledgers;
 pub mod liquidity_pools;
 pub mod offers;
@@ -1,7 +1,7 @@
 use crate::api::assets::asset_to_string;
 use crate::error::Result;
 use crate::page::Page;
-use crate::request::{Order, PageRequest, Request, UrlPageRequestExt};
+use crate::request::{Order, PageRequest, Request, StreamRequest, UrlPageRequestExt};
 use crate::resources::{self, OfferId};
 use stellar_base::asset::Asset;
 use stellar_base::crypto::PublicKey;
@@ -103,6 +103,10 @@ impl Request for AllOffersRequest {
 
 impl_page_request!(AllOffersRequest);
 
+impl StreamRequest for AllOffersRequest {
+    type Resource = resources::Offer;
+}
+
 impl Request for SingleOfferRequest {
     type Response = resources::Offer;
 
@@ -122,6 +126,10 @@ impl Request for OffersForAccountRequest {
     }
 }
 
+impl StreamRequest for OffersForAccountRequest {
+    type Resource = resources::Offer;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -191,7 +199,7 @@ mod tests {
 
     #[test]
     fn test_single_offer_request_uri() {
-        let req = single(123);
+        let req = single(OfferId::new(123).unwrap());
         let uri = req.uri(&host()).unwrap();
         assert!(uri
             .to_string()
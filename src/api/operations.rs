@@ -2,7 +2,9 @@ use crate::api::Join;
 use crate::error::Result;
 use crate::page::Page;
 use crate::request::{Order, PageRequest, Request, StreamRequest, UrlPageRequestExt};
-use crate::resources::{self, ClaimableBalanceId, LedgerId};
+use crate::resources::{
+    self, ClaimableBalanceId, LedgerSequence, LiquidityPoolId, OperationId, TransactionHash,
+};
 use stellar_base::PublicKey;
 use url::Url;
 
@@ -12,12 +14,9 @@ pub fn all() -> AllOperationsRequest {
 }
 
 /// Creates a request to retrieve a single operation.
-pub fn single<S>(operation_id: S) -> SingleOperationRequest
-where
-    S: Into<String>,
-{
+pub fn single(operation_id: OperationId) -> SingleOperationRequest {
     SingleOperationRequest {
-        operation_id: operation_id.into(),
+        operation_id,
         join: None,
     }
 }
@@ -35,7 +34,7 @@ pub fn for_account(account: &PublicKey) -> OperationsForAccountRequest {
 }
 
 /// Creates a request to retrieve a ledger's operations.
-pub fn for_ledger(ledger: LedgerId) -> OperationsForLedgerRequest {
+pub fn for_ledger(ledger: LedgerSequence) -> OperationsForLedgerRequest {
     OperationsForLedgerRequest {
         ledger,
         include_failed: None,
@@ -47,12 +46,9 @@ pub fn for_ledger(ledger: LedgerId) -> OperationsForLedgerRequest {
 }
 
 /// Creates a request to retrieve a transaction's operations.
-pub fn for_transaction<S>(tx_id: S) -> OperationsForTransactionRequest
-where
-    S: Into<String>,
-{
+pub fn for_transaction(tx_hash: &TransactionHash) -> OperationsForTransactionRequest {
     OperationsForTransactionRequest {
-        tx_id: tx_id.into(),
+        tx_hash: tx_hash.clone(),
         include_failed: None,
         join: None,
         limit: None,
@@ -62,12 +58,11 @@ where
 }
 
 /// Creates a request to retrieve a operations related to a claimable balance.
-pub fn for_claimbable_balance<S>(claimable_balance_id: S) -> OperationsForClaimableBalanceRequest
-where
-    S: Into<String>,
-{
+pub fn for_claimbable_balance(
+    claimable_balance_id: &ClaimableBalanceId,
+) -> OperationsForClaimableBalanceRequest {
     OperationsForClaimableBalanceRequest {
-        claimable_balance_id: claimable_balance_id.into(),
+        claimable_balance_id: claimable_balance_id.clone(),
         include_failed: None,
         join: None,
         limit: None,
@@ -77,12 +72,11 @@ where
 }
 
 /// Creates a request to retrieve the operations associated with a liquidity pool.
-pub fn for_liquidity_pool<S>(liquidity_pool_id: S) -> OperationsForLiquidityPoolRequest
-where
-    S: Into<String>,
-{
+pub fn for_liquidity_pool(
+    liquidity_pool_id: &LiquidityPoolId,
+) -> OperationsForLiquidityPoolRequest {
     OperationsForLiquidityPoolRequest {
-        liquidity_pool_id: liquidity_pool_id.into(),
+        liquidity_pool_id: liquidity_pool_id.clone(),
         include_failed: None,
         join: None,
         limit: None,
@@ -128,7 +122,7 @@ pub struct AllOperationsRequest {
 /// Request a single operation.
 #[derive(Debug, Clone)]
 pub struct SingleOperationRequest {
-    operation_id: String,
+    operation_id: OperationId,
     join: Option<Join>,
 }
 
@@ -146,7 +140,7 @@ pub struct OperationsForAccountRequest {
 /// Request a ledger operations.
 #[derive(Debug, Clone)]
 pub struct OperationsForLedgerRequest {
-    ledger: LedgerId,
+    ledger: LedgerSequence,
     include_failed: Option<bool>,
     join: Option<Join>,
     limit: Option<u64>,
@@ -157,7 +151,7 @@ pub struct OperationsForLedgerRequest {
 /// Request a transaction operations.
 #[derive(Debug, Clone)]
 pub struct OperationsForTransactionRequest {
-    tx_id: String,
+    tx_hash: TransactionHash,
     include_failed: Option<bool>,
     join: Option<Join>,
     limit: Option<u64>,
@@ -179,7 +173,7 @@ pub struct OperationsForClaimableBalanceRequest {
 /// Request operations associated with a liquidity pool.
 #[derive(Debug, Clone)]
 pub struct OperationsForLiquidityPoolRequest {
-    liquidity_pool_id: String,
+    liquidity_pool_id: LiquidityPoolId,
     include_failed: Option<bool>,
     join: Option<Join>,
     limit: Option<u64>,
@@ -252,7 +246,7 @@ impl Request for OperationsForTransactionRequest {
     type Response = Page<resources::Operation>;
 
     fn uri(&self, host: &Url) -> Result<Url> {
-        let mut url = host.join(&format!("transactions/{}/operations", self.tx_id))?;
+        let mut url = host.join(&format!("transactions/{}/operations", self.tx_hash))?;
         url = url.append_include_failed(&self.include_failed);
         url = url.appen_join(&self.join);
         Ok(url.append_pagination_params(self))
@@ -261,6 +255,10 @@ impl Request for OperationsForTransactionRequest {
 
 impl_page_request!(OperationsForTransactionRequest);
 
+impl StreamRequest for OperationsForTransactionRequest {
+    type Resource = resources::Operation;
+}
+
 impl OperationsForClaimableBalanceRequest {
     impl_include_failed!();
     impl_join!();
@@ -343,7 +341,7 @@ mod tests {
 
     #[test]
     fn test_single_operation_request_uri() {
-        let req = single("8181").with_join(Join::Transactions);
+        let req = single(OperationId::new(8181).unwrap()).with_join(Join::Transactions);
         let uri = req.uri(&host()).unwrap();
         assert!(uri
             .to_string()
@@ -369,7 +367,7 @@ mod tests {
 
     #[test]
     fn test_operation_for_ledger_request_uri() {
-        let req = for_ledger(888)
+        let req = for_ledger(LedgerSequence::new(888))
             .with_include_failed(true)
             .with_join(Join::Transactions);
         let uri = req.uri(&host()).unwrap();
@@ -380,10 +378,12 @@ mod tests {
 
     #[test]
     fn test_operation_for_transaction_request_uri() {
-        let req =
-            for_transaction("715ffb63673a4ee9b84d4b60924b3e141b34fe3777697f35bad6d4b990524ca2")
-                .with_include_failed(true)
-                .with_join(Join::Transactions);
+        let tx_hash = "715ffb63673a4ee9b84d4b60924b3e141b34fe3777697f35bad6d4b990524ca2"
+            .parse()
+            .unwrap();
+        let req = for_transaction(&tx_hash)
+            .with_include_failed(true)
+            .with_join(Join::Transactions);
         let uri = req.uri(&host()).unwrap();
         assert!(uri
             .to_string()
@@ -392,11 +392,13 @@ mod tests {
 
     #[test]
     fn test_operation_for_claimable_balance_request_uri() {
-        let req = for_claimbable_balance(
-            "00000000178826fbfe339e1f5c53417c6fedfe2c05e8bec14303143ec46b38981b09c3f9",
-        )
-        .with_include_failed(true)
-        .with_join(Join::Transactions);
+        let claimable_balance_id =
+            "00000000178826fbfe339e1f5c53417c6fedfe2c05e8bec14303143ec46b38981b09c3f9"
+                .parse()
+                .unwrap();
+        let req = for_claimbable_balance(&claimable_balance_id)
+            .with_include_failed(true)
+            .with_join(Join::Transactions);
         let uri = req.uri(&host()).unwrap();
         assert!(uri
             .to_string()
@@ -406,9 +408,11 @@ mod tests {
     #[test]
     fn test_operation_for_liquidity_pool_request_uri() {
         let expected_uri = "https://horizon.stellar.org/liquidity_pools/6d30e1f5721962d8bad07d90c606a3963ddbe23c8751cdbdc87224d188f4593c/operations?";
-        let liquidity_pool_id = "6d30e1f5721962d8bad07d90c606a3963ddbe23c8751cdbdc87224d188f4593c";
+        let liquidity_pool_id = "6d30e1f5721962d8bad07d90c606a3963ddbe23c8751cdbdc87224d188f4593c"
+            .parse()
+            .unwrap();
 
-        let req = for_liquidity_pool(liquidity_pool_id);
+        let req = for_liquidity_pool(&liquidity_pool_id);
         let uri = req.uri(&host()).unwrap();
         assert_eq!(expected_uri, uri.as_str());
     }
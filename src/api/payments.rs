@@ -2,7 +2,7 @@ use crate::api::Join;
 use crate::error::Result;
 use crate::page::Page;
 use crate::request::{Order, PageRequest, Request, StreamRequest, UrlPageRequestExt};
-use crate::resources::{self, LedgerId};
+use crate::resources::{self, LedgerSequence, TransactionHash};
 use stellar_base::PublicKey;
 use url::Url;
 
@@ -24,7 +24,7 @@ pub fn for_account(account: &PublicKey) -> PaymentsForAccountRequest {
 }
 
 /// Creates a request to retrieve a ledger's payments.
-pub fn for_ledger(ledger_id: LedgerId) -> PaymentsForLedgerRequest {
+pub fn for_ledger(ledger_id: LedgerSequence) -> PaymentsForLedgerRequest {
     PaymentsForLedgerRequest {
         ledger_id,
         include_failed: None,
@@ -36,12 +36,9 @@ pub fn for_ledger(ledger_id: LedgerId) -> PaymentsForLedgerRequest {
 }
 
 /// Creates a request to retrieve a transaction's payments.
-pub fn for_transaction<S>(tx_hash: S) -> PaymentsForTransactionRequest
-where
-    S: Into<String>,
-{
+pub fn for_transaction(tx_hash: &TransactionHash) -> PaymentsForTransactionRequest {
     PaymentsForTransactionRequest {
-        tx_hash: tx_hash.into(),
+        tx_hash: tx_hash.clone(),
         include_failed: None,
         join: None,
         limit: None,
@@ -94,7 +91,7 @@ pub struct PaymentsForAccountRequest {
 /// Request payments for a ledger.
 #[derive(Debug, Clone)]
 pub struct PaymentsForLedgerRequest {
-    ledger_id: LedgerId,
+    ledger_id: LedgerSequence,
     include_failed: Option<bool>,
     join: Option<Join>,
     limit: Option<u64>,
@@ -105,7 +102,7 @@ pub struct PaymentsForLedgerRequest {
 /// Request payments for a transaction.
 #[derive(Debug, Clone)]
 pub struct PaymentsForTransactionRequest {
-    tx_hash: String,
+    tx_hash: TransactionHash,
     include_failed: Option<bool>,
     join: Option<Join>,
     limit: Option<u64>,
@@ -160,6 +157,10 @@ impl Request for PaymentsForLedgerRequest {
 
 impl_page_request!(PaymentsForLedgerRequest);
 
+impl StreamRequest for PaymentsForLedgerRequest {
+    type Resource = resources::Payment;
+}
+
 impl Request for PaymentsForTransactionRequest {
     type Response = Page<resources::Payment>;
 
@@ -216,7 +217,7 @@ mod tests {
 
     #[test]
     fn test_payments_for_ledger_request_uri() {
-        let req = for_ledger(888)
+        let req = for_ledger(LedgerSequence::new(888))
             .with_include_failed(true)
             .with_join(Join::Transactions);
         let uri = req.uri(&host()).unwrap();
@@ -227,10 +228,12 @@ mod tests {
 
     #[test]
     fn test_payments_for_transaction_request_uri() {
-        let req =
-            for_transaction("715ffb63673a4ee9b84d4b60924b3e141b34fe3777697f35bad6d4b990524ca2")
-                .with_include_failed(true)
-                .with_join(Join::Transactions);
+        let tx_hash = "715ffb63673a4ee9b84d4b60924b3e141b34fe3777697f35bad6d4b990524ca2"
+            .parse()
+            .unwrap();
+        let req = for_transaction(&tx_hash)
+            .with_include_failed(true)
+            .with_join(Join::Transactions);
         let uri = req.uri(&host()).unwrap();
         assert!(uri
             .to_string()
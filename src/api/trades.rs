@@ -1,7 +1,7 @@
 use crate::error::Result;
 use crate::page::Page;
 use crate::request::{Order, PageRequest, Request, StreamRequest, UrlPageRequestExt};
-use crate::resources::{self, OfferId};
+use crate::resources::{self, LiquidityPoolId, OfferId};
 use stellar_base::{Asset, PublicKey};
 use url::Url;
 
@@ -38,9 +38,9 @@ pub fn for_offer(offer_id: OfferId) -> TradesForOfferRequest {
 }
 
 /// Creates a request to retrieve all trades associated with a liquidity pool.
-pub fn for_liquidity_pool<S: Into<String>>(liquidity_pool_id: S) -> TradesForLiquidityPoolRequest {
+pub fn for_liquidity_pool(liquidity_pool_id: &LiquidityPoolId) -> TradesForLiquidityPoolRequest {
     TradesForLiquidityPoolRequest {
-        liquidity_pool_id: liquidity_pool_id.into(),
+        liquidity_pool_id: liquidity_pool_id.clone(),
         limit: None,
         cursor: None,
         order: None,
@@ -79,7 +79,7 @@ pub struct TradesForOfferRequest {
 /// Request trades associated with a liquidity pool.
 #[derive(Debug, Clone)]
 pub struct TradesForLiquidityPoolRequest {
-    liquidity_pool_id: String,
+    liquidity_pool_id: LiquidityPoolId,
     limit: Option<u64>,
     cursor: Option<String>,
     order: Option<Order>,
@@ -200,7 +200,7 @@ mod tests {
     #[test]
     fn test_all_trades_request_uri() {
         let req = all()
-            .with_offer_id(123)
+            .with_offer_id(OfferId::new(123).unwrap())
             .with_base_asset(Asset::new_native())
             .with_counter_asset(credit_asset0());
         let uri = req.uri(&host()).unwrap();
@@ -226,7 +226,7 @@ mod tests {
 
     #[test]
     fn test_trades_for_offer_request_uri() {
-        let req = for_offer(888);
+        let req = for_offer(OfferId::new(888).unwrap());
         let uri = req.uri(&host()).unwrap();
         assert!(uri
             .to_string()
@@ -235,10 +235,12 @@ mod tests {
 
     #[test]
     fn test_trades_for_liquidity_pool_request_uri() {
-        let liquidity_pool_id = "0016ed5f76feb9f407a3676be3c96448c44e61298e8e5ba0f23011350212fc16";
+        let liquidity_pool_id = "0016ed5f76feb9f407a3676be3c96448c44e61298e8e5ba0f23011350212fc16"
+            .parse()
+            .unwrap();
         let expected_uri = "https://horizon.stellar.org/liquidity_pools/0016ed5f76feb9f407a3676be3c96448c44e61298e8e5ba0f23011350212fc16/trades?";
 
-        let req = for_liquidity_pool(liquidity_pool_id);
+        let req = for_liquidity_pool(&liquidity_pool_id);
         let uri = req.uri(&host()).unwrap();
         assert_eq!(expected_uri, uri.as_str());
     }
@@ -1,7 +1,9 @@
 use crate::error::Result;
 use crate::page::Page;
 use crate::request::{Order, PageRequest, Request, StreamRequest, UrlPageRequestExt};
-use crate::resources::{self, ClaimableBalanceId, LedgerId};
+use crate::resources::{
+    self, ClaimableBalanceId, LedgerSequence, LiquidityPoolId, TransactionHash,
+};
 use stellar_base::crypto::PublicKey;
 use stellar_base::transaction::TransactionEnvelope;
 use stellar_base::xdr::XDRSerialize;
@@ -18,8 +20,8 @@ pub fn all() -> AllTransactionsRequest {
 }
 
 /// Creates a request to retrieve a single transaction.
-pub fn single<S: Into<String>>(id: S) -> SingleTransactionRequest {
-    SingleTransactionRequest { id: id.into() }
+pub fn single(id: &TransactionHash) -> SingleTransactionRequest {
+    SingleTransactionRequest { id: id.clone() }
 }
 
 /// Creates a request to submit a transaction.
@@ -40,7 +42,7 @@ pub fn for_account(account: &PublicKey) -> TransactionsForAccountRequest {
 }
 
 /// Creates a request to retrieve a ledger's transactions.
-pub fn for_ledger(ledger: LedgerId) -> TransactionsForLedgerRequest {
+pub fn for_ledger(ledger: LedgerSequence) -> TransactionsForLedgerRequest {
     TransactionsForLedgerRequest {
         ledger,
         include_failed: None,
@@ -50,11 +52,12 @@ pub fn for_ledger(ledger: LedgerId) -> TransactionsForLedgerRequest {
     }
 }
 
-pub fn for_claimable_balance<S: Into<String>>(
-    claimable_balance_id: S,
+/// Creates a request to retrieve a transactions linked to a claimable balance.
+pub fn for_claimable_balance(
+    claimable_balance_id: &ClaimableBalanceId,
 ) -> TransactionsForClaimableBalanceRequest {
     TransactionsForClaimableBalanceRequest {
-        claimable_balance_id: claimable_balance_id.into(),
+        claimable_balance_id: claimable_balance_id.clone(),
         include_failed: None,
         limit: None,
         cursor: None,
@@ -63,11 +66,11 @@ pub fn for_claimable_balance<S: Into<String>>(
 }
 
 /// Creates a request to retrieve a transactions linked to liquidity pool.
-pub fn for_liquidity_pool<S: Into<String>>(
-    liquidity_pool_id: S,
+pub fn for_liquidity_pool(
+    liquidity_pool_id: &LiquidityPoolId,
 ) -> TransactionsForLiquidityPoolRequest {
     TransactionsForLiquidityPoolRequest {
-        liquidity_pool_id: liquidity_pool_id.into(),
+        liquidity_pool_id: liquidity_pool_id.clone(),
         include_failed: None,
         limit: None,
         cursor: None,
@@ -87,7 +90,7 @@ pub struct AllTransactionsRequest {
 /// Request a single transaction.
 #[derive(Debug, Clone)]
 pub struct SingleTransactionRequest {
-    id: String,
+    id: TransactionHash,
 }
 
 /// Submit a transaction.
@@ -110,7 +113,7 @@ pub struct TransactionsForAccountRequest {
 #[derive(Debug, Clone)]
 pub struct TransactionsForLedgerRequest {
     include_failed: Option<bool>,
-    ledger: LedgerId,
+    ledger: LedgerSequence,
     limit: Option<u64>,
     cursor: Option<String>,
     order: Option<Order>,
@@ -129,7 +132,7 @@ pub struct TransactionsForClaimableBalanceRequest {
 /// Request transaction linked to a liquidity pool.
 #[derive(Debug, Clone)]
 pub struct TransactionsForLiquidityPoolRequest {
-    liquidity_pool_id: String,
+    liquidity_pool_id: LiquidityPoolId,
     include_failed: Option<bool>,
     limit: Option<u64>,
     cursor: Option<String>,
@@ -291,7 +294,10 @@ mod tests {
 
     #[test]
     fn test_single_transaction_request_uri() {
-        let req = single("23bf920c4a000b78268589df224c1ba4c883a905687f5a5b3bdba721ee1f481e");
+        let tx_hash = "23bf920c4a000b78268589df224c1ba4c883a905687f5a5b3bdba721ee1f481e"
+            .parse()
+            .unwrap();
+        let req = single(&tx_hash);
         let uri = req.uri(&host()).unwrap();
         assert!(uri
             .to_string()
@@ -312,7 +318,7 @@ mod tests {
 
     #[test]
     fn test_transactions_for_ledger_request_uri() {
-        let req = for_ledger(888).with_include_failed(true);
+        let req = for_ledger(LedgerSequence::new(888)).with_include_failed(true);
         let uri = req.uri(&host()).unwrap();
         assert!(uri
             .to_string()
@@ -322,8 +328,10 @@ mod tests {
     #[test]
     fn test_transactions_for_claimable_balance_request_uri() {
         let claimable_balance_id =
-            "00000000178826fbfe339e1f5c53417c6fedfe2c05e8bec14303143ec46b38981b09c3f9";
-        let req = for_claimable_balance(claimable_balance_id).with_include_failed(true);
+            "00000000178826fbfe339e1f5c53417c6fedfe2c05e8bec14303143ec46b38981b09c3f9"
+                .parse()
+                .unwrap();
+        let req = for_claimable_balance(&claimable_balance_id).with_include_failed(true);
         let uri = req.uri(&host()).unwrap();
         assert!(uri
             .to_string()
@@ -334,8 +342,10 @@ mod tests {
     fn test_transactions_for_liquidity_pool_request_uri() {
         let expected_uri = "https://horizon.stellar.org/liquidity_pools/006881bb9a17b0c0f4000cb12eaeb2b954390707b03a676b87f824dc6af9f207/transactions?";
 
-        let req =
-            for_liquidity_pool("006881bb9a17b0c0f4000cb12eaeb2b954390707b03a676b87f824dc6af9f207");
+        let liquidity_pool_id = "006881bb9a17b0c0f4000cb12eaeb2b954390707b03a676b87f824dc6af9f207"
+            .parse()
+            .unwrap();
+        let req = for_liquidity_pool(&liquidity_pool_id);
         let uri = req.uri(&host()).unwrap();
 
         assert_eq!(expected_uri, uri.as_str());
@@ -0,0 +1,193 @@
+//! Dynamic authentication headers.
+//!
+//! Gateways in front of horizon often use short lived tokens, or sign
+//! each request. A `HeaderProvider` returns the headers of each request,
+//! including stream reconnections, and can refresh its credentials when
+//! horizon answers with `401 Unauthorized`.
+//!
+//! ```rust
+//! use stellar_horizon::auth::HeaderProvider;
+//! use stellar_horizon::client::HorizonHttpClient;
+//! use stellar_horizon::error::Result;
+//! use futures::future::BoxFuture;
+//! use hyper::{HeaderMap, Method};
+//! use std::sync::RwLock;
+//! use url::Url;
+//!
+//! struct GatewayToken {
+//!     token: RwLock<String>,
+//! }
+//!
+//! impl HeaderProvider for GatewayToken {
+//!     fn headers<'a>(
+//!         &'a self,
+//!         _method: &'a Method,
+//!         _uri: &'a Url,
+//!     ) -> BoxFuture<'a, Result<HeaderMap>> {
+//!         Box::pin(async move {
+//!             let mut headers = HeaderMap::new();
+//!             let token = format!("Bearer {}", self.token.read().unwrap());
+//!             headers.insert("Authorization", token.parse().unwrap());
+//!             Ok(headers)
+//!         })
+//!     }
+//!
+//!     fn refresh(&self) -> BoxFuture<'_, Result<bool>> {
+//!         Box::pin(async move {
+//!             *self.token.write().unwrap() = "a new token".to_string();
+//!             Ok(true)
+//!         })
+//!     }
+//! }
+//!
+//! # fn run() -> stellar_horizon::error::Result<()> {
+//! let token = GatewayToken {
+//!     token: RwLock::new("a token".to_string()),
+//! };
+//! let client = HorizonHttpClient::builder("https://horizon.example.org")
+//!     .with_header_provider(token)
+//!     .build()?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::error::Result;
+use futures::future::{self, BoxFuture};
+use std::fmt;
+use url::Url;
+
+/// Provides the headers of each request sent by a `HorizonHttpClient`.
+pub trait HeaderProvider: Send + Sync {
+    /// Returns the headers of a request to `uri`.
+    ///
+    /// The headers replace the client headers with the same name.
+    fn headers<'a>(
+        &'a self,
+        method: &'a hyper::Method,
+        uri: &'a Url,
+    ) -> BoxFuture<'a, Result<hyper::HeaderMap>>;
+
+    /// Refreshes the credentials after horizon answered a request with
+    /// `401 Unauthorized`.
+    ///
+    /// Returns true to send the request again, once, with new headers.
+    /// The default implementation returns false.
+    fn refresh(&self) -> BoxFuture<'_, Result<bool>> {
+        Box::pin(future::ready(Ok(false)))
+    }
+}
+
+impl fmt::Debug for dyn HeaderProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HeaderProvider")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api;
+    use crate::client::{HorizonClient, HorizonHttpClient};
+    use crate::error::Error;
+    use crate::mock::{ledger_json, MockServer};
+    use futures::stream::StreamExt;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // Signs requests with the method, path and a token version.
+    struct Signer {
+        version: AtomicU32,
+        refresh: bool,
+    }
+
+    impl HeaderProvider for Signer {
+        fn headers<'a>(
+            &'a self,
+            method: &'a hyper::Method,
+            uri: &'a Url,
+        ) -> BoxFuture<'a, Result<hyper::HeaderMap>> {
+            Box::pin(async move {
+                let version = self.version.load(Ordering::SeqCst);
+                let signature = format!("{} {} v{}", method, uri.path(), version);
+                let mut headers = hyper::HeaderMap::new();
+                headers.insert("x-signature", signature.parse().unwrap());
+                Ok(headers)
+            })
+        }
+
+        fn refresh(&self) -> BoxFuture<'_, Result<bool>> {
+            Box::pin(async move {
+                self.version.fetch_add(1, Ordering::SeqCst);
+                Ok(self.refresh)
+            })
+        }
+    }
+
+    fn client(server: &MockServer, refresh: bool) -> HorizonHttpClient {
+        HorizonHttpClient::builder(server.url().as_str())
+            .with_header_provider(Signer {
+                version: AtomicU32::new(1),
+                refresh,
+            })
+            .build()
+            .unwrap()
+    }
+
+    // Only accepts requests signed with the second token version.
+    async fn gateway() -> MockServer {
+        MockServer::start(|request| {
+            if request.headers["x-signature"].ends_with("v2") {
+                (200, ledger_json(100, 0))
+            } else {
+                let error = json!({
+                    "type": "https://stellar.org/horizon-errors/unauthorized",
+                    "title": "Unauthorized",
+                    "detail": "The token has expired.",
+                    "status": 401,
+                });
+                (401, error)
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_refresh_after_unauthorized() {
+        let server = gateway().await;
+        let client = client(&server, true);
+        let (_, ledger) = client
+            .request(api::ledgers::single(100.into()))
+            .await
+            .unwrap();
+        assert_eq!(100, ledger.sequence.value());
+        let signatures: Vec<_> = server
+            .requests()
+            .iter()
+            .map(|request| request.headers["x-signature"].clone())
+            .collect();
+        assert_eq!(
+            vec!["GET /ledgers/100 v1", "GET /ledgers/100 v2"],
+            signatures
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized_without_refresh() {
+        let server = gateway().await;
+        let client = client(&server, false);
+        let result = client.request(api::ledgers::single(100.into())).await;
+        assert!(matches!(result, Err(Error::HorizonRequestError(_))));
+        assert_eq!(1, server.requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_stream_headers() {
+        let server = gateway().await;
+        let client = client(&server, false);
+        let mut stream = client.stream(api::ledgers::all()).unwrap();
+        let result = stream.next().await.unwrap();
+        assert!(matches!(result, Err(Error::HorizonRequestError(_))));
+        let request = &server.requests()[0];
+        assert_eq!("GET /ledgers v1", request.headers["x-signature"]);
+        assert_eq!("text/event-stream", request.headers["accept"]);
+    }
+}
@@ -0,0 +1,399 @@
+//! Parallel historical backfill.
+//!
+//! Paging through months of history one page at a time is slow. A
+//! `Backfill` splits a range of ledgers into windows and fetches the
+//! records of each window concurrently, while still returning them
+//! in paging token order.
+//!
+//! The backfill works with any request whose records have a TOID
+//! paging token: ledgers, transactions, operations, payments and
+//! effects.
+//!
+//! ```rust
+//! use stellar_horizon::api;
+//! use stellar_horizon::backfill::Backfill;
+//! use stellar_horizon::client::HorizonHttpClient;
+//! use stellar_horizon::cursor::FileCursorStore;
+//! use stellar_horizon::request::PageRequest;
+//! use stellar_horizon::resources::LedgerSequence;
+//! use futures::stream::TryStreamExt;
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let store = FileCursorStore::new("cursors.json");
+//! let ledgers = LedgerSequence::new(50_000_000)..LedgerSequence::new(50_100_000);
+//! let mut operations = Backfill::new(&client, api::operations::all().with_limit(200), ledgers)
+//!     .with_window_size(1_000)
+//!     .with_concurrency(8)
+//!     .with_cursor_store(&store, "operations-backfill")
+//!     .records();
+//! while let Some(operation) = operations.try_next().await? {
+//!     println!("Operation = {:?}", operation);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::client::HorizonClient;
+use crate::cursor::CursorStore;
+use crate::error::{Error, Result};
+use crate::page::Page;
+use crate::paging;
+use crate::request::{Order, PageRequest};
+use crate::resources::{LedgerSequence, Record};
+use crate::toid::{self, Toid};
+use futures::future::{self, BoxFuture, Either};
+use futures::stream::{self, BoxStream, Fuse, FuturesUnordered, StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::pin::pin;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+/// The default number of ledgers in each window.
+pub const DEFAULT_WINDOW_SIZE: u32 = 1_000;
+
+/// The default number of windows fetched concurrently.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Fetch the records in a range of ledgers concurrently.
+pub struct Backfill<'a, C, R> {
+    client: &'a C,
+    request: R,
+    ledgers: Range<LedgerSequence>,
+    window_size: u32,
+    concurrency: usize,
+    cursor_store: Option<(&'a dyn CursorStore, String)>,
+}
+
+impl<'a, C, R> Backfill<'a, C, R>
+where
+    C: HorizonClient + Sync,
+    R: PageRequest + Clone + 'a,
+{
+    /// Creates a new backfill of the records of `request` in `ledgers`.
+    ///
+    /// The request cursor and order are replaced.
+    pub fn new(client: &'a C, request: R, ledgers: Range<LedgerSequence>) -> Self {
+        Backfill {
+            client,
+            request,
+            ledgers,
+            window_size: DEFAULT_WINDOW_SIZE,
+            concurrency: DEFAULT_CONCURRENCY,
+            cursor_store: None,
+        }
+    }
+
+    /// Sets the number of ledgers in each window.
+    pub fn with_window_size(mut self, window_size: u32) -> Self {
+        self.window_size = window_size.max(1);
+        self
+    }
+
+    /// Sets the maximum number of windows fetched concurrently.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Saves the backfill progress in `store` under `key`.
+    ///
+    /// The saved cursor points to the first ledger that wasn't
+    /// completely returned yet. If a cursor was saved, the backfill
+    /// resumes from it. Records of a partially consumed window are
+    /// returned again after resuming.
+    pub fn with_cursor_store(mut self, store: &'a dyn CursorStore, key: &str) -> Self {
+        self.cursor_store = Some((store, key.to_string()));
+        self
+    }
+
+    /// Returns the windows of ledgers fetched by the backfill.
+    pub fn windows(&self) -> Vec<Range<LedgerSequence>> {
+        windows(self.ledgers.clone(), self.window_size)
+    }
+
+    /// Returns a stream over the records, in paging token order.
+    pub fn records<T>(self) -> BoxStream<'a, Result<T>>
+    where
+        R: PageRequest<Response = Page<T>>,
+        T: Record + DeserializeOwned + Serialize + Clone + Send + 'a,
+    {
+        let Backfill {
+            client,
+            request,
+            ledgers,
+            window_size,
+            concurrency,
+            cursor_store,
+        } = self;
+
+        let windows = {
+            let cursor_store = cursor_store.clone();
+            async move {
+                let start = match cursor_store {
+                    None => None,
+                    Some((store, key)) => load_progress(store, &key).await?,
+                };
+                let start = start.map_or(ledgers.start, |s| s.max(ledgers.start));
+                Ok::<_, Error>(stream::iter(windows(start..ledgers.end, window_size)).map(Ok))
+            }
+        };
+        let fetches = WindowFetches {
+            client,
+            request,
+            concurrency,
+            cursor_store,
+            windows: stream::once(windows).try_flatten().boxed().fuse(),
+            started: VecDeque::new(),
+            fetches: FuturesUnordered::new(),
+        };
+
+        stream::try_unfold(fetches, next_page)
+            .map_ok(|records| stream::iter(records.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+}
+
+type WindowPages<T> = UnboundedReceiver<Result<Vec<T>>>;
+
+/// The windows fetched concurrently by a backfill.
+///
+/// Each started window sends its pages over a channel as they are
+/// fetched. The pages of the first window are returned as soon as
+/// they arrive, the pages of the following windows are buffered
+/// until their turn.
+struct WindowFetches<'a, C, R, T> {
+    client: &'a C,
+    request: R,
+    concurrency: usize,
+    cursor_store: Option<(&'a dyn CursorStore, String)>,
+    windows: Fuse<BoxStream<'a, Result<Range<LedgerSequence>>>>,
+    started: VecDeque<(Range<LedgerSequence>, WindowPages<T>)>,
+    fetches: FuturesUnordered<BoxFuture<'a, ()>>,
+}
+
+async fn next_page<'a, C, R, T>(
+    mut state: WindowFetches<'a, C, R, T>,
+) -> Result<Option<(Vec<T>, WindowFetches<'a, C, R, T>)>>
+where
+    C: HorizonClient + Sync,
+    R: PageRequest<Response = Page<T>> + Clone + 'a,
+    T: Record + DeserializeOwned + Serialize + Clone + Send + 'a,
+{
+    loop {
+        while state.started.len() < state.concurrency {
+            let window = match state.windows.try_next().await? {
+                Some(window) => window,
+                None => break,
+            };
+            let (sender, receiver) = mpsc::unbounded_channel();
+            let request = state.request.clone();
+            let fetch = fetch_window(state.client, request, window.clone(), sender);
+            state.fetches.push(Box::pin(fetch));
+            state.started.push_back((window, receiver));
+        }
+        let (window, pages) = match state.started.front_mut() {
+            Some(started) => started,
+            None => return Ok(None),
+        };
+        // Keep the other windows fetching while waiting for the first one.
+        let page = if state.fetches.is_empty() {
+            pages.recv().await
+        } else {
+            match future::select(pin!(pages.recv()), state.fetches.next()).await {
+                Either::Left((page, _)) => page,
+                Either::Right(_) => continue,
+            }
+        };
+        match page {
+            Some(records) => return Ok(Some((records?, state))),
+            None => {
+                // The window records were all consumed, save progress.
+                let end = window.end;
+                if let Some((store, key)) = &state.cursor_store {
+                    save_progress(*store, key, end).await?;
+                }
+                state.started.pop_front();
+            }
+        }
+    }
+}
+
+fn windows(ledgers: Range<LedgerSequence>, window_size: u32) -> Vec<Range<LedgerSequence>> {
+    let end = ledgers.end.value();
+    let mut windows = Vec::new();
+    let mut start = ledgers.start.value();
+    while start < end {
+        let window_end = start.saturating_add(window_size).min(end);
+        windows.push(LedgerSequence::new(start)..LedgerSequence::new(window_end));
+        start = window_end;
+    }
+    windows
+}
+
+async fn fetch_window<C, R, T>(
+    client: &C,
+    request: R,
+    window: Range<LedgerSequence>,
+    pages: UnboundedSender<Result<Vec<T>>>,
+) where
+    C: HorizonClient + Sync,
+    R: PageRequest<Response = Page<T>> + Clone,
+    T: Record + DeserializeOwned + Serialize + Clone + Send,
+{
+    if let Err(err) = send_window_pages(client, request, window, &pages).await {
+        let _ = pages.send(Err(err));
+    }
+}
+
+async fn send_window_pages<C, R, T>(
+    client: &C,
+    request: R,
+    window: Range<LedgerSequence>,
+    pages: &UnboundedSender<Result<Vec<T>>>,
+) -> Result<()>
+where
+    C: HorizonClient + Sync,
+    R: PageRequest<Response = Page<T>> + Clone,
+    T: Record + DeserializeOwned + Serialize + Clone + Send,
+{
+    let request = request
+        .with_cursor(&toid::cursor_for_ledger(window.start)?)
+        .with_order(&Order::Ascending);
+    let mut window_pages = paging::pages(client, request);
+    while let Some(page) = window_pages.try_next().await? {
+        let mut records = Vec::with_capacity(page.records.len());
+        let mut window_end = false;
+        for record in page.records {
+            let toid = Toid::from_paging_token(record.paging_token())?;
+            if toid.ledger_sequence() >= window.end {
+                window_end = true;
+                break;
+            }
+            records.push(record);
+        }
+        // Stop when the backfill was dropped.
+        if pages.send(Ok(records)).is_err() || window_end {
+            break;
+        }
+    }
+    Ok(())
+}
+
+async fn load_progress(store: &dyn CursorStore, key: &str) -> Result<Option<LedgerSequence>> {
+    match store.load(key).await? {
+        None => Ok(None),
+        Some(cursor) => {
+            // The cursor is the end of the ledger before the next ledger.
+            let toid: Toid = cursor.parse()?;
+            Ok(Some(LedgerSequence::new(
+                toid.ledger_sequence().value() + 1,
+            )))
+        }
+    }
+}
+
+async fn save_progress(store: &dyn CursorStore, key: &str, next: LedgerSequence) -> Result<()> {
+    store.save(key, &toid::cursor_for_ledger(next)?).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api;
+    use crate::cursor::MemoryCursorStore;
+    use crate::mock::{ledger_json, page_json, MockClient};
+    use futures::executor::block_on;
+
+    // Ledgers 1 to 100, three per page.
+    fn client() -> MockClient {
+        MockClient::new(|uri| {
+            let query: std::collections::HashMap<_, _> = uri.query_pairs().into_owned().collect();
+            let cursor: i64 = query.get("cursor").unwrap().parse().unwrap();
+            let first = (cursor >> 32) as u32 + 1;
+            let records = (first..(first + 3).min(101))
+                .map(|sequence| ledger_json(sequence, sequence as i64 * 5))
+                .collect();
+            Some(page_json(records))
+        })
+    }
+
+    fn sequences(ledgers: Vec<crate::resources::Ledger>) -> Vec<u32> {
+        ledgers.iter().map(|l| l.sequence.value()).collect()
+    }
+
+    #[test]
+    fn test_windows() {
+        let windows = windows(LedgerSequence::new(10)..LedgerSequence::new(25), 10);
+        assert_eq!(
+            vec![
+                LedgerSequence::new(10)..LedgerSequence::new(20),
+                LedgerSequence::new(20)..LedgerSequence::new(25),
+            ],
+            windows
+        );
+    }
+
+    #[test]
+    fn test_backfill_in_order() {
+        let client = client();
+        let ledgers = LedgerSequence::new(10)..LedgerSequence::new(50);
+        let records = Backfill::new(&client, api::ledgers::all(), ledgers)
+            .with_window_size(7)
+            .with_concurrency(3)
+            .records();
+        let records = block_on(records.try_collect()).unwrap();
+        assert_eq!((10..50).collect::<Vec<_>>(), sequences(records));
+    }
+
+    #[test]
+    fn test_backfill_returns_pages_before_window_end() {
+        // The second page of the window fails.
+        let client = MockClient::new(|uri| {
+            let query: std::collections::HashMap<_, _> = uri.query_pairs().into_owned().collect();
+            let cursor: i64 = query.get("cursor").unwrap().parse().unwrap();
+            let first = (cursor >> 32) as u32 + 1;
+            let records = (first..first + 3)
+                .map(|sequence| ledger_json(sequence, sequence as i64 * 5))
+                .collect();
+            (first < 13).then(|| page_json(records))
+        });
+        let ledgers = LedgerSequence::new(10)..LedgerSequence::new(30);
+        let records = Backfill::new(&client, api::ledgers::all(), ledgers).records();
+        let records: Vec<Result<crate::resources::Ledger>> = block_on(records.collect());
+        assert_eq!(4, records.len());
+        for (record, sequence) in records.iter().zip(10..13) {
+            assert_eq!(sequence, record.as_ref().unwrap().sequence.value());
+        }
+        assert!(matches!(records[3], Err(Error::HorizonServerError)));
+    }
+
+    #[test]
+    fn test_backfill_resume() {
+        let store = MemoryCursorStore::new();
+        let client = client();
+        let ledgers = LedgerSequence::new(10)..LedgerSequence::new(50);
+        let backfill = || {
+            Backfill::new(&client, api::ledgers::all(), ledgers.clone())
+                .with_window_size(10)
+                .with_cursor_store(&store, "ledgers")
+        };
+
+        // Stop in the middle of the second window.
+        let records: Vec<_> = block_on(backfill().records().take(15).try_collect()).unwrap();
+        assert_eq!((10..25).collect::<Vec<_>>(), sequences(records));
+        assert_eq!(
+            Some(LedgerSequence::new(20)),
+            block_on(load_progress(&store, "ledgers")).unwrap()
+        );
+
+        let records: Vec<_> = block_on(backfill().records().try_collect()).unwrap();
+        assert_eq!((20..50).collect::<Vec<_>>(), sequences(records));
+        assert_eq!(
+            Some(LedgerSequence::new(50)),
+            block_on(load_progress(&store, "ledgers")).unwrap()
+        );
+    }
+}
@@ -0,0 +1,333 @@
+//! Fetch many resources concurrently.
+//!
+//! Horizon has no batch endpoints. A `BatchFetch` sends the `single`
+//! request of each resource concurrently, up to a limit, and returns
+//! the results in input order. Each result is independent, so one
+//! missing resource doesn't fail the whole batch.
+//!
+//! When horizon reports that the rate limit quota is used up, new
+//! requests wait until the rate limit window resets. Requests rejected
+//! with `429 Too Many Requests` are sent again after the wait.
+//!
+//! ```rust
+//! use stellar_horizon::batch::BatchFetch;
+//! use stellar_horizon::client::HorizonHttpClient;
+//! use stellar_horizon::resources::LedgerSequence;
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let ledgers = LedgerSequence::new(50_000_000)..LedgerSequence::new(50_000_100);
+//! let ledgers = BatchFetch::new(&client)
+//!     .with_concurrency(16)
+//!     .ledgers(ledgers)
+//!     .await;
+//! for ledger in ledgers {
+//!     println!("Ledger = {}", ledger?.hash);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::api;
+use crate::client::HorizonClient;
+use crate::error::{Error, Result};
+use crate::headers::{self, HeaderMap};
+use crate::request::Request;
+use crate::resources::{Account, Ledger, LedgerSequence, Transaction, TransactionHash};
+use futures::stream::{self, StreamExt};
+use std::ops::Range;
+use std::sync::Mutex;
+use std::time::Duration;
+use stellar_base::PublicKey;
+use tokio::time::Instant;
+
+/// The default number of requests sent concurrently.
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+// The wait when horizon doesn't say when the rate limit window resets.
+const DEFAULT_RATE_LIMIT_WAIT: Duration = Duration::from_secs(1);
+
+// The number of times a request rejected by the rate limit is retried.
+const MAX_RATE_LIMIT_RETRIES: u32 = 5;
+
+/// Fetch many resources concurrently.
+pub struct BatchFetch<'a, C> {
+    client: &'a C,
+    concurrency: usize,
+    resume_at: Mutex<Option<Instant>>,
+}
+
+impl<'a, C> BatchFetch<'a, C>
+where
+    C: HorizonClient + Sync,
+{
+    /// Creates a new batch fetch that sends requests with `client`.
+    pub fn new(client: &'a C) -> Self {
+        BatchFetch {
+            client,
+            concurrency: DEFAULT_CONCURRENCY,
+            resume_at: Mutex::new(None),
+        }
+    }
+
+    /// Sets the number of requests sent concurrently.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Sends `requests` and returns their responses in the same order.
+    pub async fn requests<R, I>(&self, requests: I) -> Vec<Result<R::Response>>
+    where
+        R: Request,
+        I: IntoIterator<Item = R>,
+    {
+        stream::iter(requests)
+            .map(|request| self.request(request))
+            .buffered(self.concurrency)
+            .collect()
+            .await
+    }
+
+    /// Fetches `accounts`.
+    pub async fn accounts(&self, accounts: &[PublicKey]) -> Vec<Result<Account>> {
+        self.requests(accounts.iter().map(api::accounts::single))
+            .await
+    }
+
+    /// Fetches the transactions with the given `hashes`.
+    pub async fn transactions(&self, hashes: &[TransactionHash]) -> Vec<Result<Transaction>> {
+        self.requests(hashes.iter().map(api::transactions::single))
+            .await
+    }
+
+    /// Fetches the ledgers in the `ledgers` range.
+    pub async fn ledgers(&self, ledgers: Range<LedgerSequence>) -> Vec<Result<Ledger>> {
+        let sequences = ledgers.start.value()..ledgers.end.value();
+        self.requests(sequences.map(|sequence| api::ledgers::single(sequence.into())))
+            .await
+    }
+
+    async fn request<R: Request>(&self, request: R) -> Result<R::Response> {
+        let mut retries = 0;
+        loop {
+            let resume_at = *self.resume_at.lock().unwrap();
+            if let Some(resume_at) = resume_at {
+                tokio::time::sleep_until(resume_at).await;
+            }
+            let wait = match self.client.request(&request).await {
+                Ok((headers, response)) => {
+                    if let Some(wait) = rate_limit_wait(&headers) {
+                        self.resume_after(wait);
+                    }
+                    return Ok(response);
+                }
+                Err(Error::RateLimitExceeded { reset }) if retries < MAX_RATE_LIMIT_RETRIES => {
+                    reset.unwrap_or(DEFAULT_RATE_LIMIT_WAIT)
+                }
+                Err(err) => return Err(err),
+            };
+            self.resume_after(wait);
+            retries += 1;
+        }
+    }
+
+    /// Holds new requests for `wait`.
+    fn resume_after(&self, wait: Duration) {
+        let mut resume_at = self.resume_at.lock().unwrap();
+        let wait_until = Instant::now() + wait;
+        if resume_at.is_none_or(|resume_at| resume_at < wait_until) {
+            *resume_at = Some(wait_until);
+        }
+    }
+}
+
+/// Fetches `accounts` concurrently.
+pub async fn fetch_accounts<C>(client: &C, accounts: &[PublicKey]) -> Vec<Result<Account>>
+where
+    C: HorizonClient + Sync,
+{
+    BatchFetch::new(client).accounts(accounts).await
+}
+
+/// Fetches the transactions with the given `hashes` concurrently.
+pub async fn fetch_transactions<C>(
+    client: &C,
+    hashes: &[TransactionHash],
+) -> Vec<Result<Transaction>>
+where
+    C: HorizonClient + Sync,
+{
+    BatchFetch::new(client).transactions(hashes).await
+}
+
+/// Fetches the ledgers in the `ledgers` range concurrently.
+pub async fn fetch_ledgers<C>(client: &C, ledgers: Range<LedgerSequence>) -> Vec<Result<Ledger>>
+where
+    C: HorizonClient + Sync,
+{
+    BatchFetch::new(client).ledgers(ledgers).await
+}
+
+/// Returns how long to wait before the next request, if the rate limit
+/// quota is used up.
+fn rate_limit_wait(headers: &HeaderMap) -> Option<Duration> {
+    if headers::rate_limit_remaining(headers)? > 0 {
+        return None;
+    }
+    let wait = headers::rate_limit_reset(headers)
+        .map(|reset| Duration::from_secs(reset.into()))
+        .unwrap_or(DEFAULT_RATE_LIMIT_WAIT);
+    Some(wait)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CachingClient;
+    use crate::mock::{ledger_json, MockClient};
+    use crate::request::StreamRequest;
+    use futures::future::BoxFuture;
+    use futures::Stream;
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn account_json(account_id: &str) -> Value {
+        let mut account: Value =
+            serde_json::from_str(include_str!("../tests/fixtures/account.json")).unwrap();
+        account["id"] = json!(account_id);
+        account["account_id"] = json!(account_id);
+        account
+    }
+
+    fn transaction_json(hash: &str) -> Value {
+        let transactions: Value =
+            serde_json::from_str(include_str!("../tests/fixtures/all_transactions.json")).unwrap();
+        let mut transaction = transactions["_embedded"]["records"][0].clone();
+        transaction["id"] = json!(hash);
+        transaction["hash"] = json!(hash);
+        transaction
+    }
+
+    #[tokio::test]
+    async fn test_fetch_ledgers_in_order() {
+        let client = MockClient::new(|uri| {
+            let sequence: u32 = uri.path().trim_start_matches("/ledgers/").parse().unwrap();
+            // Ledger 3 is missing.
+            (sequence != 3).then(|| ledger_json(sequence, 0))
+        });
+        let ledgers = BatchFetch::new(&client)
+            .with_concurrency(2)
+            .ledgers(LedgerSequence::new(1)..LedgerSequence::new(6))
+            .await;
+        assert_eq!(5, ledgers.len());
+        for (ledger, sequence) in ledgers.iter().zip(1..) {
+            match ledger {
+                Ok(ledger) => assert_eq!(sequence, ledger.sequence.value()),
+                Err(err) => {
+                    assert_eq!(3, sequence);
+                    assert!(matches!(err, Error::HorizonServerError));
+                }
+            }
+        }
+        assert_eq!(5, client.requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_accounts() {
+        let client =
+            MockClient::new(|uri| Some(account_json(uri.path().trim_start_matches("/accounts/"))));
+        let accounts = [
+            "GA73S4WXZG7EONFCIFDSZ6VOJKFC2PMV5574YDJC4V4UBDGPAYN4SPAC",
+            "GA2T66AMBWHNR7S23MMQ4RR5KIPITETFOUC6DATE74FIFGRYKQIW7RWV",
+        ]
+        .map(|account| PublicKey::from_account_id(account).unwrap());
+        let fetched = fetch_accounts(&client, &accounts).await;
+        for (account, fetched) in accounts.iter().zip(fetched) {
+            assert_eq!(account.account_id(), fetched.unwrap().account_id);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_transactions() {
+        let client = MockClient::new(|uri| {
+            Some(transaction_json(
+                uri.path().trim_start_matches("/transactions/"),
+            ))
+        });
+        let hashes: Vec<TransactionHash> = (1..=3)
+            .map(|n| format!("{:064x}", n).parse().unwrap())
+            .collect();
+        let transactions = fetch_transactions(&client, &hashes).await;
+        for (hash, transaction) in hashes.iter().zip(transactions) {
+            assert_eq!(hash, &transaction.unwrap().hash);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_through_cache() {
+        let client = CachingClient::new(MockClient::new(|uri| {
+            let sequence: u32 = uri.path().trim_start_matches("/ledgers/").parse().unwrap();
+            Some(ledger_json(sequence, 0))
+        }));
+        for _ in 0..2 {
+            let ledgers =
+                fetch_ledgers(&client, LedgerSequence::new(1)..LedgerSequence::new(4)).await;
+            assert!(ledgers.iter().all(Result::is_ok));
+        }
+        assert_eq!(3, client.inner().requests().len());
+        assert_eq!(3, client.len());
+    }
+
+    /// Rejects the first request with a rate limit error.
+    struct RateLimitedClient {
+        inner: MockClient,
+        attempts: AtomicU32,
+    }
+
+    impl HorizonClient for RateLimitedClient {
+        fn request<'a, R: Request + 'a>(
+            &'a self,
+            req: R,
+        ) -> BoxFuture<'a, Result<(HeaderMap, R::Response)>> {
+            if self.attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                let reset = Some(Duration::from_millis(100));
+                return Box::pin(async move { Err(Error::RateLimitExceeded { reset }) });
+            }
+            self.inner.request(req)
+        }
+
+        fn stream<'a, R: StreamRequest + 'static>(
+            &'a self,
+            req: R,
+        ) -> Result<Box<dyn Stream<Item = Result<R::Resource>> + 'static + Send + Unpin>> {
+            self.inner.stream(req)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_too_many_requests() {
+        let client = RateLimitedClient {
+            inner: MockClient::new(|_| Some(ledger_json(1, 0))),
+            attempts: AtomicU32::new(0),
+        };
+        let start = Instant::now();
+        let ledgers = BatchFetch::new(&client)
+            .ledgers(LedgerSequence::new(1)..LedgerSequence::new(2))
+            .await;
+        assert_eq!(1, ledgers[0].as_ref().unwrap().sequence.value());
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(2, client.attempts.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_rate_limit_wait() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(None, rate_limit_wait(&headers));
+        headers.insert("X-Ratelimit-Remaining", "3".parse().unwrap());
+        assert_eq!(None, rate_limit_wait(&headers));
+        headers.insert("X-Ratelimit-Remaining", "0".parse().unwrap());
+        assert_eq!(Some(DEFAULT_RATE_LIMIT_WAIT), rate_limit_wait(&headers));
+        headers.insert("X-Ratelimit-Reset", "12".parse().unwrap());
+        assert_eq!(Some(Duration::from_secs(12)), rate_limit_wait(&headers));
+    }
+}
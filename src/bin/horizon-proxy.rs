@@ -0,0 +1,498 @@
+//! Caching reverse proxy for Horizon.
+//!
+//! Serves the Horizon REST API by forwarding GET requests for Horizon
+//! routes with a `HorizonHttpClient`. Immutable resources, like ledgers
+//! and transactions by hash, and closed pages of history are cached in
+//! memory by a `CachingClient`. Each client is rate limited by ip
+//! address, and streams are relayed through a `StreamHub`, so that
+//! clients streaming the same resource share one upstream connection.
+//!
+//! Links in responses point to the upstream server, unless the url the
+//! proxy is reached at is set with `--public-url`.
+//!
+//! Requires the `proxy` feature.
+use bytes::Bytes;
+use clap::Parser;
+use futures::future;
+use futures::stream::StreamExt;
+use http_body_util::{combinators::UnsyncBoxBody, BodyExt, Full, StreamBody};
+use hyper::body::{Frame, Incoming};
+use hyper::header::{self, HeaderValue};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use stellar_horizon::cache::{CachePolicy, CachingClient};
+use stellar_horizon::client::HorizonHttpClient;
+use stellar_horizon::error::{Error, Result};
+use stellar_horizon::horizon_error::HorizonError;
+use stellar_horizon::hub::StreamHub;
+use stellar_horizon::request::{Request, StreamRequest};
+use stellar_horizon::stream::StreamEvent;
+use tokio::net::TcpListener;
+use url::Url;
+
+/// Caching reverse proxy for Horizon.
+#[derive(Parser)]
+#[command(name = "horizon-proxy", version)]
+struct Args {
+    /// The upstream Horizon server url.
+    #[arg(
+        long,
+        env = "HORIZON_URL",
+        default_value = "https://horizon.stellar.org"
+    )]
+    horizon: String,
+    /// The address to listen on.
+    #[arg(long, default_value = "127.0.0.1:8000")]
+    listen: SocketAddr,
+    /// The url clients reach the proxy at, used in response links.
+    #[arg(long)]
+    public_url: Option<Url>,
+    /// The maximum number of cached responses.
+    #[arg(long, default_value_t = 10_000)]
+    cache_size: usize,
+    /// The requests per second allowed for each client.
+    #[arg(long, default_value_t = 20.0)]
+    rate: f64,
+    /// The requests a client can send at once.
+    #[arg(long, default_value_t = 40.0)]
+    burst: f64,
+}
+
+type Body = UnsyncBoxBody<Bytes, Infallible>;
+
+// The first path segment of Horizon routes.
+const ROUTES: &[&str] = &[
+    "accounts",
+    "assets",
+    "claimable_balances",
+    "effects",
+    "fee_stats",
+    "health",
+    "ledgers",
+    "liquidity_pools",
+    "offers",
+    "operations",
+    "order_book",
+    "paths",
+    "payments",
+    "trade_aggregations",
+    "trades",
+    "transactions",
+];
+
+// Resources whose pages only grow at the end.
+const HISTORY: &[&str] = &[
+    "ledgers",
+    "transactions",
+    "operations",
+    "payments",
+    "effects",
+    "trades",
+];
+
+/// A request for a Horizon route, answered with the response json.
+#[derive(Debug, Clone)]
+struct RawRequest {
+    path: String,
+    query: Option<String>,
+}
+
+impl Request for RawRequest {
+    type Response = Value;
+
+    fn uri(&self, host: &Url) -> Result<Url> {
+        // Paths are appended to the host path, and can't change the
+        // host.
+        let mut uri = host.clone();
+        uri.set_path(&format!(
+            "{}{}",
+            host.path().trim_end_matches('/'),
+            self.path
+        ));
+        uri.set_query(self.query.as_deref());
+        Ok(uri)
+    }
+}
+
+impl StreamRequest for RawRequest {
+    type Resource = Value;
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: HashMap<IpAddr, Bucket>,
+}
+
+struct Proxy {
+    client: CachingClient<HorizonHttpClient>,
+    hub: StreamHub<HorizonHttpClient>,
+    upstream: String,
+    public_url: Option<String>,
+    limiter: Mutex<RateLimiter>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let proxy = Arc::new(Proxy {
+        client: CachingClient::new_with_options(
+            HorizonHttpClient::new_from_str(&args.horizon)?,
+            args.cache_size,
+        ),
+        hub: StreamHub::new(HorizonHttpClient::new_from_str(&args.horizon)?),
+        upstream: args.horizon.trim_end_matches('/').to_string(),
+        public_url: args
+            .public_url
+            .map(|url| url.as_str().trim_end_matches('/').to_string()),
+        limiter: Mutex::new(RateLimiter::new(args.rate, args.burst)),
+    });
+    let listener = TcpListener::bind(args.listen).await?;
+    println!("Proxying {} on http://{}", args.horizon, args.listen);
+    loop {
+        let (stream, remote) = listener.accept().await?;
+        let proxy = proxy.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |req| handle(proxy.clone(), remote.ip(), req));
+            if let Err(err) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                eprintln!("connection error: {}", err);
+            }
+        });
+    }
+}
+
+async fn handle(
+    proxy: Arc<Proxy>,
+    remote: IpAddr,
+    req: hyper::Request<Incoming>,
+) -> std::result::Result<Response<Body>, Infallible> {
+    if !proxy.limiter.lock().unwrap().allow(remote, Instant::now()) {
+        return Ok(problem(
+            StatusCode::TOO_MANY_REQUESTS,
+            "Rate Limit Exceeded",
+        ));
+    }
+    if req.method() != Method::GET {
+        return Ok(problem(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "Method Not Allowed",
+        ));
+    }
+    let policy = match cache_policy(req.uri().path()) {
+        Some(policy) => policy,
+        None => return Ok(problem(StatusCode::NOT_FOUND, "Resource Missing")),
+    };
+    let request = RawRequest {
+        path: req.uri().path().to_string(),
+        query: req.uri().query().map(str::to_string),
+    };
+    let accepts_events = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/event-stream"));
+    let response = if accepts_events {
+        let last_event_id = req
+            .headers()
+            .get("last-event-id")
+            .and_then(|id| id.to_str().ok());
+        proxy.stream(request, last_event_id)
+    } else {
+        proxy.get(request, policy).await
+    };
+    Ok(response)
+}
+
+impl Proxy {
+    async fn get(&self, request: RawRequest, policy: CachePolicy) -> Response<Body> {
+        let body = match self.client.request_with_policy(request, policy).await {
+            Ok((_, value)) => value.to_string(),
+            Err(err) => return error_response(err),
+        };
+        // Point links to the proxy instead of the upstream server.
+        let body = match &self.public_url {
+            Some(public_url) => body.replace(&self.upstream, public_url),
+            None => body,
+        };
+        let mut response = Response::new(full(body));
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/hal+json"),
+        );
+        response
+    }
+
+    fn stream(&self, mut request: RawRequest, last_event_id: Option<&str>) -> Response<Body> {
+        if let Some(cursor) = last_event_id {
+            request.query = Some(with_cursor(request.query.as_deref(), cursor));
+        }
+        let events = match self.hub.subscribe(request) {
+            Ok(events) => events,
+            Err(err) => return error_response(err),
+        };
+        // End the stream on errors, clients reconnect with the last
+        // event id.
+        let frames = events
+            .take_while(|event| future::ready(event.is_ok()))
+            .filter_map(|event| future::ready(event.ok()))
+            .map(|event| Ok(Frame::data(sse_frame(&event))));
+        let mut response = Response::new(StreamBody::new(frames).boxed_unsync());
+        let headers = response.headers_mut();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/event-stream"),
+        );
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        response
+    }
+}
+
+impl RateLimiter {
+    fn new(rate: f64, burst: f64) -> RateLimiter {
+        RateLimiter {
+            rate,
+            burst: burst.max(1.0),
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Returns true if `client` can send a request at `now`.
+    fn allow(&mut self, client: IpAddr, now: Instant) -> bool {
+        if self.buckets.len() > 10_000 {
+            // Full buckets are the same as no bucket.
+            let full = Duration::from_secs_f64(self.burst / self.rate.max(f64::EPSILON));
+            self.buckets
+                .retain(|_, bucket| now.duration_since(bucket.updated) < full);
+        }
+        let burst = self.burst;
+        let bucket = self.buckets.entry(client).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(burst);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Returns the cache policy of the Horizon route `path`, or `None` if
+/// `path` isn't a Horizon route.
+fn cache_policy(path: &str) -> Option<CachePolicy> {
+    if path == "/" {
+        return Some(CachePolicy::Never);
+    }
+    let segments: Vec<_> = path
+        .strip_prefix('/')?
+        .trim_end_matches('/')
+        .split('/')
+        .collect();
+    if !ROUTES.contains(&segments[0]) || !segments.iter().all(|segment| is_plain(segment)) {
+        return None;
+    }
+    let history = |resource: &str| HISTORY.contains(&resource);
+    let policy = match segments.as_slice() {
+        ["ledgers", _] | ["transactions", _] | ["operations", _] => CachePolicy::Immutable,
+        ["ledgers", _, resource] | ["transactions", _, resource] if history(resource) => {
+            CachePolicy::Immutable
+        }
+        ["operations", _, "effects"] => CachePolicy::Immutable,
+        [resource] if history(resource) => CachePolicy::ClosedPage,
+        ["accounts" | "liquidity_pools" | "claimable_balances", _, resource]
+            if history(resource) =>
+        {
+            CachePolicy::ClosedPage
+        }
+        _ => CachePolicy::Never,
+    };
+    Some(policy)
+}
+
+/// Returns true if `segment` is a plain path segment, and not an empty,
+/// dot or scheme segment that could send the request elsewhere.
+fn is_plain(segment: &str) -> bool {
+    let dots = segment.to_ascii_lowercase().replace("%2e", ".");
+    !segment.is_empty() && dots != "." && dots != ".." && !segment.contains([':', '\\'])
+}
+
+/// Replaces the cursor of `query`.
+fn with_cursor(query: Option<&str>, cursor: &str) -> String {
+    let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+    for (key, value) in url::form_urlencoded::parse(query.unwrap_or_default().as_bytes()) {
+        if key != "cursor" {
+            serializer.append_pair(&key, &value);
+        }
+    }
+    serializer.append_pair("cursor", cursor);
+    serializer.finish()
+}
+
+fn sse_frame(event: &StreamEvent<Value>) -> Bytes {
+    let mut frame = String::new();
+    if let Some(id) = &event.id {
+        frame.push_str(&format!("id: {}\n", id));
+    }
+    frame.push_str(&format!("data: {}\n\n", event.resource));
+    Bytes::from(frame)
+}
+
+fn full<B: Into<Bytes>>(body: B) -> Body {
+    Full::new(body.into()).boxed_unsync()
+}
+
+fn problem(status: StatusCode, title: &str) -> Response<Body> {
+    let body = json!({
+        "type": "about:blank",
+        "title": title,
+        "status": status.as_u16(),
+        "detail": title,
+    });
+    let mut response = Response::new(full(body.to_string()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/problem+json"),
+    );
+    response
+}
+
+fn error_response(err: Error) -> Response<Body> {
+    let err = match err {
+        Error::HorizonRequestError(err) => err,
+        err => {
+            eprintln!("upstream error: {}", err);
+            return problem(StatusCode::BAD_GATEWAY, "Bad Gateway");
+        }
+    };
+    let (status, body) = match &err {
+        HorizonError::BadRequest(err) => (err.base.status, serde_json::to_value(err)),
+        HorizonError::TransactionFailed(err) => (err.base.status, serde_json::to_value(err)),
+        HorizonError::TransactionMalformed(err) => (err.base.status, serde_json::to_value(err)),
+        HorizonError::BeforeHistory(err)
+        | HorizonError::StaleHistory(err)
+        | HorizonError::Timeout(err)
+        | HorizonError::Other(err) => (err.status, serde_json::to_value(err)),
+    };
+    let status = u16::try_from(status)
+        .ok()
+        .and_then(|status| StatusCode::from_u16(status).ok())
+        .unwrap_or(StatusCode::BAD_GATEWAY);
+    let mut response = Response::new(full(body.unwrap_or_default().to_string()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/problem+json"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_policy() {
+        assert_eq!(Some(CachePolicy::Immutable), cache_policy("/ledgers/10"));
+        assert_eq!(
+            Some(CachePolicy::Immutable),
+            cache_policy("/transactions/abcd")
+        );
+        assert_eq!(
+            Some(CachePolicy::Immutable),
+            cache_policy("/ledgers/10/operations")
+        );
+        assert_eq!(Some(CachePolicy::ClosedPage), cache_policy("/payments"));
+        assert_eq!(
+            Some(CachePolicy::ClosedPage),
+            cache_policy("/accounts/GA73/effects")
+        );
+        assert_eq!(Some(CachePolicy::Never), cache_policy("/"));
+        assert_eq!(Some(CachePolicy::Never), cache_policy("/accounts/GA73"));
+        assert_eq!(Some(CachePolicy::Never), cache_policy("/offers/"));
+        assert_eq!(Some(CachePolicy::Never), cache_policy("/fee_stats"));
+    }
+
+    #[test]
+    fn test_reject_other_paths() {
+        for path in [
+            "",
+            "*",
+            "/metrics",
+            "//evil.example/x",
+            "/https://evil.example/x",
+            "/ledgers/https://evil.example/x",
+            "/ledgers/../metrics",
+            "/ledgers/%2E%2e/metrics",
+            "/ledgers/.",
+            "/ledgers//10",
+            "/ledgers\\..\\metrics",
+        ] {
+            assert_eq!(None, cache_policy(path), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_raw_request_uri() {
+        let request = RawRequest {
+            path: "/ledgers/10".to_string(),
+            query: Some("limit=2".to_string()),
+        };
+        let host: Url = "https://horizon.example/api/".parse().unwrap();
+        assert_eq!(
+            "https://horizon.example/api/ledgers/10?limit=2",
+            request.uri(&host).unwrap().as_str()
+        );
+        let request = RawRequest {
+            path: "//evil.example/x".to_string(),
+            query: None,
+        };
+        let host: Url = "https://horizon.example".parse().unwrap();
+        assert_eq!(
+            Some("horizon.example"),
+            request.uri(&host).unwrap().host_str()
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::new(1.0, 2.0);
+        let client: IpAddr = "127.0.0.1".parse().unwrap();
+        let other: IpAddr = "127.0.0.2".parse().unwrap();
+        let now = Instant::now();
+        assert!(limiter.allow(client, now));
+        assert!(limiter.allow(client, now));
+        assert!(!limiter.allow(client, now));
+        assert!(limiter.allow(other, now));
+        assert!(limiter.allow(client, now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_with_cursor() {
+        assert_eq!(
+            "limit=5&cursor=10",
+            with_cursor(Some("cursor=now&limit=5"), "10")
+        );
+        assert_eq!("cursor=10", with_cursor(None, "10"));
+    }
+}
@@ -0,0 +1,489 @@
+//! Command line Horizon explorer.
+//!
+//! Requires the `cli` feature.
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use futures::stream::{Stream, TryStreamExt};
+use serde::Serialize;
+use serde_json::Value;
+use stellar_base::asset::Asset;
+use stellar_base::crypto::PublicKey;
+use stellar_base::transaction::TransactionEnvelope;
+use stellar_base::xdr::XDRDeserialize;
+use stellar_horizon::api;
+use stellar_horizon::client::{HorizonClient, HorizonHttpClient};
+use stellar_horizon::error::{Error, Result};
+use stellar_horizon::page::Page;
+use stellar_horizon::request::{Order, PageRequest, Request};
+use stellar_horizon::resources::{LedgerSequence, OperationId, TransactionHash};
+
+/// Explore the Stellar network through Horizon.
+#[derive(Parser)]
+#[command(name = "horizon", version)]
+struct Cli {
+    /// The Horizon server url.
+    #[arg(
+        long,
+        env = "HORIZON_URL",
+        default_value = "https://horizon.stellar.org"
+    )]
+    horizon: String,
+    /// The output format.
+    #[arg(long, value_enum, default_value_t = Output::Json)]
+    output: Output,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Output {
+    Json,
+    Table,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OrderArg {
+    Asc,
+    Desc,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Fetch an account.
+    Account { account: String },
+    /// Fetch a transaction.
+    Transaction { hash: String },
+    /// List transactions.
+    Transactions {
+        #[command(flatten)]
+        filter: Filter,
+        #[command(flatten)]
+        page: PageArgs,
+    },
+    /// Fetch an operation.
+    Operation { id: String },
+    /// List operations.
+    Operations {
+        #[command(flatten)]
+        filter: Filter,
+        #[command(flatten)]
+        page: PageArgs,
+    },
+    /// List effects.
+    Effects {
+        #[command(flatten)]
+        filter: Filter,
+        /// Only effects of this operation.
+        #[arg(long, conflicts_with_all = ["account", "ledger", "transaction"])]
+        operation: Option<String>,
+        #[command(flatten)]
+        page: PageArgs,
+    },
+    /// List offers.
+    Offers {
+        /// Only offers of this account.
+        #[arg(long)]
+        account: Option<String>,
+        #[command(flatten)]
+        page: PageArgs,
+    },
+    /// Fetch an order book, assets are `native` or `CODE:ISSUER`.
+    OrderBook {
+        selling: String,
+        buying: String,
+        #[arg(long)]
+        limit: Option<u64>,
+    },
+    /// Stream resources as they are created.
+    Stream {
+        #[command(subcommand)]
+        resource: StreamResource,
+    },
+    /// Submit a base64 encoded transaction envelope.
+    Submit { xdr: String },
+}
+
+#[derive(Subcommand)]
+enum StreamResource {
+    /// Stream payments.
+    Payments {
+        /// Only payments of this account.
+        #[arg(long)]
+        account: Option<String>,
+        #[arg(long, default_value = "now")]
+        cursor: String,
+    },
+    /// Stream ledgers.
+    Ledgers {
+        #[arg(long, default_value = "now")]
+        cursor: String,
+    },
+}
+
+#[derive(Args)]
+struct Filter {
+    /// Only records of this account.
+    #[arg(long, conflicts_with_all = ["ledger", "transaction"])]
+    account: Option<String>,
+    /// Only records of this ledger.
+    #[arg(long, conflicts_with = "transaction")]
+    ledger: Option<u32>,
+    /// Only records of this transaction.
+    #[arg(long)]
+    transaction: Option<String>,
+}
+
+#[derive(Args)]
+struct PageArgs {
+    /// Start after this paging token.
+    #[arg(long)]
+    cursor: Option<String>,
+    /// The number of records.
+    #[arg(long)]
+    limit: Option<u64>,
+    /// The records order.
+    #[arg(long, value_enum)]
+    order: Option<OrderArg>,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to start runtime");
+    if let Err(err) = runtime.block_on(run(cli)) {
+        match err {
+            Error::HorizonRequestError(err) => eprintln!("error: {:#?}", err),
+            err => {
+                eprintln!("error: {}", err);
+                let mut source = std::error::Error::source(&err);
+                while let Some(err) = source {
+                    eprintln!("caused by: {}", err);
+                    source = err.source();
+                }
+            }
+        }
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let client = HorizonHttpClient::new_from_str(&cli.horizon)?;
+    let output = cli.output;
+    match cli.command {
+        Command::Account { account } => {
+            let request = api::accounts::single(&public_key(&account)?);
+            show(&client, request, output).await
+        }
+        Command::Transaction { hash } => {
+            let hash: TransactionHash = hash.parse()?;
+            show(&client, api::transactions::single(&hash), output).await
+        }
+        Command::Transactions { filter, page } => match filter.into_target()? {
+            Target::All => list(&client, api::transactions::all(), page, output).await,
+            Target::Account(account) => {
+                let request = api::transactions::for_account(&account);
+                list(&client, request, page, output).await
+            }
+            Target::Ledger(ledger) => {
+                let request = api::transactions::for_ledger(ledger);
+                list(&client, request, page, output).await
+            }
+            Target::Transaction(hash) => {
+                show(&client, api::transactions::single(&hash), output).await
+            }
+        },
+        Command::Operation { id } => {
+            let id: OperationId = id.parse()?;
+            show(&client, api::operations::single(id), output).await
+        }
+        Command::Operations { filter, page } => match filter.into_target()? {
+            Target::All => list(&client, api::operations::all(), page, output).await,
+            Target::Account(account) => {
+                let request = api::operations::for_account(&account);
+                list(&client, request, page, output).await
+            }
+            Target::Ledger(ledger) => {
+                let request = api::operations::for_ledger(ledger);
+                list(&client, request, page, output).await
+            }
+            Target::Transaction(hash) => {
+                let request = api::operations::for_transaction(&hash);
+                list(&client, request, page, output).await
+            }
+        },
+        Command::Effects {
+            operation: Some(operation),
+            page,
+            ..
+        } => {
+            let request = api::effects::for_operation(operation.parse()?);
+            list(&client, request, page, output).await
+        }
+        Command::Effects { filter, page, .. } => match filter.into_target()? {
+            Target::All => list(&client, api::effects::all(), page, output).await,
+            Target::Account(account) => {
+                let request = api::effects::for_account(&account);
+                list(&client, request, page, output).await
+            }
+            Target::Ledger(ledger) => {
+                let request = api::effects::for_ledger(ledger);
+                list(&client, request, page, output).await
+            }
+            Target::Transaction(hash) => {
+                let request = api::effects::for_transaction(&hash);
+                list(&client, request, page, output).await
+            }
+        },
+        Command::Offers {
+            account: Some(account),
+            page,
+        } => {
+            let request = api::offers::for_account(&public_key(&account)?);
+            list(&client, request, page, output).await
+        }
+        Command::Offers { page, .. } => list(&client, api::offers::all(), page, output).await,
+        Command::OrderBook {
+            selling,
+            buying,
+            limit,
+        } => {
+            let mut request = api::aggregations::order_book(asset(&selling)?, asset(&buying)?);
+            if let Some(limit) = limit {
+                request = request.with_limit(limit);
+            }
+            show(&client, request, output).await
+        }
+        Command::Stream {
+            resource: StreamResource::Payments { account, cursor },
+        } => match account {
+            Some(account) => {
+                let request = api::payments::for_account(&public_key(&account)?);
+                follow(client.stream(request.with_cursor(&cursor))?, output).await
+            }
+            None => {
+                let request = api::payments::all().with_cursor(&cursor);
+                follow(client.stream(request)?, output).await
+            }
+        },
+        Command::Stream {
+            resource: StreamResource::Ledgers { cursor },
+        } => {
+            let request = api::ledgers::all().with_cursor(&cursor);
+            follow(client.stream(request)?, output).await
+        }
+        Command::Submit { xdr } => {
+            let envelope = TransactionEnvelope::from_xdr_base64(&xdr)?;
+            show(&client, api::transactions::submit(&envelope)?, output).await
+        }
+    }
+}
+
+enum Target {
+    All,
+    Account(PublicKey),
+    Ledger(LedgerSequence),
+    Transaction(TransactionHash),
+}
+
+impl Filter {
+    fn into_target(self) -> Result<Target> {
+        if let Some(account) = self.account {
+            return Ok(Target::Account(public_key(&account)?));
+        }
+        if let Some(ledger) = self.ledger {
+            return Ok(Target::Ledger(LedgerSequence::new(ledger)));
+        }
+        if let Some(hash) = self.transaction {
+            return Ok(Target::Transaction(hash.parse()?));
+        }
+        Ok(Target::All)
+    }
+}
+
+impl PageArgs {
+    fn apply<R: PageRequest>(self, mut request: R) -> R {
+        if let Some(cursor) = &self.cursor {
+            request = request.with_cursor(cursor);
+        }
+        if let Some(limit) = self.limit {
+            request = request.with_limit(limit);
+        }
+        match self.order {
+            Some(OrderArg::Asc) => request.with_order(&Order::Ascending),
+            Some(OrderArg::Desc) => request.with_order(&Order::Descending),
+            None => request,
+        }
+    }
+}
+
+fn public_key(account: &str) -> Result<PublicKey> {
+    Ok(PublicKey::from_account_id(account)?)
+}
+
+/// Parses `native` or `CODE:ISSUER`.
+fn asset(asset: &str) -> Result<Asset> {
+    match asset.split_once(':') {
+        None if asset.eq_ignore_ascii_case("native") => Ok(Asset::new_native()),
+        Some((code, issuer)) => Ok(Asset::new_credit(code, public_key(issuer)?)?),
+        None => Err(Error::StellarBaseError(
+            stellar_base::error::Error::InvalidAssetCode,
+        )),
+    }
+}
+
+async fn show<R>(client: &HorizonHttpClient, request: R, output: Output) -> Result<()>
+where
+    R: Request,
+    R::Response: Serialize,
+{
+    let (_, response) = client.request(request).await?;
+    let value = serde_json::to_value(&response)?;
+    match output {
+        Output::Json => println!("{}", serde_json::to_string_pretty(&value)?),
+        Output::Table => print_table(&fields_table(&value)),
+    }
+    Ok(())
+}
+
+async fn list<R, T>(
+    client: &HorizonHttpClient,
+    request: R,
+    page: PageArgs,
+    output: Output,
+) -> Result<()>
+where
+    R: PageRequest<Response = Page<T>>,
+    T: serde::de::DeserializeOwned + Serialize + Clone,
+{
+    let (_, page) = client.request(page.apply(request)).await?;
+    let records = serde_json::to_value(&page.records)?;
+    match output {
+        Output::Json => println!("{}", serde_json::to_string_pretty(&records)?),
+        Output::Table => {
+            let records = records.as_array().map(Vec::as_slice).unwrap_or_default();
+            print_table(&records_table(records));
+        }
+    }
+    Ok(())
+}
+
+async fn follow<S, T>(stream: S, output: Output) -> Result<()>
+where
+    S: Stream<Item = Result<T>> + Unpin,
+    T: Serialize,
+{
+    let mut stream = stream;
+    let mut columns = None;
+    while let Some(resource) = stream.try_next().await? {
+        let value = serde_json::to_value(&resource)?;
+        match output {
+            Output::Json => println!("{}", value),
+            Output::Table => {
+                let columns =
+                    columns.get_or_insert_with(|| scalar_columns(std::slice::from_ref(&value)));
+                let row: Vec<_> = columns.iter().map(|c| cell(&value[c])).collect();
+                println!("{}", row.join("  "));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns a two columns table with the fields of `value`.
+fn fields_table(value: &Value) -> Vec<Vec<String>> {
+    let mut rows = vec![vec!["field".to_string(), "value".to_string()]];
+    if let Value::Object(fields) = value {
+        for (name, value) in fields {
+            if name != "_links" {
+                rows.push(vec![name.clone(), cell(value)]);
+            }
+        }
+    }
+    rows
+}
+
+/// Returns a table with a row for each record, and a column for each of
+/// their scalar fields.
+fn records_table(records: &[Value]) -> Vec<Vec<String>> {
+    let columns = scalar_columns(records);
+    let mut rows = vec![columns.clone()];
+    for record in records {
+        rows.push(columns.iter().map(|c| cell(&record[c])).collect());
+    }
+    rows
+}
+
+fn scalar_columns(records: &[Value]) -> Vec<String> {
+    let mut columns: Vec<String> = Vec::new();
+    for record in records {
+        if let Value::Object(fields) = record {
+            for (name, value) in fields {
+                let scalar = !value.is_object() && !value.is_array();
+                if scalar && !columns.contains(name) {
+                    columns.push(name.clone());
+                }
+            }
+        }
+    }
+    // Json fields are sorted by name, show the identifying ones first.
+    let first = ["id", "paging_token", "type", "created_at"];
+    columns.sort_by_key(|c| first.iter().position(|f| f == c).unwrap_or(first.len()));
+    columns
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn print_table(rows: &[Vec<String>]) {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            rows.iter()
+                .filter_map(|row| row.get(i))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for row in rows {
+        let line: Vec<_> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_asset() {
+        assert_eq!(Asset::new_native(), asset("native").unwrap());
+        let issuer = "GA73S4WXZG7EONFCIFDSZ6VOJKFC2PMV5574YDJC4V4UBDGPAYN4SPAC";
+        let usd = asset(&format!("USD:{}", issuer)).unwrap();
+        assert_eq!("USD", usd.as_credit().unwrap().code());
+        assert!(asset("USD").is_err());
+    }
+
+    #[test]
+    fn test_records_table() {
+        let records = vec![
+            json!({"b": 1, "id": "1", "nested": {"x": 1}}),
+            json!({"c": null, "id": "2", "list": [1]}),
+        ];
+        let table = records_table(&records);
+        assert_eq!(vec!["id", "b", "c"], table[0]);
+        assert_eq!(vec!["1", "1", ""], table[1]);
+        assert_eq!(vec!["2", "", ""], table[2]);
+    }
+}
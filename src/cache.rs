@@ -0,0 +1,448 @@
+//! Cache responses that can't change.
+//!
+//! A `CachingClient` wraps another client and keeps the responses of
+//! cacheable requests in a size-bounded LRU cache, keyed by request
+//! uri. Whether a response is cached depends on the `CachePolicy` of
+//! the request type:
+//!
+//!  * Single ledgers, transactions and operations never change.
+//!  * Pages of history never change once their cursor window is in the
+//!    past: pages in descending order from a cursor in an ingested
+//!    ledger, or full pages in ascending order from a cursor.
+//!
+//! Other requests are sent to the wrapped client every time.
+//!
+//! ```rust
+//! use stellar_horizon::api;
+//! use stellar_horizon::cache::{CachePolicy, CachingClient};
+//! use stellar_horizon::client::{HorizonClient, HorizonHttpClient};
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let client = CachingClient::new(client)
+//!     .with_policy::<api::trades::TradesForOfferRequest>(CachePolicy::ClosedPage);
+//! // Only the first request is sent to horizon.
+//! for _ in 0..10 {
+//!     let (_, ledger) = client.request(api::ledgers::single(1.into())).await?;
+//!     println!("Ledger = {}", ledger.hash);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::api;
+use crate::client::HorizonClient;
+use crate::error::Result;
+use crate::headers::HeaderMap;
+use crate::request::{request_key, Request, StreamRequest, ValueRequest};
+use crate::stream::EventStream;
+use futures::future::BoxFuture;
+use futures::Stream;
+use lru::LruCache;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use url::Url;
+
+/// The default number of cached responses.
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+// The page limit used by horizon if the request has none.
+const DEFAULT_PAGE_LIMIT: usize = 10;
+
+/// When to cache the response of a request.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Never cache the response.
+    Never,
+    /// Always cache the response.
+    Immutable,
+    /// Cache pages whose cursor window is in the past.
+    ClosedPage,
+}
+
+/// Client that caches the responses that can't change.
+pub struct CachingClient<C> {
+    inner: C,
+    policies: HashMap<&'static str, CachePolicy>,
+    cache: Mutex<LruCache<String, (HeaderMap, Value)>>,
+}
+
+impl<C> CachingClient<C>
+where
+    C: HorizonClient,
+{
+    /// Creates a new caching client that sends requests with `inner`.
+    pub fn new(inner: C) -> CachingClient<C> {
+        CachingClient::new_with_options(inner, DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new caching client that keeps at most `capacity`
+    /// responses.
+    pub fn new_with_options(inner: C, capacity: usize) -> CachingClient<C> {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        CachingClient {
+            inner,
+            policies: default_policies(),
+            cache: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Sets the cache policy of requests of type `R`.
+    pub fn with_policy<R: Request>(mut self, policy: CachePolicy) -> Self {
+        self.policies.insert(policy_key::<R>(), policy);
+        self
+    }
+
+    /// Returns the cache policy of requests of type `R`.
+    ///
+    /// References to requests have the policy of the request type.
+    pub fn policy<R: Request>(&self) -> CachePolicy {
+        self.policies
+            .get(policy_key::<R>())
+            .copied()
+            .unwrap_or(CachePolicy::Never)
+    }
+
+    /// Returns the wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns the number of cached responses.
+    pub fn len(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+
+    /// Returns true if no response is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all cached responses.
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// Sends `req` with the cache `policy` instead of the policy of its
+    /// type.
+    ///
+    /// Used for requests whose type doesn't tell what they request.
+    pub async fn request_with_policy<R: Request>(
+        &self,
+        req: R,
+        policy: CachePolicy,
+    ) -> Result<(HeaderMap, R::Response)> {
+        if policy == CachePolicy::Never || req.post_body()?.is_some() {
+            return self.inner.request(req).await;
+        }
+        let key = request_key(&req)?;
+        let cached = self.cache.lock().unwrap().get(&key).cloned();
+        let (headers, value) = match cached {
+            Some(cached) => cached,
+            None => {
+                let (headers, value) = self.inner.request(ValueRequest(&req)).await?;
+                if is_cacheable(policy, &key, &value) {
+                    let mut cache = self.cache.lock().unwrap();
+                    cache.put(key, (headers.clone(), value.clone()));
+                }
+                (headers, value)
+            }
+        };
+        Ok((headers, serde_json::from_value(value)?))
+    }
+}
+
+impl<C> HorizonClient for CachingClient<C>
+where
+    C: HorizonClient + Sync,
+{
+    fn request<'a, R: Request + 'a>(
+        &'a self,
+        req: R,
+    ) -> BoxFuture<'a, Result<(HeaderMap, R::Response)>> {
+        Box::pin(self.request_with_policy(req, self.policy::<R>()))
+    }
+
+    fn stream<'a, R: StreamRequest + 'static>(
+        &'a self,
+        req: R,
+    ) -> Result<Box<dyn Stream<Item = Result<R::Resource>> + 'static + Send + Unpin>> {
+        self.inner.stream(req)
+    }
+
+    fn stream_events<R: StreamRequest + 'static>(
+        &self,
+        req: R,
+    ) -> Result<EventStream<R::Resource>> {
+        self.inner.stream_events(req)
+    }
+}
+
+fn default_policies() -> HashMap<&'static str, CachePolicy> {
+    use std::any::type_name;
+
+    let immutable = [
+        type_name::<api::ledgers::SingleLedgerRequest>(),
+        type_name::<api::transactions::SingleTransactionRequest>(),
+        type_name::<api::operations::SingleOperationRequest>(),
+    ];
+    let closed_pages = [
+        type_name::<api::ledgers::AllLedgersRequest>(),
+        type_name::<api::transactions::AllTransactionsRequest>(),
+        type_name::<api::transactions::TransactionsForAccountRequest>(),
+        type_name::<api::transactions::TransactionsForLedgerRequest>(),
+        type_name::<api::transactions::TransactionsForClaimableBalanceRequest>(),
+        type_name::<api::transactions::TransactionsForLiquidityPoolRequest>(),
+        type_name::<api::operations::AllOperationsRequest>(),
+        type_name::<api::operations::OperationsForAccountRequest>(),
+        type_name::<api::operations::OperationsForLedgerRequest>(),
+        type_name::<api::operations::OperationsForTransactionRequest>(),
+        type_name::<api::operations::OperationsForClaimableBalanceRequest>(),
+        type_name::<api::operations::OperationsForLiquidityPoolRequest>(),
+        type_name::<api::payments::AllPaymentsRequest>(),
+        type_name::<api::payments::PaymentsForAccountRequest>(),
+        type_name::<api::payments::PaymentsForLedgerRequest>(),
+        type_name::<api::payments::PaymentsForTransactionRequest>(),
+        type_name::<api::effects::AllEffectsRequest>(),
+        type_name::<api::effects::EffectsForLedgerRequest>(),
+        type_name::<api::effects::EffectsForTransactionRequest>(),
+        type_name::<api::effects::EffectsForOperationRequest>(),
+        type_name::<api::effects::EffectsForAccountRequest>(),
+        type_name::<api::effects::EffectsForLiquidityPoolRequest>(),
+        type_name::<api::trades::AllTradesRequest>(),
+        type_name::<api::trades::TradesForAccountRequest>(),
+        type_name::<api::trades::TradesForOfferRequest>(),
+        type_name::<api::trades::TradesForLiquidityPoolRequest>(),
+    ];
+    immutable
+        .into_iter()
+        .map(|name| (name, CachePolicy::Immutable))
+        .chain(
+            closed_pages
+                .into_iter()
+                .map(|name| (name, CachePolicy::ClosedPage)),
+        )
+        .collect()
+}
+
+// Borrowed requests are sent as they are, so they share the policy of
+// the request type.
+fn policy_key<R: Request>() -> &'static str {
+    std::any::type_name::<R>().trim_start_matches('&')
+}
+
+/// Returns true if the response `value` to the request with uri `key`
+/// can be cached.
+///
+/// Pages in descending order from a cursor don't change once the ledger
+/// of the cursor is ingested. Pages in ascending order from a cursor can
+/// still grow until they are full.
+fn is_cacheable(policy: CachePolicy, key: &str, value: &Value) -> bool {
+    match policy {
+        CachePolicy::Never => false,
+        CachePolicy::Immutable => true,
+        CachePolicy::ClosedPage => {
+            let uri: Url = match key.parse() {
+                Ok(uri) => uri,
+                Err(_) => return false,
+            };
+            let query: HashMap<_, _> = uri.query_pairs().collect();
+            let cursor = match query.get("cursor") {
+                Some(cursor) if !cursor.is_empty() && cursor != "now" => cursor,
+                _ => return false,
+            };
+            if query.get("order").is_some_and(|order| order == "desc") {
+                // Records are ingested a ledger at a time, so records
+                // older than the cursor can only be added if the cursor
+                // is past the ledger of the latest record of the page.
+                let first = value["_embedded"]["records"][0]["paging_token"].as_str();
+                return match (paging_token_toid(cursor), first.and_then(paging_token_toid)) {
+                    (Some(cursor), Some(first)) => cursor <= next_ledger_toid(first),
+                    _ => false,
+                };
+            }
+            let limit = query
+                .get("limit")
+                .and_then(|limit| limit.parse().ok())
+                .unwrap_or(DEFAULT_PAGE_LIMIT);
+            let records = value["_embedded"]["records"].as_array().map_or(0, Vec::len);
+            records == limit
+        }
+    }
+}
+
+/// Returns the TOID at the start of a paging token, the ledger sequence
+/// is in its upper 32 bits.
+fn paging_token_toid(paging_token: &str) -> Option<i64> {
+    paging_token.split('-').next()?.parse().ok()
+}
+
+/// Returns the first TOID of the ledger after the ledger of `toid`.
+fn next_ledger_toid(toid: i64) -> i64 {
+    (toid | 0xffff_ffff).saturating_add(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{ledger_json, page_json, MockClient};
+    use crate::request::{Order, PageRequest};
+
+    fn ledgers_client() -> CachingClient<MockClient> {
+        let client = MockClient::new(|uri| {
+            if uri.path() == "/ledgers" {
+                let limit = uri
+                    .query_pairs()
+                    .find(|(key, _)| key == "limit")
+                    .map_or(10, |(_, limit)| limit.parse().unwrap());
+                let records: Vec<_> = (1..=limit)
+                    .map(|sequence| ledger_json(sequence, 0))
+                    .collect();
+                Some(page_json(records))
+            } else {
+                Some(ledger_json(7, 0))
+            }
+        });
+        CachingClient::new(client)
+    }
+
+    #[tokio::test]
+    async fn test_cache_immutable_resources() {
+        let client = ledgers_client();
+        for _ in 0..3 {
+            let (_, ledger) = client
+                .request(api::ledgers::single(7.into()))
+                .await
+                .unwrap();
+            assert_eq!(7, ledger.sequence.value());
+        }
+        assert_eq!(1, client.inner().requests().len());
+        assert_eq!(1, client.len());
+    }
+
+    #[tokio::test]
+    async fn test_cache_closed_pages() {
+        let client = ledgers_client();
+        let closed = api::ledgers::all().with_cursor("100").with_limit(2);
+        let descending = api::ledgers::all()
+            .with_cursor("100")
+            .with_order(&Order::Descending);
+        let latest = api::ledgers::all().with_limit(2);
+        for _ in 0..2 {
+            client.request(closed.clone()).await.unwrap();
+            client.request(descending.clone()).await.unwrap();
+            client.request(latest.clone()).await.unwrap();
+        }
+        assert_eq!(4, client.inner().requests().len());
+        assert_eq!(2, client.len());
+    }
+
+    #[tokio::test]
+    async fn test_skip_descending_pages_after_latest_ledger() {
+        // Horizon has ingested up to ledger 8.
+        let client = CachingClient::new(MockClient::new(|_| {
+            Some(page_json(vec![ledger_json(8, 0), ledger_json(7, 0)]))
+        }));
+        let past = api::ledgers::all()
+            .with_cursor(&(9i64 << 32).to_string())
+            .with_order(&Order::Descending);
+        let future = api::ledgers::all()
+            .with_cursor(&(20i64 << 32).to_string())
+            .with_order(&Order::Descending);
+        for _ in 0..2 {
+            client.request(past.clone()).await.unwrap();
+            client.request(future.clone()).await.unwrap();
+        }
+        assert_eq!(3, client.inner().requests().len());
+        assert_eq!(1, client.len());
+    }
+
+    #[test]
+    fn test_paging_token_toid() {
+        assert_eq!(Some(12884905986), paging_token_toid("12884905986"));
+        assert_eq!(
+            Some(2347349951127555),
+            paging_token_toid("2347349951127555-4")
+        );
+        assert_eq!(None, paging_token_toid("abc"));
+        assert_eq!(4 << 32, next_ledger_toid(3 << 32));
+        assert_eq!(4 << 32, next_ledger_toid((3 << 32) + 4097));
+    }
+
+    #[tokio::test]
+    async fn test_skip_open_pages() {
+        let client = CachingClient::new(MockClient::new(|_| {
+            Some(page_json(vec![ledger_json(1, 0)]))
+        }));
+        let request = api::ledgers::all().with_cursor("100").with_limit(2);
+        client.request(request.clone()).await.unwrap();
+        client.request(request).await.unwrap();
+        assert_eq!(2, client.inner().requests().len());
+        assert!(client.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_configure_policy() {
+        let client = ledgers_client()
+            .with_policy::<api::ledgers::SingleLedgerRequest>(CachePolicy::Never)
+            .with_policy::<api::ledgers::AllLedgersRequest>(CachePolicy::Immutable);
+        client
+            .request(api::ledgers::single(7.into()))
+            .await
+            .unwrap();
+        client
+            .request(api::ledgers::single(7.into()))
+            .await
+            .unwrap();
+        client.request(api::ledgers::all()).await.unwrap();
+        client.request(api::ledgers::all()).await.unwrap();
+        assert_eq!(3, client.inner().requests().len());
+        assert_eq!(
+            CachePolicy::ClosedPage,
+            client.policy::<api::payments::AllPaymentsRequest>()
+        );
+        assert_eq!(
+            CachePolicy::Never,
+            client.policy::<api::accounts::AllAccountsRequest>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_with_policy() {
+        let client = ledgers_client();
+        for policy in [
+            CachePolicy::Never,
+            CachePolicy::Immutable,
+            CachePolicy::Immutable,
+        ] {
+            client
+                .request_with_policy(api::ledgers::all(), policy)
+                .await
+                .unwrap();
+        }
+        assert_eq!(2, client.inner().requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_evict_least_recently_used() {
+        let client =
+            CachingClient::new_with_options(MockClient::new(|_| Some(ledger_json(7, 0))), 2);
+        for sequence in [1, 2, 1, 3, 1, 2] {
+            client
+                .request(api::ledgers::single(sequence.into()))
+                .await
+                .unwrap();
+        }
+        let requested: Vec<_> = client
+            .inner()
+            .requests()
+            .iter()
+            .map(|uri| uri.path().to_string())
+            .collect();
+        assert_eq!(
+            vec!["/ledgers/1", "/ledgers/2", "/ledgers/3", "/ledgers/2"],
+            requested
+        );
+    }
+}
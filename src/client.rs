@@ -3,25 +3,37 @@
 //! All request paths are joined using relative segments (no leading '/') via [`Url::join`](https://docs.rs/url/latest/url/struct.Url.html#method.join).
 //! If your base host includes a path (e.g., https://example.com/horizon/api), ensure it ends
 //! with a trailing slash (https://example.com/horizon/api/) so joins append to that path.
+use crate::api;
+use crate::auth::HeaderProvider;
 use crate::error::{Error, Result};
 use crate::headers::HeaderMap;
 use crate::horizon_error::HorizonError;
+use crate::network::Network;
+use crate::options::{self, RequestOptions};
 use crate::request::{Request, StreamRequest};
+use crate::stream::{EventStream, StreamEvent};
 use bytes::Bytes;
 use futures::future::{BoxFuture, Future};
 use futures::stream::TryStreamExt;
 use futures::Stream;
 use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::rt::{Read, Write};
 use hyper_timeout::TimeoutConnector;
 use hyper_tls::HttpsConnector;
-use hyper_util::client::legacy::{connect::HttpConnector, Client, ResponseFuture};
-use hyper_util::rt::TokioExecutor;
+use hyper_util::client::legacy::connect::proxy::Tunnel;
+use hyper_util::client::legacy::connect::{Connect, Connection, HttpConnector};
+use hyper_util::client::legacy::{Client, ResponseFuture};
+use hyper_util::rt::{TokioExecutor, TokioTimer};
 use std::convert::TryInto;
 use std::marker::Unpin;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
+use tokio::sync::OnceCell;
+use tokio::time::Instant;
+use tower_service::Service;
 use url::Url;
 
 /// Horizon Client trait. Send HTTP and stream requests to Horizon.
@@ -36,21 +48,68 @@ pub trait HorizonClient {
         &'a self,
         req: R,
     ) -> Result<Box<dyn Stream<Item = Result<R::Resource>> + 'static + Send + Unpin>>;
+    /// Create a stream request that yields each resource together with
+    /// its event id and the time it was received.
+    ///
+    /// The default implementation wraps `stream` and can't provide the
+    /// event id.
+    fn stream_events<R: StreamRequest + 'static>(
+        &self,
+        req: R,
+    ) -> Result<EventStream<R::Resource>> {
+        let stream = self.stream(req)?;
+        Ok(Box::new(
+            stream.map_ok(|resource| StreamEvent::new(None, resource)),
+        ))
+    }
 }
 
 type HttpClient = Client<TimeoutConnector<HttpsConnector<HttpConnector>>, Full<Bytes>>;
 
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+type ResponseResult = BoxFuture<'static, Result<hyper::Response<Incoming>>>;
+
+/// The default connect, read and write timeout.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The default time idle connections are kept in the pool.
+pub const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+// The delay before the first retry, doubled after each retry.
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+const DEFAULT_CLIENT_NAME: &str = "aurora-rs/stellar-horizon-rs";
+
+/// A hyper client, whatever its connector.
+trait SendRequest: Send + Sync {
+    fn send_request(&self, req: hyper::Request<Full<Bytes>>) -> ResponseFuture;
+}
+
+impl<C> SendRequest for Client<C, Full<Bytes>>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    fn send_request(&self, req: hyper::Request<Full<Bytes>>) -> ResponseFuture {
+        self.request(req)
+    }
+}
+
 /// Type that implements `HorizonClient` using `hyper` for http.
 pub struct HorizonHttpClient {
     inner: Arc<HorizonHttpClientInner>,
 }
 
 struct HorizonHttpClientInner {
-    inner: HttpClient,
+    inner: Box<dyn SendRequest>,
     host: Url,
     client_name: String,
     client_version: String,
     extra_headers: Option<hyper::HeaderMap>,
+    header_provider: Option<Arc<dyn HeaderProvider>>,
+    max_retries: u32,
+    network: Option<Network>,
+    network_verified: OnceCell<()>,
 }
 
 type BoxDecoder = Box<dyn Unpin + Send + Stream<Item = http_types::Result<async_sse::Event>>>;
@@ -64,29 +123,43 @@ where
     client: Arc<HorizonHttpClientInner>,
     last_id: Option<String>,
     request: R,
-    response: Option<ResponseFuture>,
+    response: Option<ResponseResult>,
     decoder: Option<BoxDecoder>,
+    interrupted: Option<BoxFuture<'static, Error>>,
+    done: bool,
+}
+
+/// A `Stream` over the events of a horizon stream connection.
+#[must_use = "Streams are lazy and do nothing unless polled"]
+pub struct HorizonHttpEventStream<R>
+where
+    R: StreamRequest,
+{
+    inner: HorizonHttpStream<R>,
 }
 
 impl HorizonHttpClientInner {
     pub fn new(host: Url) -> Result<HorizonHttpClientInner> {
         let https = HttpsConnector::new();
         let mut timeout_connector = TimeoutConnector::new(https);
-        let duration = Duration::from_secs(60);
 
-        timeout_connector.set_connect_timeout(Some(duration));
-        timeout_connector.set_read_timeout(Some(duration));
-        timeout_connector.set_write_timeout(Some(duration));
+        timeout_connector.set_connect_timeout(Some(DEFAULT_TIMEOUT));
+        timeout_connector.set_read_timeout(Some(DEFAULT_TIMEOUT));
+        timeout_connector.set_write_timeout(Some(DEFAULT_TIMEOUT));
         let inner =
             Client::builder(TokioExecutor::new()).build::<_, Full<Bytes>>(timeout_connector);
-        let client_name = "aurora-rs/stellar-horizon-rs".to_string();
+        let client_name = DEFAULT_CLIENT_NAME.to_string();
         let client_version = crate::VERSION.to_string();
         Ok(HorizonHttpClientInner {
-            inner,
+            inner: Box::new(inner),
             host,
             client_name,
             client_version,
             extra_headers: None,
+            header_provider: None,
+            max_retries: 0,
+            network: None,
+            network_verified: OnceCell::new(),
         })
     }
 
@@ -95,14 +168,18 @@ impl HorizonHttpClientInner {
         client: HttpClient,
         extra_headers: hyper::HeaderMap,
     ) -> Result<HorizonHttpClientInner> {
-        let client_name = "aurora-rs/stellar-horizon-rs".to_string();
+        let client_name = DEFAULT_CLIENT_NAME.to_string();
         let client_version = crate::VERSION.to_string();
         Ok(HorizonHttpClientInner {
-            inner: client,
+            inner: Box::new(client),
             host,
             client_name,
             client_version,
             extra_headers: Some(extra_headers),
+            header_provider: None,
+            max_retries: 0,
+            network: None,
+            network_verified: OnceCell::new(),
         })
     }
 
@@ -115,8 +192,13 @@ impl HorizonHttpClientInner {
         Ok(client)
     }
 
-    pub fn request_builder(&self, uri: Url) -> http::request::Builder {
+    pub async fn request_builder(
+        &self,
+        method: hyper::Method,
+        uri: Url,
+    ) -> Result<http::request::Builder> {
         let mut builder = hyper::Request::builder()
+            .method(method.clone())
             .uri(uri.to_string())
             .header("X-Client-Name", self.client_name.to_string())
             .header("X-Client-Version", self.client_version.to_string());
@@ -128,15 +210,49 @@ impl HorizonHttpClientInner {
                     .map(|(k, v)| (k.clone(), v.clone())),
             );
         }
-        builder
+        if let Some(header_provider) = &self.header_provider {
+            let headers = header_provider.headers(&method, &uri).await?;
+            builder.headers_mut().unwrap().extend(headers);
+        }
+        Ok(builder)
     }
 
-    fn get(&self, uri: Url) -> http::request::Builder {
-        self.request_builder(uri).method(hyper::Method::GET)
+    /// Sends a request with the default headers and `headers`.
+    ///
+    /// If horizon answers `401 Unauthorized`, the header provider can
+    /// refresh its credentials and the request is sent again, once.
+    async fn send(
+        &self,
+        method: hyper::Method,
+        uri: Url,
+        headers: hyper::HeaderMap,
+        body: Bytes,
+    ) -> Result<hyper::Response<Incoming>> {
+        let mut refreshed = false;
+        loop {
+            let mut builder = self.request_builder(method.clone(), uri.clone()).await?;
+            builder.headers_mut().unwrap().extend(headers.clone());
+            let request = builder.body(Full::new(body.clone()))?;
+            // The legacy error type from hyper-util doesn't implement
+            // Into<Error>, a server error is enough for our purposes.
+            let response = self
+                .raw_request(request)
+                .await
+                .map_err(|_| Error::HorizonServerError)?;
+            if response.status() == hyper::StatusCode::UNAUTHORIZED && !refreshed {
+                if let Some(header_provider) = &self.header_provider {
+                    if header_provider.refresh().await? {
+                        refreshed = true;
+                        continue;
+                    }
+                }
+            }
+            return Ok(response);
+        }
     }
 
     fn raw_request(&self, req: hyper::Request<Full<Bytes>>) -> ResponseFuture {
-        self.inner.request(req)
+        self.inner.send_request(req)
     }
 }
 
@@ -172,6 +288,28 @@ impl HorizonHttpClient {
         })
     }
 
+    /// Creates a new horizon client for `network`.
+    ///
+    /// The client checks that horizon uses the network passphrase before
+    /// sending its first request.
+    pub fn for_network(network: Network) -> Result<HorizonHttpClient> {
+        let host: Url = network
+            .horizon_url()
+            .parse()
+            .map_err(|_| Error::InvalidHost)?;
+        let mut inner = HorizonHttpClientInner::new(host)?;
+        inner.network = Some(network);
+        Ok(HorizonHttpClient {
+            inner: Arc::new(inner),
+        })
+    }
+
+    /// Returns a builder to configure the connection of a new horizon
+    /// client for `host`.
+    pub fn builder(host: &str) -> HorizonHttpClientBuilder {
+        HorizonHttpClientBuilder::new(host)
+    }
+
     /// Creates a new horizon client with the specified host url.
     pub fn new<U>(host: U) -> Result<HorizonHttpClient>
     where
@@ -182,17 +320,278 @@ impl HorizonHttpClient {
         Ok(HorizonHttpClient { inner })
     }
 
-    /// Returns a request builder with default headers.
-    fn request_builder(&self, uri: Url) -> http::request::Builder {
-        self.inner.request_builder(uri)
+    /// Returns the network of the client, if created with `for_network`.
+    pub fn network(&self) -> Option<&Network> {
+        self.inner.network.as_ref()
     }
 
-    /// Performs a request.
-    fn raw_request(&self, req: hyper::Request<Full<Bytes>>) -> ResponseFuture {
-        self.inner.raw_request(req)
+    /// Checks that horizon uses the passphrase of the client network.
+    ///
+    /// Does nothing if the client has no network.
+    pub async fn verify_network(&self) -> Result<()> {
+        let network = match &self.inner.network {
+            Some(network) => network,
+            None => return Ok(()),
+        };
+        let (_, root) = send_request(self, &api::root::root()).await?;
+        if root.network_passphrase != network.passphrase() {
+            return Err(Error::NetworkMismatch {
+                expected: network.passphrase().to_string(),
+                found: root.network_passphrase,
+            });
+        }
+        Ok(())
     }
 }
 
+/// Builder for a `HorizonHttpClient` with custom connection settings.
+///
+/// ```rust
+/// use stellar_horizon::client::HorizonHttpClient;
+/// use std::time::Duration;
+///
+/// # fn run() -> stellar_horizon::error::Result<()> {
+/// let client = HorizonHttpClient::builder("https://horizon.stellar.org")
+///     .with_connect_timeout(Some(Duration::from_secs(5)))
+///     .with_read_timeout(Some(Duration::from_secs(30)))
+///     .with_client_name("my-wallet")
+///     .with_http2(true)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct HorizonHttpClientBuilder {
+    host: String,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    client_name: String,
+    client_version: String,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: usize,
+    http2: bool,
+    tcp_keepalive: Option<Duration>,
+    headers: hyper::HeaderMap,
+    proxy: Option<String>,
+    network: Option<Network>,
+    max_retries: u32,
+    header_provider: Option<Arc<dyn HeaderProvider>>,
+}
+
+impl HorizonHttpClientBuilder {
+    /// Creates a new builder for a client of `host`, with the same
+    /// settings as `HorizonHttpClient::new`.
+    pub fn new(host: &str) -> HorizonHttpClientBuilder {
+        HorizonHttpClientBuilder {
+            host: host.to_string(),
+            connect_timeout: Some(DEFAULT_TIMEOUT),
+            read_timeout: Some(DEFAULT_TIMEOUT),
+            write_timeout: Some(DEFAULT_TIMEOUT),
+            client_name: DEFAULT_CLIENT_NAME.to_string(),
+            client_version: crate::VERSION.to_string(),
+            pool_idle_timeout: Some(DEFAULT_POOL_IDLE_TIMEOUT),
+            pool_max_idle_per_host: usize::MAX,
+            http2: false,
+            tcp_keepalive: None,
+            headers: hyper::HeaderMap::new(),
+            proxy: None,
+            network: None,
+            max_retries: 0,
+            header_provider: None,
+        }
+    }
+
+    /// Sets the timeout to connect to horizon, `None` to wait forever.
+    pub fn with_connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Sets the timeout of each read, `None` to wait forever.
+    ///
+    /// Streams receive a heartbeat from horizon every few seconds, so
+    /// the read timeout also applies to them.
+    pub fn with_read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Sets the timeout of each write, `None` to wait forever.
+    pub fn with_write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Sets the value of the `X-Client-Name` header.
+    pub fn with_client_name<S: Into<String>>(mut self, client_name: S) -> Self {
+        self.client_name = client_name.into();
+        self
+    }
+
+    /// Sets the value of the `X-Client-Version` header.
+    pub fn with_client_version<S: Into<String>>(mut self, client_version: S) -> Self {
+        self.client_version = client_version.into();
+        self
+    }
+
+    /// Sets how long idle connections are kept in the pool, `None` to
+    /// keep them forever.
+    pub fn with_pool_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.pool_idle_timeout = timeout;
+        self
+    }
+
+    /// Sets the maximum number of idle connections kept per host.
+    pub fn with_pool_max_idle_per_host(mut self, max_idle: usize) -> Self {
+        self.pool_max_idle_per_host = max_idle;
+        self
+    }
+
+    /// Negotiates HTTP/2 with horizon, if the server supports it.
+    pub fn with_http2(mut self, http2: bool) -> Self {
+        self.http2 = http2;
+        self
+    }
+
+    /// Sets the TCP keepalive interval, `None` to disable keepalive.
+    pub fn with_tcp_keepalive(mut self, interval: Option<Duration>) -> Self {
+        self.tcp_keepalive = interval;
+        self
+    }
+
+    /// Sets headers sent with every request.
+    pub fn with_headers(mut self, headers: hyper::HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Connects to horizon through the HTTP proxy at `proxy`.
+    ///
+    /// Credentials in the proxy url are sent with basic authentication.
+    pub fn with_proxy(mut self, proxy: &str) -> Self {
+        self.proxy = Some(proxy.to_string());
+        self
+    }
+
+    /// Checks that horizon uses the passphrase of `network` before
+    /// sending the first request, like `HorizonHttpClient::for_network`.
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    /// Adds the headers of `header_provider` to every request and stream
+    /// reconnection.
+    pub fn with_header_provider<P>(mut self, header_provider: P) -> Self
+    where
+        P: HeaderProvider + 'static,
+    {
+        self.header_provider = Some(Arc::new(header_provider));
+        self
+    }
+
+    /// Retries requests up to `max_retries` times after a server error.
+    ///
+    /// Requests are not retried by default.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Builds the client.
+    pub fn build(self) -> Result<HorizonHttpClient> {
+        let host: Url = self.host.parse().map_err(|_| Error::InvalidHost)?;
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        http.set_keepalive(self.tcp_keepalive);
+        let mut tls = native_tls::TlsConnector::builder();
+        if self.http2 {
+            tls.request_alpns(&["h2", "http/1.1"]);
+        }
+        let tls = tokio_native_tls::TlsConnector::from(tls.build()?);
+        let inner: Box<dyn SendRequest> = match &self.proxy {
+            Some(proxy) => {
+                let (proxy, auth) = parse_proxy(proxy)?;
+                let mut tunnel = Tunnel::new(proxy, http);
+                if let Some(auth) = auth {
+                    tunnel = tunnel.with_auth(auth);
+                }
+                Box::new(self.hyper_client(tunnel, tls))
+            }
+            None => Box::new(self.hyper_client(http, tls)),
+        };
+        let extra_headers = (!self.headers.is_empty()).then_some(self.headers);
+        let inner = HorizonHttpClientInner {
+            inner,
+            host,
+            client_name: self.client_name,
+            client_version: self.client_version,
+            extra_headers,
+            header_provider: self.header_provider,
+            max_retries: self.max_retries,
+            network: self.network,
+            network_verified: OnceCell::new(),
+        };
+        Ok(HorizonHttpClient {
+            inner: Arc::new(inner),
+        })
+    }
+
+    fn hyper_client<T>(
+        &self,
+        connector: T,
+        tls: tokio_native_tls::TlsConnector,
+    ) -> Client<TimeoutConnector<HttpsConnector<T>>, Full<Bytes>>
+    where
+        T: Service<hyper::Uri> + Clone + Send + Sync + 'static,
+        T::Response: Read + Write + Connection + Send + Unpin + 'static,
+        T::Future: Send + 'static,
+        T::Error: Into<BoxError>,
+    {
+        let https = HttpsConnector::from((connector, tls));
+        let mut timeout_connector = TimeoutConnector::new(https);
+        timeout_connector.set_connect_timeout(self.connect_timeout);
+        timeout_connector.set_read_timeout(self.read_timeout);
+        timeout_connector.set_write_timeout(self.write_timeout);
+        Client::builder(TokioExecutor::new())
+            .pool_timer(TokioTimer::new())
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .build(timeout_connector)
+    }
+}
+
+/// Splits the credentials from the `proxy` url, returning the proxy uri
+/// and the `Proxy-Authorization` header.
+fn parse_proxy(proxy: &str) -> Result<(hyper::Uri, Option<hyper::header::HeaderValue>)> {
+    use base64::Engine;
+
+    let mut url: Url = proxy.parse().map_err(|_| Error::InvalidProxy)?;
+    let auth = if url.username().is_empty() {
+        None
+    } else {
+        let username = percent_decode(url.username());
+        let password = url.password().map(percent_decode).unwrap_or_default();
+        let credentials =
+            base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+        let auth = format!("Basic {}", credentials)
+            .parse()
+            .map_err(|_| Error::InvalidProxy)?;
+        Some(auth)
+    };
+    url.set_username("").map_err(|_| Error::InvalidProxy)?;
+    url.set_password(None).map_err(|_| Error::InvalidProxy)?;
+    let uri = url.as_str().parse().map_err(|_| Error::InvalidProxy)?;
+    Ok((uri, auth))
+}
+
+fn percent_decode(s: &str) -> String {
+    percent_encoding::percent_decode_str(s)
+        .decode_utf8_lossy()
+        .to_string()
+}
+
 impl HorizonClient for HorizonHttpClient {
     fn request<'a, R: Request + 'a>(
         &'a self,
@@ -205,12 +604,18 @@ impl HorizonClient for HorizonHttpClient {
         &'a self,
         request: R,
     ) -> Result<Box<dyn Stream<Item = Result<R::Resource>> + 'static + Send + Unpin>> {
-        Ok(Box::new(HorizonHttpStream {
-            client: self.inner.clone(),
+        Ok(Box::new(HorizonHttpStream::new(
+            self.inner.clone(),
             request,
-            last_id: None,
-            response: None,
-            decoder: None,
+        )))
+    }
+
+    fn stream_events<R: StreamRequest + 'static>(
+        &self,
+        request: R,
+    ) -> Result<EventStream<R::Resource>> {
+        Ok(Box::new(HorizonHttpEventStream {
+            inner: HorizonHttpStream::new(self.inner.clone(), request),
         }))
     }
 }
@@ -218,65 +623,158 @@ impl HorizonClient for HorizonHttpClient {
 async fn execute_request<R: Request>(
     client: &HorizonHttpClient,
     req: R,
+) -> Result<(HeaderMap, R::Response)> {
+    options::run(req.options(), async {
+        // Only successful checks are kept, so that requests, and especially
+        // transaction submissions, fail until horizon uses the right network.
+        client
+            .inner
+            .network_verified
+            .get_or_try_init(|| client.verify_network())
+            .await?;
+        send_with_retries(client, &req).await
+    })
+    .await
+}
+
+async fn send_with_retries<R: Request>(
+    client: &HorizonHttpClient,
+    req: &R,
+) -> Result<(HeaderMap, R::Response)> {
+    let max_retries = req
+        .options()
+        .and_then(RequestOptions::retries)
+        .unwrap_or(client.inner.max_retries);
+    let mut backoff = RETRY_BACKOFF;
+    let mut retries = 0;
+    loop {
+        match send_request(client, req).await {
+            Err(Error::HorizonServerError) if retries < max_retries => {}
+            result => return result,
+        }
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+        retries += 1;
+    }
+}
+
+async fn send_request<R: Request>(
+    client: &HorizonHttpClient,
+    req: &R,
 ) -> Result<(HeaderMap, R::Response)> {
     let uri = req.uri(&client.inner.host)?;
-    let request_builder = client.request_builder(uri);
-
-    let request = if let Some(body) = req.post_body()? {
-        request_builder
-            .method(hyper::Method::POST)
-            .header(
-                hyper::header::CONTENT_TYPE,
-                "application/x-www-form-urlencoded",
-            )
-            .body(Full::new(Bytes::from(body)))?
+    let mut headers = req
+        .options()
+        .map(|options| options.headers().clone())
+        .unwrap_or_default();
+
+    let (method, body) = if let Some(body) = req.post_body()? {
+        headers.insert(
+            hyper::header::CONTENT_TYPE,
+            hyper::header::HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        (hyper::Method::POST, Bytes::from(body))
     } else {
-        request_builder
-            .method(hyper::Method::GET)
-            .body(Full::new(Bytes::new()))?
+        (hyper::Method::GET, Bytes::new())
     };
 
-    let response = match client.raw_request(request).await {
-        Ok(r) => r,
-        Err(_e) => return Err(Error::HorizonServerError),
-    };
-    let status = response.status();
+    let response = client.inner.send(method, uri, headers, body).await?;
 
-    if status.is_success() {
+    let status = response.status();
+    if status.is_success() || req.accepts_status(status.as_u16()) {
         let headers = response.headers().clone();
         let body = response.into_body();
         let bytes = body.collect().await?.to_bytes();
         let result: R::Response = serde_json::from_slice(&bytes)?;
         Ok((headers, result))
-    } else if status.is_client_error() {
-        let body = response.into_body();
-        let bytes = body.collect().await?.to_bytes();
-        let result: HorizonError = serde_json::from_slice(&bytes)?;
-        Err(Error::HorizonRequestError(result))
     } else {
-        Err(Error::HorizonServerError)
+        Err(response_error(response).await)
     }
 }
 
-impl<R> Stream for HorizonHttpStream<R>
+/// Returns the error of an unsuccessful response.
+async fn response_error(response: hyper::Response<Incoming>) -> Error {
+    if response.status() == hyper::StatusCode::TOO_MANY_REQUESTS {
+        let reset = crate::headers::rate_limit_reset(response.headers())
+            .map(|reset| Duration::from_secs(reset.into()));
+        return Error::RateLimitExceeded { reset };
+    }
+    if !response.status().is_client_error() {
+        return Error::HorizonServerError;
+    }
+    let bytes = match response.into_body().collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(err) => return err.into(),
+    };
+    match serde_json::from_slice::<HorizonError>(&bytes) {
+        Ok(result) => Error::HorizonRequestError(result),
+        Err(err) => err.into(),
+    }
+}
+
+impl<R> HorizonHttpStream<R>
 where
     R: StreamRequest,
 {
-    type Item = Result<R::Resource>;
+    fn new(client: Arc<HorizonHttpClientInner>, request: R) -> HorizonHttpStream<R> {
+        let interrupted = request
+            .options()
+            .map(|options| Box::pin(options.interrupted(Instant::now())) as BoxFuture<_>);
+        HorizonHttpStream {
+            client,
+            request,
+            last_id: None,
+            response: None,
+            decoder: None,
+            interrupted,
+            done: false,
+        }
+    }
 
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+    fn poll_next_event(
+        &mut self,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<StreamEvent<R::Resource>>>> {
+        // The stream ends when cancelled or past its deadline.
+        if let Some(interrupted) = &mut self.interrupted {
+            if interrupted.as_mut().poll(cx).is_ready() {
+                self.interrupted = None;
+                self.response = None;
+                self.decoder = None;
+                self.done = true;
+            }
+        }
+        if self.done {
+            return Poll::Ready(None);
+        }
         loop {
             if self.response.is_none() && self.decoder.is_none() {
                 let uri = self.request.uri(&self.client.host)?;
-                let mut request_builder =
-                    self.client.get(uri).header("Accept", "text/event-stream");
+                let mut headers = self
+                    .request
+                    .options()
+                    .map(|options| options.headers().clone())
+                    .unwrap_or_default();
+                headers.insert(
+                    hyper::header::ACCEPT,
+                    hyper::header::HeaderValue::from_static("text/event-stream"),
+                );
                 if let Some(last_id) = &self.last_id {
-                    request_builder = request_builder.header("Last-Event-Id", last_id.clone());
+                    let last_id = last_id.parse().map_err(http::Error::from)?;
+                    headers.insert("Last-Event-Id", last_id);
                 }
 
-                let request = request_builder.body(Full::new(Bytes::new()))?;
-                let response = self.client.raw_request(request);
-                self.response = Some(response);
+                // Each reconnection gets fresh headers from the header provider.
+                let client = self.client.clone();
+                self.response = Some(Box::pin(async move {
+                    let response = client
+                        .send(hyper::Method::GET, uri, headers, Bytes::new())
+                        .await?;
+                    if !response.status().is_success() {
+                        return Err(response_error(response).await);
+                    }
+                    Ok(response)
+                }));
             }
 
             if let Some(mut resp) = self.response.take() {
@@ -285,15 +783,10 @@ where
                         self.response = Some(resp);
                         return Poll::Pending;
                     }
-                    Poll::Ready(Err(_e)) => {
-                        // Map legacy client error to a generic horizon server error.
-                        // The legacy error type from hyper-util doesn't implement Into<Error>,
-                        // and for our purposes a server-level failure is sufficient.
-                        return Poll::Ready(Some(Err(Error::HorizonServerError)));
+                    Poll::Ready(Err(err)) => {
+                        return Poll::Ready(Some(Err(err)));
                     }
                     Poll::Ready(Ok(resp)) => {
-                        // TODO(fra): handle non success statuses
-                        assert!(resp.status().is_success());
                         let body_stream = resp
                             .into_body()
                             .into_data_stream()
@@ -327,7 +820,8 @@ where
                                 if msg.name() == "message" {
                                     let result: R::Resource =
                                         serde_json::from_slice(&msg.into_bytes())?;
-                                    return Poll::Ready(Some(Ok(result)));
+                                    let event = StreamEvent::new(self.last_id.clone(), result);
+                                    return Poll::Ready(Some(Ok(event)));
                                 }
                             }
                             async_sse::Event::Retry(duration) => {
@@ -340,3 +834,84 @@ where
         }
     }
 }
+
+impl<R> Stream for HorizonHttpStream<R>
+where
+    R: StreamRequest,
+{
+    type Item = Result<R::Resource>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.poll_next_event(cx).map_ok(StreamEvent::into_resource)
+    }
+}
+
+impl<R> Stream for HorizonHttpEventStream<R>
+where
+    R: StreamRequest,
+{
+    type Item = Result<StreamEvent<R::Resource>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_event(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{ledger_json, MockServer};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_builder_headers() {
+        let server = MockServer::start(|_| (200, ledger_json(100, 0))).await;
+        let mut headers = hyper::HeaderMap::new();
+        headers.insert("x-api-key", "secret".parse().unwrap());
+        let client = HorizonHttpClient::builder(server.url().as_str())
+            .with_client_name("wallet")
+            .with_client_version("1.2.3")
+            .with_headers(headers)
+            .with_pool_max_idle_per_host(0)
+            .with_tcp_keepalive(Some(Duration::from_secs(30)))
+            .build()
+            .unwrap();
+        let (_, ledger) = client
+            .request(api::ledgers::single(100.into()))
+            .await
+            .unwrap();
+        assert_eq!(100, ledger.sequence.value());
+        let request = &server.requests()[0];
+        assert_eq!("wallet", request.headers["x-client-name"]);
+        assert_eq!("1.2.3", request.headers["x-client-version"]);
+        assert_eq!("secret", request.headers["x-api-key"]);
+    }
+
+    #[tokio::test]
+    async fn test_builder_proxy() {
+        // The proxy refuses the tunnel.
+        let proxy = MockServer::start(|_| (403, json!({}))).await;
+        let mut proxy_url = proxy.url().clone();
+        proxy_url.set_username("user").unwrap();
+        proxy_url.set_password(Some("p@ss")).unwrap();
+        let client = HorizonHttpClient::builder("https://horizon.example.org/")
+            .with_proxy(proxy_url.as_str())
+            .build()
+            .unwrap();
+        let result = client.request(api::ledgers::single(100.into())).await;
+        assert!(matches!(result, Err(Error::HorizonServerError)));
+        let request = &proxy.requests()[0];
+        assert_eq!("CONNECT", request.method);
+        assert_eq!("horizon.example.org:443", request.path);
+        // base64 of "user:p@ss".
+        assert_eq!("Basic dXNlcjpwQHNz", request.headers["proxy-authorization"]);
+    }
+
+    #[test]
+    fn test_builder_invalid_proxy() {
+        let result = HorizonHttpClient::builder("https://horizon.example.org/")
+            .with_proxy("not a url")
+            .build();
+        assert!(matches!(result, Err(Error::InvalidProxy)));
+    }
+}
@@ -0,0 +1,283 @@
+//! Share concurrent identical requests.
+//!
+//! A `CoalescingClient` wraps another client and sends a single request
+//! when several tasks request the same uri at the same time. Every
+//! waiter receives a copy of the result. Responses are shared as json
+//! and deserialized by each waiter, so they don't need to be `Clone`.
+//!
+//! Requests with a body, like transaction submissions, are never
+//! shared.
+//!
+//! ```rust
+//! use stellar_base::PublicKey;
+//! use stellar_horizon::api;
+//! use stellar_horizon::client::{HorizonClient, HorizonHttpClient};
+//! use stellar_horizon::coalesce::CoalescingClient;
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let client = CoalescingClient::new(client);
+//! let account = PublicKey::from_account_id("GA73S4WXZG7EONFCIFDSZ6VOJKFC2PMV5574YDJC4V4UBDGPAYN4SPAC")?;
+//! // Only one request is sent to horizon.
+//! let (first, second) = futures::join!(
+//!     client.request(api::accounts::single(&account)),
+//!     client.request(api::accounts::single(&account)),
+//! );
+//! println!("Sequence = {}", first?.1.sequence);
+//! # Ok(())
+//! # }
+//! ```
+use crate::client::HorizonClient;
+use crate::error::{Error, Result};
+use crate::headers::HeaderMap;
+use crate::request::{request_key, Request, StreamRequest, ValueRequest};
+use crate::stream::EventStream;
+use futures::future::BoxFuture;
+use futures::Stream;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+type SharedResult = std::result::Result<(HeaderMap, Value), Arc<Error>>;
+type InFlight = Mutex<HashMap<String, watch::Receiver<Option<SharedResult>>>>;
+
+/// Client that shares concurrent identical requests.
+pub struct CoalescingClient<C> {
+    inner: C,
+    in_flight: InFlight,
+}
+
+enum Role {
+    Leader(watch::Sender<Option<SharedResult>>),
+    Waiter(watch::Receiver<Option<SharedResult>>),
+}
+
+// Removes the in flight request when the leader completes or is
+// dropped, waiters then send the request again.
+struct InFlightGuard<'a> {
+    in_flight: &'a InFlight,
+    key: &'a str,
+}
+
+impl<C> CoalescingClient<C>
+where
+    C: HorizonClient,
+{
+    /// Creates a new coalescing client that sends requests with `inner`.
+    pub fn new(inner: C) -> CoalescingClient<C> {
+        CoalescingClient {
+            inner,
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns the number of requests in flight.
+    pub fn in_flight_count(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+
+    async fn coalesced_request<R: Request>(&self, req: R) -> Result<(HeaderMap, R::Response)> {
+        // Waiters would share the timeout and cancellation of the leader.
+        if req.post_body()?.is_some() || req.options().is_some() {
+            return self.inner.request(req).await;
+        }
+        let key = request_key(&req)?;
+        loop {
+            match self.role(&key) {
+                Role::Leader(sender) => {
+                    let guard = InFlightGuard {
+                        in_flight: &self.in_flight,
+                        key: &key,
+                    };
+                    let result = self
+                        .inner
+                        .request(ValueRequest(&req))
+                        .await
+                        .map_err(Arc::new);
+                    drop(guard);
+                    sender.send_replace(Some(result.clone()));
+                    return into_response(result);
+                }
+                Role::Waiter(mut receiver) => {
+                    if let Ok(result) = receiver.wait_for(Option::is_some).await {
+                        let result = result.clone().expect("waited for result");
+                        return into_response(result);
+                    }
+                }
+            }
+        }
+    }
+
+    fn role(&self, key: &str) -> Role {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        match in_flight.get(key) {
+            Some(receiver) => Role::Waiter(receiver.clone()),
+            None => {
+                let (sender, receiver) = watch::channel(None);
+                in_flight.insert(key.to_string(), receiver);
+                Role::Leader(sender)
+            }
+        }
+    }
+}
+
+impl<C> HorizonClient for CoalescingClient<C>
+where
+    C: HorizonClient + Sync,
+{
+    fn request<'a, R: Request + 'a>(
+        &'a self,
+        req: R,
+    ) -> BoxFuture<'a, Result<(HeaderMap, R::Response)>> {
+        Box::pin(self.coalesced_request(req))
+    }
+
+    fn stream<'a, R: StreamRequest + 'static>(
+        &'a self,
+        req: R,
+    ) -> Result<Box<dyn Stream<Item = Result<R::Resource>> + 'static + Send + Unpin>> {
+        self.inner.stream(req)
+    }
+
+    fn stream_events<R: StreamRequest + 'static>(
+        &self,
+        req: R,
+    ) -> Result<EventStream<R::Resource>> {
+        self.inner.stream_events(req)
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.in_flight.lock().unwrap().remove(self.key);
+    }
+}
+
+fn into_response<T>(result: SharedResult) -> Result<(HeaderMap, T)>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let (headers, value) = result.map_err(shared_error)?;
+    Ok((headers, serde_json::from_value(value)?))
+}
+
+/// Returns the error of a shared request.
+///
+/// Horizon errors are copied, other errors are wrapped.
+fn shared_error(err: Arc<Error>) -> Error {
+    match err.as_ref() {
+        Error::HorizonRequestError(horizon_error) => {
+            Error::HorizonRequestError(horizon_error.clone())
+        }
+        Error::HorizonServerError => Error::HorizonServerError,
+        _ => Error::CoalescedRequestFailed(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api;
+    use crate::mock::{ledger_json, MockClient};
+    use std::time::Duration;
+    use url::Url;
+
+    /// Waits before sending requests, so that they overlap.
+    struct SlowClient(MockClient);
+
+    impl HorizonClient for SlowClient {
+        fn request<'a, R: Request + 'a>(
+            &'a self,
+            req: R,
+        ) -> BoxFuture<'a, Result<(HeaderMap, R::Response)>> {
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                self.0.request(req).await
+            })
+        }
+
+        fn stream<'a, R: StreamRequest + 'static>(
+            &'a self,
+            req: R,
+        ) -> Result<Box<dyn Stream<Item = Result<R::Resource>> + 'static + Send + Unpin>> {
+            self.0.stream(req)
+        }
+    }
+
+    fn slow_client<F>(responder: F) -> CoalescingClient<SlowClient>
+    where
+        F: Fn(&Url) -> Option<Value> + Send + Sync + 'static,
+    {
+        CoalescingClient::new(SlowClient(MockClient::new(responder)))
+    }
+
+    #[tokio::test]
+    async fn test_share_concurrent_requests() {
+        let client = slow_client(|_| Some(ledger_json(7, 0)));
+        let requests = (0..5).map(|_| client.request(api::ledgers::single(7.into())));
+        let responses = futures::future::join_all(requests).await;
+        for response in responses {
+            assert_eq!(7, response.unwrap().1.sequence.value());
+        }
+        assert_eq!(1, client.inner().0.requests().len());
+        assert_eq!(0, client.in_flight_count());
+    }
+
+    #[tokio::test]
+    async fn test_send_different_requests() {
+        let client = slow_client(|_| Some(ledger_json(7, 0)));
+        let (first, second) = futures::join!(
+            client.request(api::ledgers::single(7.into())),
+            client.request(api::ledgers::single(8.into())),
+        );
+        first.unwrap();
+        second.unwrap();
+        assert_eq!(2, client.inner().0.requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_send_sequential_requests() {
+        let client = slow_client(|_| Some(ledger_json(7, 0)));
+        client
+            .request(api::ledgers::single(7.into()))
+            .await
+            .unwrap();
+        client
+            .request(api::ledgers::single(7.into()))
+            .await
+            .unwrap();
+        assert_eq!(2, client.inner().0.requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_share_errors() {
+        let client = slow_client(|_| None);
+        let (first, second) = futures::join!(
+            client.request(api::ledgers::single(7.into())),
+            client.request(api::ledgers::single(7.into())),
+        );
+        assert!(matches!(first, Err(Error::HorizonServerError)));
+        assert!(matches!(second, Err(Error::HorizonServerError)));
+        assert_eq!(1, client.inner().0.requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_leader_dropped() {
+        let client = slow_client(|_| Some(ledger_json(7, 0)));
+        let leader = tokio::time::timeout(
+            Duration::from_millis(1),
+            client.request(api::ledgers::single(7.into())),
+        );
+        let (leader, waiter) =
+            futures::join!(leader, client.request(api::ledgers::single(7.into())));
+        assert!(leader.is_err());
+        assert_eq!(7, waiter.unwrap().1.sequence.value());
+        assert_eq!(1, client.inner().0.requests().len());
+    }
+}
@@ -0,0 +1,501 @@
+//! Persistent cursors.
+//!
+//! Long running consumers need to resume from where they stopped
+//! after a restart. A `CursorStore` saves the cursor of the last
+//! processed record under a key, and the functions in this module
+//! load it to resume streams and pagers.
+//!
+//! Cursors are committed when the next item is requested, that is
+//! after the previous item was processed. If the consumer stops
+//! before that, the item is returned again after resuming, giving
+//! at-least-once semantics.
+//!
+//! The `_with_commit` variants save cursors in batches or at intervals
+//! instead, see `CommitPolicy`.
+//!
+//! ```rust
+//! use stellar_horizon::api;
+//! use stellar_horizon::client::HorizonHttpClient;
+//! use stellar_horizon::cursor::{self, FileCursorStore};
+//! use futures::stream::TryStreamExt;
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let store = FileCursorStore::new("cursors.json");
+//! let mut payments = cursor::stream_events(&client, &store, "payments", api::payments::all());
+//! while let Some(event) = payments.try_next().await? {
+//!     println!("Payment = {:?}", event.resource);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::client::HorizonClient;
+use crate::error::{Error, Result};
+use crate::page::Page;
+use crate::paging;
+use crate::request::{PageRequest, StreamRequest};
+use crate::resources::Record;
+use crate::stream::StreamEvent;
+use futures::future::{self, BoxFuture};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Store cursors by key.
+pub trait CursorStore: Send + Sync {
+    /// Returns the cursor saved under `key`, if any.
+    fn load<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<String>>>;
+    /// Saves `cursor` under `key`, replacing the previous one.
+    fn save<'a>(&'a self, key: &'a str, cursor: &'a str) -> BoxFuture<'a, Result<()>>;
+}
+
+/// A `CursorStore` that keeps cursors in memory.
+///
+/// Useful for testing, or to share cursors between consumers in the
+/// same process.
+#[derive(Debug, Default)]
+pub struct MemoryCursorStore {
+    cursors: Mutex<HashMap<String, String>>,
+}
+
+/// A `CursorStore` that saves cursors to a json file.
+///
+/// The file is replaced atomically on each save, so that it's never
+/// left half written.
+#[derive(Debug)]
+pub struct FileCursorStore {
+    path: PathBuf,
+    lock: tokio::sync::Mutex<()>,
+}
+
+/// When the cursor of processed items is saved.
+///
+/// Saving less often is cheaper, but more items are returned again
+/// after resuming. The cursor is always saved when the stream ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitPolicy {
+    /// Save the cursor of each item.
+    EachItem,
+    /// Save the cursor once every this many items.
+    Every(usize),
+    /// Save the cursor at most once per interval.
+    Interval(Duration),
+}
+
+impl MemoryCursorStore {
+    /// Creates a new, empty, store.
+    pub fn new() -> MemoryCursorStore {
+        Default::default()
+    }
+}
+
+impl CursorStore for MemoryCursorStore {
+    fn load<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        let cursor = self.cursors.lock().unwrap().get(key).cloned();
+        Box::pin(future::ready(Ok(cursor)))
+    }
+
+    fn save<'a>(&'a self, key: &'a str, cursor: &'a str) -> BoxFuture<'a, Result<()>> {
+        self.cursors
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), cursor.to_string());
+        Box::pin(future::ready(Ok(())))
+    }
+}
+
+impl FileCursorStore {
+    /// Creates a new store that saves cursors to `path`.
+    ///
+    /// The file is created on the first save.
+    pub fn new<P: Into<PathBuf>>(path: P) -> FileCursorStore {
+        FileCursorStore {
+            path: path.into(),
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    async fn read(&self) -> Result<HashMap<String, String>> {
+        match tokio::fs::read(&self.path).await {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn write(&self, cursors: &HashMap<String, String>) -> Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec(cursors)?).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+impl CursorStore for FileCursorStore {
+    fn load<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        Box::pin(async move {
+            let _guard = self.lock.lock().await;
+            Ok(self.read().await?.remove(key))
+        })
+    }
+
+    fn save<'a>(&'a self, key: &'a str, cursor: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let _guard = self.lock.lock().await;
+            let mut cursors = self.read().await?;
+            cursors.insert(key.to_string(), cursor.to_string());
+            self.write(&cursors).await
+        })
+    }
+}
+
+/// Returns a stream over the events of `request`, starting from the
+/// cursor saved under `key`.
+///
+/// The cursor of each event is saved when the next event is requested.
+/// If no cursor was saved, the request cursor is used.
+pub fn stream_events<'a, C, S, R>(
+    client: &'a C,
+    store: &'a S,
+    key: &'a str,
+    request: R,
+) -> BoxStream<'a, Result<StreamEvent<R::Resource>>>
+where
+    C: HorizonClient + Sync,
+    S: CursorStore + ?Sized,
+    R: StreamRequest + PageRequest + 'static,
+{
+    stream_events_with_commit(client, store, key, request, CommitPolicy::EachItem)
+}
+
+/// Like `stream_events`, but saves cursors according to `commit`.
+pub fn stream_events_with_commit<'a, C, S, R>(
+    client: &'a C,
+    store: &'a S,
+    key: &'a str,
+    request: R,
+    commit: CommitPolicy,
+) -> BoxStream<'a, Result<StreamEvent<R::Resource>>>
+where
+    C: HorizonClient + Sync,
+    S: CursorStore + ?Sized,
+    R: StreamRequest + PageRequest + 'static,
+{
+    let events = async move {
+        let request = match store.load(key).await? {
+            Some(cursor) => request.with_cursor(&cursor),
+            None => request,
+        };
+        client.stream_events(request)
+    };
+    let events = stream::once(events).try_flatten();
+    commit_cursors(store, key, events, commit, |event| {
+        event.cursor().map(str::to_string)
+    })
+}
+
+/// Returns a stream over all the records of `request`, starting from
+/// the cursor saved under `key`.
+///
+/// The paging token of each record is saved when the next record is
+/// requested, and after the last record.
+/// If no cursor was saved, the request cursor is used.
+pub fn records<'a, C, S, R, T>(
+    client: &'a C,
+    store: &'a S,
+    key: &'a str,
+    request: R,
+) -> BoxStream<'a, Result<T>>
+where
+    C: HorizonClient + Sync,
+    S: CursorStore + ?Sized,
+    R: PageRequest<Response = Page<T>> + Clone + 'a,
+    T: Record + DeserializeOwned + Serialize + Clone + Send + 'a,
+{
+    records_with_commit(client, store, key, request, CommitPolicy::EachItem)
+}
+
+/// Like `records`, but saves cursors according to `commit`.
+pub fn records_with_commit<'a, C, S, R, T>(
+    client: &'a C,
+    store: &'a S,
+    key: &'a str,
+    request: R,
+    commit: CommitPolicy,
+) -> BoxStream<'a, Result<T>>
+where
+    C: HorizonClient + Sync,
+    S: CursorStore + ?Sized,
+    R: PageRequest<Response = Page<T>> + Clone + 'a,
+    T: Record + DeserializeOwned + Serialize + Clone + Send + 'a,
+{
+    let records = async move {
+        let request = match store.load(key).await? {
+            Some(cursor) => request.with_cursor(&cursor),
+            None => request,
+        };
+        Ok::<_, Error>(paging::records(client, request))
+    };
+    let records = stream::once(records).try_flatten();
+    commit_cursors(store, key, records, commit, |record| {
+        Some(record.paging_token().to_string())
+    })
+}
+
+struct CommitState<St, F> {
+    stream: St,
+    cursor: F,
+    pending: Option<String>,
+    uncommitted: usize,
+    committed_at: Instant,
+}
+
+impl<St, F> CommitState<St, F> {
+    /// Saves the pending cursor, if any.
+    async fn commit<S>(&mut self, store: &S, key: &str) -> Result<()>
+    where
+        S: CursorStore + ?Sized,
+    {
+        if let Some(pending) = self.pending.take() {
+            store.save(key, &pending).await?;
+        }
+        self.uncommitted = 0;
+        self.committed_at = Instant::now();
+        Ok(())
+    }
+
+    fn should_commit(&self, commit: CommitPolicy) -> bool {
+        self.pending.is_some()
+            && match commit {
+                CommitPolicy::EachItem => true,
+                CommitPolicy::Every(items) => self.uncommitted >= items,
+                CommitPolicy::Interval(interval) => self.committed_at.elapsed() >= interval,
+            }
+    }
+}
+
+/// Saves the cursor of the items of `stream` according to `commit`,
+/// when the next item is requested, and when the stream ends or fails.
+///
+/// Errors of `stream` are returned without ending the stream, which
+/// only ends with `stream` or when the cursor can't be saved.
+fn commit_cursors<'a, S, St, T, F>(
+    store: &'a S,
+    key: &'a str,
+    stream: St,
+    commit: CommitPolicy,
+    cursor: F,
+) -> BoxStream<'a, Result<T>>
+where
+    S: CursorStore + ?Sized,
+    St: futures::Stream<Item = Result<T>> + Send + 'a,
+    T: Send + 'a,
+    F: Fn(&T) -> Option<String> + Send + 'a,
+{
+    let state = CommitState {
+        stream: Box::pin(stream),
+        cursor,
+        pending: None,
+        uncommitted: 0,
+        committed_at: Instant::now(),
+    };
+    stream::unfold(Some(state), move |state| async move {
+        let mut state = state?;
+        if state.should_commit(commit) {
+            if let Err(err) = state.commit(store, key).await {
+                return Some((Err(err), None));
+            }
+        }
+        match state.stream.next().await {
+            Some(Ok(item)) => {
+                if let Some(cursor) = (state.cursor)(&item) {
+                    state.pending = Some(cursor);
+                }
+                state.uncommitted += 1;
+                Some((Ok(item), Some(state)))
+            }
+            Some(Err(err)) => match state.commit(store, key).await {
+                Ok(()) => Some((Err(err), Some(state))),
+                Err(err) => Some((Err(err), None)),
+            },
+            None => match state.commit(store, key).await {
+                Ok(()) => None,
+                Err(err) => Some((Err(err), None)),
+            },
+        }
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api;
+    use crate::mock::{ledger_json, page_json, MockClient};
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    // Ledgers 1 to 5, as pages of two records or as a stream.
+    fn client(paged: bool) -> MockClient {
+        MockClient::new(move |uri| {
+            let query: HashMap<_, _> = uri.query_pairs().into_owned().collect();
+            let first = query
+                .get("cursor")
+                .map_or(1, |c| (c.parse::<i64>().unwrap() >> 32) as u32 + 1);
+            let records: Vec<_> = (first..(first + 2).min(6))
+                .map(|sequence| ledger_json(sequence, sequence as i64 * 5))
+                .collect();
+            if paged {
+                Some(page_json(records))
+            } else {
+                Some(json!(records))
+            }
+        })
+    }
+
+    fn sequences(ledgers: &[crate::resources::Ledger]) -> Vec<u32> {
+        ledgers.iter().map(|l| l.sequence.value()).collect()
+    }
+
+    #[test]
+    fn test_memory_cursor_store() {
+        let store = MemoryCursorStore::new();
+        assert_eq!(None, block_on(store.load("a")).unwrap());
+        block_on(store.save("a", "123")).unwrap();
+        assert_eq!(Some("123".to_string()), block_on(store.load("a")).unwrap());
+        assert_eq!(None, block_on(store.load("b")).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_file_cursor_store() {
+        let path = std::env::temp_dir().join(format!("cursors-{}.json", std::process::id()));
+        let store = FileCursorStore::new(&path);
+        assert_eq!(None, store.load("a").await.unwrap());
+        store.save("a", "123").await.unwrap();
+        store.save("b", "456").await.unwrap();
+
+        let store = FileCursorStore::new(&path);
+        assert_eq!(Some("123".to_string()), store.load("a").await.unwrap());
+        assert_eq!(Some("456".to_string()), store.load("b").await.unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_records_resume() {
+        let client = client(true);
+        let store = MemoryCursorStore::new();
+        let request = api::ledgers::all();
+
+        // Process two records, the second isn't committed.
+        let ledgers: Vec<_> = block_on(
+            records(&client, &store, "ledgers", request.clone())
+                .take(2)
+                .try_collect(),
+        )
+        .unwrap();
+        assert_eq!(vec![1, 2], sequences(&ledgers));
+
+        let ledgers: Vec<_> =
+            block_on(records(&client, &store, "ledgers", request).try_collect()).unwrap();
+        assert_eq!(vec![2, 3, 4, 5], sequences(&ledgers));
+        let cursor = block_on(store.load("ledgers")).unwrap();
+        assert_eq!(Some((5i64 << 32).to_string()), cursor);
+    }
+
+    #[test]
+    fn test_stream_events_resume() {
+        let client = client(false);
+        let store = MemoryCursorStore::new();
+        block_on(store.save("ledgers", &(2i64 << 32).to_string())).unwrap();
+
+        let events: Vec<_> =
+            block_on(stream_events(&client, &store, "ledgers", api::ledgers::all()).try_collect())
+                .unwrap();
+        let ledgers: Vec<_> = events.into_iter().map(StreamEvent::into_resource).collect();
+        assert_eq!(vec![3, 4], sequences(&ledgers));
+        let cursor = block_on(store.load("ledgers")).unwrap();
+        assert_eq!(Some((4i64 << 32).to_string()), cursor);
+    }
+
+    // Records the saved cursors.
+    #[derive(Default)]
+    struct RecordingStore {
+        saved: Mutex<Vec<String>>,
+    }
+
+    impl CursorStore for RecordingStore {
+        fn load<'a>(&'a self, _key: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+            Box::pin(future::ready(Ok(None)))
+        }
+
+        fn save<'a>(&'a self, _key: &'a str, cursor: &'a str) -> BoxFuture<'a, Result<()>> {
+            self.saved.lock().unwrap().push(cursor.to_string());
+            Box::pin(future::ready(Ok(())))
+        }
+    }
+
+    fn saved_sequences(store: &RecordingStore) -> Vec<i64> {
+        store
+            .saved
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|cursor| cursor.parse::<i64>().unwrap() >> 32)
+            .collect()
+    }
+
+    #[test]
+    fn test_commit_every_items() {
+        let client = client(true);
+        let store = RecordingStore::default();
+        let commit = CommitPolicy::Every(2);
+        let ledgers: Vec<_> = block_on(
+            records_with_commit(&client, &store, "ledgers", api::ledgers::all(), commit)
+                .try_collect(),
+        )
+        .unwrap();
+        assert_eq!(vec![1, 2, 3, 4, 5], sequences(&ledgers));
+        // Every two records, and after the last.
+        assert_eq!(vec![2, 4, 5], saved_sequences(&store));
+    }
+
+    #[test]
+    fn test_commit_interval() {
+        let client = client(false);
+        let store = RecordingStore::default();
+        let commit = CommitPolicy::Interval(Duration::from_secs(3600));
+        let events: Vec<_> = block_on(
+            stream_events_with_commit(&client, &store, "ledgers", api::ledgers::all(), commit)
+                .try_collect(),
+        )
+        .unwrap();
+        assert_eq!(2, events.len());
+        // Only when the stream ends.
+        assert_eq!(vec![2], saved_sequences(&store));
+    }
+
+    #[test]
+    fn test_commit_continues_after_errors() {
+        let store = RecordingStore::default();
+        let items = stream::iter(vec![
+            Ok(1i64 << 32),
+            Err(Error::HorizonServerError),
+            Ok(2i64 << 32),
+        ]);
+        let items = commit_cursors(&store, "ledgers", items, CommitPolicy::Every(10), |item| {
+            Some(item.to_string())
+        });
+        let items: Vec<Result<i64>> = block_on(items.collect());
+        assert_eq!(3, items.len());
+        assert!(matches!(items[1], Err(Error::HorizonServerError)));
+        assert_eq!(2i64 << 32, *items[2].as_ref().unwrap());
+        // When the stream fails, and after the last item.
+        assert_eq!(vec![1, 2], saved_sequences(&store));
+    }
+}
@@ -0,0 +1,407 @@
+//! Detect deposits to an account.
+//!
+//! Exchanges receive deposits on a shared account and route them to
+//! customers either by the transaction memo or by the id of the muxed
+//! account the payment was sent to. A `DepositMonitor` resolves the
+//! payments received by the account to a `DepositKey`, and looks up the
+//! customer with that key.
+//!
+//! Payments without a key, or with a key that doesn't match any
+//! customer, are reported too, so that they can be refunded or handled
+//! manually.
+//!
+//! ```rust
+//! use stellar_base::PublicKey;
+//! use stellar_horizon::client::HorizonHttpClient;
+//! use stellar_horizon::deposit::{DepositKey, DepositMonitor, DepositStatus};
+//! use futures::stream::TryStreamExt;
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let account = PublicKey::from_account_id("GA73S4WXZG7EONFCIFDSZ6VOJKFC2PMV5574YDJC4V4UBDGPAYN4SPAC")?;
+//! let monitor = DepositMonitor::new(&account, |key: &DepositKey| match key {
+//!     DepositKey::MemoId(id) | DepositKey::MuxedId(id) => Some(*id),
+//!     _ => None,
+//! });
+//! let mut deposits = monitor.stream(&client, "now")?;
+//! while let Some(deposit) = deposits.try_next().await? {
+//!     match deposit.status {
+//!         DepositStatus::Resolved { customer, .. } => println!("Customer = {}", customer),
+//!         _ => println!("Unresolved deposit = {:?}", deposit.payment),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::api::{self, payments::PaymentsForAccountRequest, Join};
+use crate::client::HorizonClient;
+use crate::error::Result;
+use crate::request::PageRequest;
+use crate::resources::{Asset, Payment, Transaction};
+use futures::stream::{BoxStream, Stream, StreamExt, TryStreamExt};
+use stellar_base::crypto::PublicKey;
+
+/// The key used to route a deposit to a customer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DepositKey {
+    /// The id of the muxed account the payment was sent to.
+    MuxedId(u64),
+    /// A `MEMO_ID` memo.
+    MemoId(u64),
+    /// A `MEMO_TEXT` memo.
+    MemoText(String),
+    /// A `MEMO_HASH` memo, base64 encoded.
+    MemoHash(String),
+    /// A `MEMO_RETURN` memo, base64 encoded.
+    MemoReturn(String),
+    /// A `MEMO_ID` memo that is not a valid id, as received.
+    InvalidMemoId(String),
+}
+
+/// The result of resolving a deposit to a customer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DepositStatus<K> {
+    /// The deposit key matches a customer.
+    Resolved { key: DepositKey, customer: K },
+    /// The deposit key doesn't match any customer.
+    UnknownKey(DepositKey),
+    /// The payment has no memo and was not sent to a muxed account.
+    MissingKey,
+}
+
+/// A payment received by the monitored account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deposit<K> {
+    /// The customer the deposit belongs to, if any.
+    pub status: DepositStatus<K>,
+    /// The asset received.
+    pub asset: Asset,
+    /// The amount received.
+    pub amount: String,
+    /// The payment operation.
+    pub payment: Payment,
+}
+
+type Resolver<K> = Box<dyn Fn(&DepositKey) -> Option<K> + Send + Sync>;
+
+/// Resolve the payments received by an account to customers.
+pub struct DepositMonitor<K> {
+    account: PublicKey,
+    resolver: Resolver<K>,
+}
+
+impl<K> Deposit<K> {
+    /// Returns the deposit key, if any.
+    pub fn key(&self) -> Option<&DepositKey> {
+        match &self.status {
+            DepositStatus::Resolved { key, .. } => Some(key),
+            DepositStatus::UnknownKey(key) => Some(key),
+            DepositStatus::MissingKey => None,
+        }
+    }
+
+    /// Returns the customer the deposit belongs to, if any.
+    pub fn customer(&self) -> Option<&K> {
+        match &self.status {
+            DepositStatus::Resolved { customer, .. } => Some(customer),
+            _ => None,
+        }
+    }
+}
+
+impl<K> DepositMonitor<K>
+where
+    K: Send + Sync,
+{
+    /// Creates a new monitor of the deposits to `account`.
+    ///
+    /// `resolver` returns the customer with the given key, or `None` if
+    /// the key is unknown.
+    pub fn new<F>(account: &PublicKey, resolver: F) -> DepositMonitor<K>
+    where
+        F: Fn(&DepositKey) -> Option<K> + Send + Sync + 'static,
+    {
+        DepositMonitor {
+            account: *account,
+            resolver: Box::new(resolver),
+        }
+    }
+
+    /// Returns the request for the account payments, joined with their
+    /// transactions.
+    pub fn request(&self) -> PaymentsForAccountRequest {
+        api::payments::for_account(&self.account).with_join(Join::Transactions)
+    }
+
+    /// Returns a stream of the deposits to the account, starting from
+    /// `cursor`.
+    pub fn stream<'a, C>(
+        &'a self,
+        client: &'a C,
+        cursor: &str,
+    ) -> Result<BoxStream<'a, Result<Deposit<K>>>>
+    where
+        C: HorizonClient + Sync,
+    {
+        let payments = client.stream(self.request().with_cursor(cursor))?;
+        Ok(self.deposits(client, payments))
+    }
+
+    /// Returns the deposits in a stream of the account payments.
+    ///
+    /// Payments sent by the account and failed payments are skipped.
+    /// The transaction of payments that were not joined with their
+    /// transaction is fetched with `client`.
+    pub fn deposits<'a, C, S>(
+        &'a self,
+        client: &'a C,
+        payments: S,
+    ) -> BoxStream<'a, Result<Deposit<K>>>
+    where
+        C: HorizonClient + Sync,
+        S: Stream<Item = Result<Payment>> + Send + 'a,
+    {
+        payments
+            .try_filter_map(move |payment| self.resolve(client, payment))
+            .boxed()
+    }
+
+    /// Returns the deposit of `payment`, or `None` if the payment was
+    /// not received by the account.
+    ///
+    /// The memo is read from the joined transaction, payments without
+    /// a transaction are considered without memo.
+    pub fn deposit(&self, payment: Payment) -> Option<Deposit<K>> {
+        let muxed_id = self.received(&payment)?;
+        let key = muxed_id.or_else(|| payment.base().transaction.as_ref().and_then(memo_key));
+        self.new_deposit(payment, key)
+    }
+
+    async fn resolve<C>(&self, client: &C, payment: Payment) -> Result<Option<Deposit<K>>>
+    where
+        C: HorizonClient + Sync,
+    {
+        let muxed_id = match self.received(&payment) {
+            None => return Ok(None),
+            Some(muxed_id) => muxed_id,
+        };
+        let key = match (muxed_id, &payment.base().transaction) {
+            (Some(key), _) => Some(key),
+            (None, Some(transaction)) => memo_key(transaction),
+            (None, None) => {
+                let request = api::transactions::single(&payment.base().transaction_hash);
+                let (_, transaction) = client.request(request).await?;
+                memo_key(&transaction)
+            }
+        };
+        Ok(self.new_deposit(payment, key))
+    }
+
+    /// Returns the muxed id key of a successful payment to the monitored
+    /// account, or `None` if the payment is not a deposit.
+    fn received(&self, payment: &Payment) -> Option<Option<DepositKey>> {
+        if !payment.base().transaction_successful {
+            return None;
+        }
+        let (to, to_muxed_id) = match payment {
+            Payment::CreateAccount(op) => (op.account.as_str(), None),
+            Payment::Payment(op) => (op.to.as_str(), op.to_muxed_id.as_ref()),
+            Payment::PathPaymentStrictReceive(op) => (op.to.as_str(), op.to_muxed_id.as_ref()),
+            Payment::PathPaymentStrictSend(op) => (op.to.as_str(), op.to_muxed_id.as_ref()),
+            Payment::AccountMerge(_) | Payment::InvokeHostFunction(_) => return None,
+        };
+        if to != self.account.account_id() {
+            return None;
+        }
+        let muxed_id = to_muxed_id
+            .and_then(|id| id.parse().ok())
+            .map(DepositKey::MuxedId);
+        Some(muxed_id)
+    }
+
+    /// Returns the deposit of `payment`, or `None` if the payment
+    /// doesn't transfer an asset.
+    fn new_deposit(&self, payment: Payment, key: Option<DepositKey>) -> Option<Deposit<K>> {
+        let status = match key {
+            None => DepositStatus::MissingKey,
+            Some(key @ DepositKey::InvalidMemoId(_)) => DepositStatus::UnknownKey(key),
+            Some(key) => match (self.resolver)(&key) {
+                Some(customer) => DepositStatus::Resolved { key, customer },
+                None => DepositStatus::UnknownKey(key),
+            },
+        };
+        let (asset, amount) = match &payment {
+            Payment::CreateAccount(op) => (native_asset(), op.starting_balance.clone()),
+            Payment::Payment(op) => (op.asset.clone(), op.amount.clone()),
+            Payment::PathPaymentStrictReceive(op) => (op.asset.clone(), op.amount.clone()),
+            Payment::PathPaymentStrictSend(op) => (op.asset.clone(), op.amount.clone()),
+            Payment::AccountMerge(_) | Payment::InvokeHostFunction(_) => return None,
+        };
+        Some(Deposit {
+            status,
+            asset,
+            amount,
+            payment,
+        })
+    }
+}
+
+/// Returns the deposit key of the transaction memo.
+fn memo_key(transaction: &Transaction) -> Option<DepositKey> {
+    let memo = transaction
+        .memo
+        .as_deref()
+        .filter(|memo| !memo.is_empty())?;
+    match transaction
+        .memo_type
+        .to_lowercase()
+        .trim_start_matches("memo_")
+    {
+        "id" => match memo.parse() {
+            Ok(id) => Some(DepositKey::MemoId(id)),
+            Err(_) => Some(DepositKey::InvalidMemoId(memo.to_string())),
+        },
+        "text" => Some(DepositKey::MemoText(memo.to_string())),
+        "hash" => Some(DepositKey::MemoHash(memo.to_string())),
+        "return" => Some(DepositKey::MemoReturn(memo.to_string())),
+        _ => None,
+    }
+}
+
+fn native_asset() -> Asset {
+    Asset {
+        asset_type: "native".to_string(),
+        asset_code: None,
+        asset_issuer: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{payment_json, MockClient};
+    use futures::executor::block_on;
+    use serde_json::{json, Value};
+
+    const EXCHANGE: &str = "GA73S4WXZG7EONFCIFDSZ6VOJKFC2PMV5574YDJC4V4UBDGPAYN4SPAC";
+    const CUSTOMER: &str = "GBZZY5CHD6CWL7YVJ52JQANGPVFEQV3ULU3RMAZ3RKFS6M7ZQWGHMO4C";
+
+    fn transaction_json(memo_type: &str, memo: Option<&str>) -> Value {
+        let transactions: Value =
+            serde_json::from_str(include_str!("../tests/fixtures/all_transactions.json")).unwrap();
+        let mut transaction = transactions["_embedded"]["records"][0].clone();
+        transaction["memo_type"] = json!(memo_type);
+        transaction["memo"] = json!(memo);
+        transaction
+    }
+
+    fn payment(to: &str, transaction: Option<Value>) -> Value {
+        let mut payment = payment_json(CUSTOMER, to, 100);
+        if let Some(transaction) = transaction {
+            payment["transaction"] = transaction;
+        }
+        payment
+    }
+
+    fn monitor() -> DepositMonitor<&'static str> {
+        let account = PublicKey::from_account_id(EXCHANGE).unwrap();
+        DepositMonitor::new(&account, |key| match key {
+            DepositKey::MemoId(42) | DepositKey::MuxedId(42) => Some("alice"),
+            DepositKey::MemoText(text) if text == "bob" => Some("bob"),
+            _ => None,
+        })
+    }
+
+    fn deposit_of(payment: Value) -> Option<Deposit<&'static str>> {
+        monitor().deposit(serde_json::from_value(payment).unwrap())
+    }
+
+    #[test]
+    fn test_deposit_by_memo() {
+        let deposit =
+            deposit_of(payment(EXCHANGE, Some(transaction_json("id", Some("42"))))).unwrap();
+        assert_eq!(Some(&"alice"), deposit.customer());
+        assert_eq!(Some(&DepositKey::MemoId(42)), deposit.key());
+        assert_eq!("1.0000000", deposit.amount);
+        assert_eq!("native", deposit.asset.asset_type);
+
+        let deposit = deposit_of(payment(
+            EXCHANGE,
+            Some(transaction_json("text", Some("bob"))),
+        ))
+        .unwrap();
+        assert_eq!(Some(&"bob"), deposit.customer());
+    }
+
+    #[test]
+    fn test_deposit_by_muxed_id() {
+        let mut payment = payment(EXCHANGE, Some(transaction_json("none", None)));
+        payment["to_muxed_id"] = json!("42");
+        let deposit = deposit_of(payment).unwrap();
+        assert_eq!(Some(&DepositKey::MuxedId(42)), deposit.key());
+        assert_eq!(Some(&"alice"), deposit.customer());
+    }
+
+    #[test]
+    fn test_unresolved_deposits() {
+        let deposit = deposit_of(payment(EXCHANGE, Some(transaction_json("none", None)))).unwrap();
+        assert_eq!(DepositStatus::MissingKey, deposit.status);
+
+        let deposit =
+            deposit_of(payment(EXCHANGE, Some(transaction_json("text", Some(""))))).unwrap();
+        assert_eq!(DepositStatus::MissingKey, deposit.status);
+
+        let deposit = deposit_of(payment(
+            EXCHANGE,
+            Some(transaction_json("text", Some("eve"))),
+        ))
+        .unwrap();
+        assert_eq!(
+            DepositStatus::UnknownKey(DepositKey::MemoText("eve".to_string())),
+            deposit.status
+        );
+
+        let deposit = deposit_of(payment(
+            EXCHANGE,
+            Some(transaction_json("id", Some("alice"))),
+        ))
+        .unwrap();
+        assert_eq!(
+            DepositStatus::UnknownKey(DepositKey::InvalidMemoId("alice".to_string())),
+            deposit.status
+        );
+    }
+
+    #[test]
+    fn test_skip_outgoing_and_failed_payments() {
+        assert!(deposit_of(payment(CUSTOMER, Some(transaction_json("id", Some("42"))))).is_none());
+
+        let mut failed = payment(EXCHANGE, Some(transaction_json("id", Some("42"))));
+        failed["transaction_successful"] = json!(false);
+        assert!(deposit_of(failed).is_none());
+    }
+
+    #[test]
+    fn test_stream_fetches_missing_transactions() {
+        let client = MockClient::new(|uri| {
+            if uri.path().starts_with("/transactions/") {
+                Some(transaction_json("id", Some("42")))
+            } else {
+                Some(json!([
+                    payment(EXCHANGE, None),
+                    payment(CUSTOMER, None),
+                    payment(EXCHANGE, Some(transaction_json("text", Some("bob")))),
+                ]))
+            }
+        });
+        let monitor = monitor();
+        let deposits: Vec<_> =
+            block_on(monitor.stream(&client, "now").unwrap().try_collect()).unwrap();
+        let customers: Vec<_> = deposits.iter().map(|d| d.customer().copied()).collect();
+        assert_eq!(vec![Some("alice"), Some("bob")], customers);
+
+        let requests = client.requests();
+        assert_eq!(2, requests.len());
+        assert!(requests[0].query().unwrap().contains("join=transactions"));
+    }
+}
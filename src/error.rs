@@ -15,6 +15,11 @@ pub enum Error {
     HttpError(#[from] http::Error),
     #[error("hyper error")]
     HyperError(#[from] hyper::Error),
+    #[error("io error")]
+    IoError(#[from] std::io::Error),
+    #[cfg(feature = "indexer")]
+    #[error("sqlite error")]
+    SqliteError(#[from] rusqlite::Error),
     #[error("json error")]
     JsonError(#[from] serde_json::error::Error),
     #[error("invalid uri")]
@@ -23,6 +28,55 @@ pub enum Error {
     InvalidUrl(#[from] url::ParseError),
     #[error("invalid host")]
     InvalidHost,
+    #[error("invalid proxy")]
+    InvalidProxy,
+    #[error("tls error")]
+    TlsError(#[from] native_tls::Error),
     #[error("invalid predicate")]
     InvalidPredicate,
+    #[error("invalid ledger sequence")]
+    InvalidLedgerSequence,
+    #[error("invalid transaction hash")]
+    InvalidTransactionHash,
+    #[error("invalid operation id")]
+    InvalidOperationId,
+    #[error("invalid offer id")]
+    InvalidOfferId,
+    #[error("invalid liquidity pool id")]
+    InvalidLiquidityPoolId,
+    #[error("invalid claimable balance id")]
+    InvalidClaimableBalanceId,
+    #[error("invalid toid")]
+    InvalidToid,
+    #[error("paging token {current} out of order after {previous}")]
+    PagingTokenOutOfOrder {
+        previous: crate::resources::PagingToken,
+        current: crate::resources::PagingToken,
+    },
+    #[error("ledger gap, expected {expected} but got {found}")]
+    LedgerGap {
+        expected: crate::resources::LedgerSequence,
+        found: crate::resources::LedgerSequence,
+    },
+    #[error("ledger {sequence} previous hash doesn't match")]
+    LedgerHashMismatch {
+        sequence: crate::resources::LedgerSequence,
+    },
+    #[error("stream subscriber lagged behind by {0} events")]
+    StreamLagged(u64),
+    #[error("webhook delivery failed: {0}")]
+    WebhookDeliveryFailed(String),
+    #[error("coalesced request failed")]
+    CoalescedRequestFailed(#[source] std::sync::Arc<Error>),
+    #[error("rate limit exceeded")]
+    RateLimitExceeded {
+        /// The time until the rate limit window resets, if known.
+        reset: Option<std::time::Duration>,
+    },
+    #[error("request timed out")]
+    RequestTimeout,
+    #[error("request cancelled")]
+    RequestCancelled,
+    #[error("network passphrase mismatch, expected {expected:?} but horizon uses {found:?}")]
+    NetworkMismatch { expected: String, found: String },
 }
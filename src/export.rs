@@ -0,0 +1,746 @@
+//! Export records to NDJSON and CSV.
+//!
+//! `write_ndjson` writes each record on its own line, using the same
+//! json representation as Horizon, so no information is lost.
+//!
+//! `write_csv` writes one row per record, with the columns returned by
+//! `CsvRecord::csv_header`. Each kind of record has a fixed set of
+//! columns, operations and effects of different types share the same
+//! columns so that they can be written to the same file: the columns of
+//! the fields of a type, like `path` or `limit`, are empty in the rows
+//! of the other types. Assets are written as `native` or `CODE:ISSUER`,
+//! paths as a list of assets separated by `;`. Fields without a column
+//! are written as a json object in the last, `details`, column.
+//!
+//! Cells starting with `=`, `+`, `-` or `@`, like a memo chosen by the
+//! sender, are prefixed with `'` so that spreadsheets show them as text
+//! instead of running them as formulas.
+//!
+//! ```rust
+//! use stellar_base::PublicKey;
+//! use stellar_horizon::api;
+//! use stellar_horizon::client::HorizonHttpClient;
+//! use stellar_horizon::{export, paging};
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let account = PublicKey::from_account_id("GA73S4WXZG7EONFCIFDSZ6VOJKFC2PMV5574YDJC4V4UBDGPAYN4SPAC")?;
+//! let payments = paging::records(&client, api::payments::for_account(&account));
+//! let file = std::fs::File::create("payments.csv")?;
+//! export::write_csv(payments, file).await?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::error::Result;
+use crate::resources::{Effect, Operation, Payment, Trade, Transaction};
+use futures::stream::{Stream, StreamExt};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::io::Write;
+
+const OPERATION_HEADER: &[&str] = &[
+    "id",
+    "paging_token",
+    "type",
+    "created_at",
+    "transaction_hash",
+    "transaction_successful",
+    "source_account",
+    "from",
+    "to",
+    "asset",
+    "amount",
+    "source_asset",
+    "source_amount",
+    "source_max",
+    "destination_min",
+    "path",
+    "selling_asset",
+    "buying_asset",
+    "price",
+    "offer_id",
+    "limit",
+    "trustor",
+    "trustee",
+    "liquidity_pool_id",
+    "balance_id",
+    "details",
+];
+
+const EFFECT_HEADER: &[&str] = &[
+    "id",
+    "paging_token",
+    "type",
+    "created_at",
+    "account",
+    "asset",
+    "amount",
+    "limit",
+    "trustor",
+    "seller",
+    "offer_id",
+    "sold_asset",
+    "sold_amount",
+    "bought_asset",
+    "bought_amount",
+    "liquidity_pool_id",
+    "balance_id",
+    "details",
+];
+
+const TRANSACTION_HEADER: &[&str] = &[
+    "id",
+    "paging_token",
+    "ledger",
+    "created_at",
+    "source_account",
+    "fee_account",
+    "fee_charged",
+    "max_fee",
+    "operation_count",
+    "successful",
+    "memo_type",
+    "memo",
+    "details",
+];
+
+const TRADE_HEADER: &[&str] = &[
+    "id",
+    "paging_token",
+    "ledger_close_time",
+    "trade_type",
+    "base_account",
+    "base_asset",
+    "base_amount",
+    "counter_account",
+    "counter_asset",
+    "counter_amount",
+    "base_is_seller",
+    "price",
+    "details",
+];
+
+/// A record that can be written as a CSV row.
+pub trait CsvRecord: Serialize {
+    /// Returns the names of the CSV columns.
+    fn csv_header() -> &'static [&'static str];
+    /// Returns the CSV cells, in the same order as the header.
+    fn csv_row(&self) -> Result<Vec<String>>;
+}
+
+/// Writes the records of `records` to `writer` as NDJSON, returns the
+/// number of records written.
+pub async fn write_ndjson<S, T, W>(records: S, mut writer: W) -> Result<usize>
+where
+    S: Stream<Item = Result<T>>,
+    T: Serialize,
+    W: Write,
+{
+    let mut records = Box::pin(records);
+    let mut count = 0;
+    while let Some(record) = records.next().await {
+        serde_json::to_writer(&mut writer, &record?)?;
+        writer.write_all(b"\n")?;
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+/// Writes the records of `records` to `writer` as CSV, starting with the
+/// header, returns the number of records written.
+pub async fn write_csv<S, T, W>(records: S, mut writer: W) -> Result<usize>
+where
+    S: Stream<Item = Result<T>>,
+    T: CsvRecord,
+    W: Write,
+{
+    let mut records = Box::pin(records);
+    write_csv_line(&mut writer, T::csv_header())?;
+    let mut count = 0;
+    while let Some(record) = records.next().await {
+        write_csv_line(&mut writer, &record?.csv_row()?)?;
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+impl CsvRecord for Operation {
+    fn csv_header() -> &'static [&'static str] {
+        OPERATION_HEADER
+    }
+
+    fn csv_row(&self) -> Result<Vec<String>> {
+        operation_row(self)
+    }
+}
+
+impl CsvRecord for Payment {
+    fn csv_header() -> &'static [&'static str] {
+        OPERATION_HEADER
+    }
+
+    fn csv_row(&self) -> Result<Vec<String>> {
+        operation_row(self)
+    }
+}
+
+impl CsvRecord for Effect {
+    fn csv_header() -> &'static [&'static str] {
+        EFFECT_HEADER
+    }
+
+    fn csv_row(&self) -> Result<Vec<String>> {
+        let mut fields = Fields::new(self)?;
+        // Claimable balance and sponsorship effects have the asset as a
+        // string, the other effects as separate fields.
+        let asset = match fields.remove("asset") {
+            Value::Null => fields.take_asset(""),
+            asset => {
+                fields.remove("asset_type");
+                cell(&asset)
+            }
+        };
+        let (sold_asset, sold_amount) = fields.take_asset_amount("sold");
+        let (bought_asset, bought_amount) = fields.take_asset_amount("bought");
+        Ok(vec![
+            fields.take("id"),
+            fields.take("paging_token"),
+            fields.take("type"),
+            fields.take("created_at"),
+            fields.take("account"),
+            asset,
+            fields.take("amount"),
+            fields.take("limit"),
+            fields.take("trustor"),
+            fields.take("seller"),
+            fields.take("offer_id"),
+            sold_asset,
+            sold_amount,
+            bought_asset,
+            bought_amount,
+            fields.take_liquidity_pool_id(),
+            fields.take("balance_id"),
+            fields.details(),
+        ])
+    }
+}
+
+impl CsvRecord for Transaction {
+    fn csv_header() -> &'static [&'static str] {
+        TRANSACTION_HEADER
+    }
+
+    fn csv_row(&self) -> Result<Vec<String>> {
+        let mut fields = Fields::new(self)?;
+        Ok(vec![
+            fields.take("id"),
+            fields.take("paging_token"),
+            fields.take("ledger"),
+            fields.take("created_at"),
+            fields.take("source_account"),
+            fields.take("fee_account"),
+            fields.take("fee_charged"),
+            fields.take("max_fee"),
+            fields.take("operation_count"),
+            fields.take("successful"),
+            fields.take("memo_type"),
+            fields.take("memo"),
+            fields.details(),
+        ])
+    }
+}
+
+impl CsvRecord for Trade {
+    fn csv_header() -> &'static [&'static str] {
+        TRADE_HEADER
+    }
+
+    fn csv_row(&self) -> Result<Vec<String>> {
+        let mut fields = Fields::new(self)?;
+        let price = match fields.remove("price") {
+            Value::Object(price) => format!("{}/{}", cell(&price["n"]), cell(&price["d"])),
+            _ => String::new(),
+        };
+        Ok(vec![
+            fields.take("id"),
+            fields.take("paging_token"),
+            fields.take("ledger_close_time"),
+            fields.take("trade_type"),
+            fields.take("base_account"),
+            fields.take_asset("base_"),
+            fields.take("base_amount"),
+            fields.take("counter_account"),
+            fields.take_asset("counter_"),
+            fields.take("counter_amount"),
+            fields.take("base_is_seller"),
+            price,
+            fields.details(),
+        ])
+    }
+}
+
+/// Returns the row of an operation, with the account sending funds in
+/// `from`, the account receiving them in `to`, and the amount moved.
+fn operation_row<T: Serialize>(operation: &T) -> Result<Vec<String>> {
+    let mut fields = Fields::new(operation)?;
+    let (from, to, amount) = match fields.get("type").as_str() {
+        "create_account" => ("funder", "account", "starting_balance"),
+        "account_merge" => ("account", "into", ""),
+        "claim_claimable_balance" => ("", "claimant", ""),
+        "clawback" => ("from", "", "amount"),
+        _ => ("from", "to", "amount"),
+    };
+    Ok(vec![
+        fields.take("id"),
+        fields.take("paging_token"),
+        fields.take("type"),
+        fields.take("created_at"),
+        fields.take("transaction_hash"),
+        fields.take("transaction_successful"),
+        fields.take("source_account"),
+        fields.take(from),
+        fields.take(to),
+        fields.take_asset(""),
+        fields.take(amount),
+        fields.take_asset("source_"),
+        fields.take("source_amount"),
+        fields.take("source_max"),
+        fields.take("destination_min"),
+        fields.take_path("path"),
+        fields.take_asset("selling_"),
+        fields.take_asset("buying_"),
+        fields.take("price"),
+        fields.take("offer_id"),
+        fields.take("limit"),
+        fields.take("trustor"),
+        fields.take("trustee"),
+        fields.take("liquidity_pool_id"),
+        fields.take("balance_id"),
+        fields.details(),
+    ])
+}
+
+/// The top level fields of a record json.
+struct Fields(Map<String, Value>);
+
+impl Fields {
+    fn new<T: Serialize>(record: &T) -> Result<Fields> {
+        let mut fields = match serde_json::to_value(record)? {
+            Value::Object(fields) => fields,
+            _ => Map::new(),
+        };
+        fields.remove("_links");
+        fields.remove("type_i");
+        Ok(Fields(fields))
+    }
+
+    fn get(&self, name: &str) -> String {
+        self.0.get(name).map(cell).unwrap_or_default()
+    }
+
+    fn remove(&mut self, name: &str) -> Value {
+        self.0.remove(name).unwrap_or(Value::Null)
+    }
+
+    /// Removes the field `name` and returns its cell.
+    fn take(&mut self, name: &str) -> String {
+        cell(&self.remove(name))
+    }
+
+    /// Removes the asset fields starting with `prefix` and returns the
+    /// asset as `native` or `CODE:ISSUER`.
+    ///
+    /// Assets without a code, like liquidity pool shares, are returned as
+    /// their type.
+    fn take_asset(&mut self, prefix: &str) -> String {
+        let asset_type = self.take(&format!("{}asset_type", prefix));
+        let code = self.take(&format!("{}asset_code", prefix));
+        let issuer = self.take(&format!("{}asset_issuer", prefix));
+        if code.is_empty() {
+            asset_type
+        } else if issuer.is_empty() {
+            code
+        } else {
+            format!("{}:{}", code, issuer)
+        }
+    }
+
+    /// Removes the list of assets `name` and returns the assets separated
+    /// by `;`.
+    fn take_path(&mut self, name: &str) -> String {
+        match self.remove(name) {
+            Value::Array(assets) => assets
+                .into_iter()
+                .map(|asset| match asset {
+                    Value::Object(asset) => Fields(asset).take_asset(""),
+                    asset => cell(&asset),
+                })
+                .collect::<Vec<_>>()
+                .join(";"),
+            path => cell(&path),
+        }
+    }
+
+    /// Removes the asset and amount `name`, either an object with an
+    /// `asset` and an `amount` or the fields starting with `name`.
+    fn take_asset_amount(&mut self, name: &str) -> (String, String) {
+        match self.remove(name) {
+            Value::Object(mut amount) => {
+                let mut take = |name| amount.remove(name).map(|v| cell(&v)).unwrap_or_default();
+                (take("asset"), take("amount"))
+            }
+            _ => (
+                self.take_asset(&format!("{}_", name)),
+                self.take(&format!("{}_amount", name)),
+            ),
+        }
+    }
+
+    /// Removes the id of the liquidity pool, the rest of the pool stays
+    /// in the details.
+    fn take_liquidity_pool_id(&mut self) -> String {
+        match self.0.get_mut("liquidity_pool") {
+            Some(Value::Object(pool)) => pool.remove("id").map(|id| cell(&id)).unwrap_or_default(),
+            _ => self.take("liquidity_pool_id"),
+        }
+    }
+
+    /// Returns the remaining fields as a json object, or an empty cell if
+    /// there are none.
+    fn details(self) -> String {
+        let fields: Map<_, _> = self.0.into_iter().filter(|(_, v)| !v.is_null()).collect();
+        if fields.is_empty() {
+            String::new()
+        } else {
+            Value::Object(fields).to_string()
+        }
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn write_csv_line<W: Write, S: AsRef<str>>(writer: &mut W, cells: &[S]) -> Result<()> {
+    for (index, cell) in cells.iter().enumerate() {
+        if index > 0 {
+            writer.write_all(b",")?;
+        }
+        let cell = escape_formula(cell.as_ref());
+        if cell.contains([',', '"', '\n', '\r']) {
+            write!(writer, "\"{}\"", cell.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(cell.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\r\n")?;
+    Ok(())
+}
+
+/// Returns `cell` prefixed with `'` if spreadsheets would read it as a
+/// formula.
+fn escape_formula(cell: &str) -> Cow<'_, str> {
+    if cell.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        Cow::Owned(format!("'{}", cell))
+    } else {
+        Cow::Borrowed(cell)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::fixture;
+    use futures::executor::block_on;
+    use futures::stream;
+    use serde::de::DeserializeOwned;
+
+    const ISSUER: &str = "GAAZI4TCR3TY5OJHCTJC2A4QSY6CJWJH5IAJTGKIN2ER7LBNVKOCCWN7";
+    const TRUSTOR: &str = "GALPCCZN4YXA3YMJHKL6CVIECKPLJJCTVMSNYWBTKJW4K5HQLYLDMZTB";
+
+    fn records<T>(records: Vec<T>) -> impl Stream<Item = Result<T>> {
+        stream::iter(records.into_iter().map(Ok))
+    }
+
+    fn csv<T: CsvRecord>(items: Vec<T>) -> Vec<Vec<String>> {
+        let mut output = Vec::new();
+        block_on(write_csv(records(items), &mut output)).unwrap();
+        parse_csv(&String::from_utf8(output).unwrap())
+    }
+
+    // Parses CSV lines ending with `\r\n`, with quoted cells.
+    fn parse_csv(text: &str) -> Vec<Vec<String>> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        let mut cell = String::new();
+        let mut quoted = false;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (quoted, c) {
+                (true, '"') if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    cell.push('"');
+                }
+                (_, '"') => quoted = !quoted,
+                (false, ',') => row.push(std::mem::take(&mut cell)),
+                (false, '\r') => {}
+                (false, '\n') => {
+                    row.push(std::mem::take(&mut cell));
+                    rows.push(std::mem::take(&mut row));
+                }
+                (_, c) => cell.push(c),
+            }
+        }
+        rows
+    }
+
+    fn column(header: &[&str], row: &[String], name: &str) -> String {
+        let index = header.iter().position(|c| *c == name).unwrap();
+        row[index].clone()
+    }
+
+    // Returns the operation or effect `json` with the fields of `fields`.
+    fn with_fields<T: DeserializeOwned>(json: &str, fields: serde_json::Value) -> T {
+        let mut record: serde_json::Value = serde_json::from_str(json).unwrap();
+        let record_fields = record.as_object_mut().unwrap();
+        for (name, value) in fields.as_object().unwrap() {
+            record_fields.insert(name.clone(), value.clone());
+        }
+        serde_json::from_value(record).unwrap()
+    }
+
+    #[test]
+    fn test_ndjson_is_lossless() {
+        let operations: Vec<Operation> =
+            fixture(include_str!("../tests/fixtures/all_operations.json"));
+        let mut output = Vec::new();
+        let count = block_on(write_ndjson(records(operations.clone()), &mut output)).unwrap();
+        assert_eq!(operations.len(), count);
+
+        let parsed: Vec<Operation> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(operations, parsed);
+    }
+
+    #[test]
+    fn test_operations_csv() {
+        let operations: Vec<Operation> =
+            fixture(include_str!("../tests/fixtures/all_operations.json"));
+        let rows = csv(operations);
+        assert_eq!(OPERATION_HEADER, rows[0].as_slice());
+        let column = |row: &[String], name: &str| column(OPERATION_HEADER, row, name);
+        let create_account = &rows[1];
+        assert_eq!("create_account", column(create_account, "type"));
+        assert_eq!(
+            "GAAZI4TCR3TY5OJHCTJC2A4QSY6CJWJH5IAJTGKIN2ER7LBNVKOCCWN7",
+            column(create_account, "from")
+        );
+        assert_eq!("20.0000000", column(create_account, "amount"));
+        assert_eq!("", column(create_account, "details"));
+
+        let payment = &rows[2];
+        assert_eq!("payment", column(payment, "type"));
+        assert_eq!("native", column(payment, "asset"));
+        assert!(rows.iter().all(|row| row.len() == OPERATION_HEADER.len()));
+    }
+
+    #[test]
+    fn test_operation_type_columns() {
+        let operations: Vec<Operation> =
+            fixture(include_str!("../tests/fixtures/all_operations.json"));
+        let payment = serde_json::to_string(&operations[1]).unwrap();
+        let path_payment: Operation = with_fields(
+            &payment,
+            serde_json::json!({
+                "type": "path_payment_strict_send",
+                "type_i": 13,
+                "asset_type": "credit_alphanum4",
+                "asset_code": "USD",
+                "asset_issuer": ISSUER,
+                "from": ISSUER,
+                "to": ISSUER,
+                "amount": "10.0000000",
+                "path": [
+                    {"asset_type": "native"},
+                    {"asset_type": "credit_alphanum4", "asset_code": "EUR", "asset_issuer": ISSUER}
+                ],
+                "source_amount": "5.0000000",
+                "destination_min": "9.5000000",
+                "source_asset_type": "native",
+            }),
+        );
+        let offer: Operation = with_fields(
+            &payment,
+            serde_json::json!({
+                "type": "manage_sell_offer",
+                "type_i": 3,
+                "amount": "100.0000000",
+                "price": "0.5000000",
+                "price_r": {"n": 1, "d": 2},
+                "buying_asset_type": "native",
+                "selling_asset_type": "credit_alphanum4",
+                "selling_asset_code": "USD",
+                "selling_asset_issuer": ISSUER,
+                "offer_id": "42",
+            }),
+        );
+        let change_trust: Operation = with_fields(
+            &payment,
+            serde_json::json!({
+                "type": "change_trust",
+                "type_i": 6,
+                "asset_type": "credit_alphanum4",
+                "asset_code": "USD",
+                "asset_issuer": ISSUER,
+                "limit": "1000.0000000",
+                "trustee": ISSUER,
+                "trustor": TRUSTOR,
+            }),
+        );
+        let rows = csv(vec![path_payment, offer, change_trust]);
+        let column = |row: &[String], name: &str| column(OPERATION_HEADER, row, name);
+
+        let path_payment = &rows[1];
+        assert_eq!(
+            format!("native;EUR:{}", ISSUER),
+            column(path_payment, "path")
+        );
+        assert_eq!("9.5000000", column(path_payment, "destination_min"));
+        assert_eq!("native", column(path_payment, "source_asset"));
+        assert_eq!(format!("USD:{}", ISSUER), column(path_payment, "asset"));
+        assert_eq!("", column(path_payment, "details"));
+
+        let offer = &rows[2];
+        assert_eq!("0.5000000", column(offer, "price"));
+        assert_eq!("42", column(offer, "offer_id"));
+        assert_eq!(format!("USD:{}", ISSUER), column(offer, "selling_asset"));
+        assert_eq!("native", column(offer, "buying_asset"));
+        assert_eq!("100.0000000", column(offer, "amount"));
+
+        let change_trust = &rows[3];
+        assert_eq!("1000.0000000", column(change_trust, "limit"));
+        assert_eq!(TRUSTOR, column(change_trust, "trustor"));
+        assert_eq!(ISSUER, column(change_trust, "trustee"));
+        assert_eq!("", column(change_trust, "path"));
+    }
+
+    #[test]
+    fn test_other_records_csv() {
+        let effects: Vec<Effect> = fixture(include_str!("../tests/fixtures/all_effects.json"));
+        let count = effects.len();
+        let rows = csv(effects);
+        assert_eq!(EFFECT_HEADER, rows[0].as_slice());
+        assert_eq!(count + 1, rows.len());
+        assert!(rows.iter().all(|row| row.len() == EFFECT_HEADER.len()));
+
+        let trades: Vec<Trade> = fixture(include_str!("../tests/fixtures/all_trades.json"));
+        let rows = csv(trades);
+        assert_eq!(TRADE_HEADER, rows[0].as_slice());
+        assert!(column(TRADE_HEADER, &rows[1], "price").contains('/'));
+
+        let transactions: Vec<Transaction> =
+            fixture(include_str!("../tests/fixtures/all_transactions.json"));
+        let rows = csv(transactions);
+        assert_eq!(TRANSACTION_HEADER, rows[0].as_slice());
+        assert_eq!("hello world", column(TRANSACTION_HEADER, &rows[1], "memo"));
+    }
+
+    #[test]
+    fn test_effect_type_columns() {
+        let effects: Vec<Effect> = fixture(include_str!("../tests/fixtures/all_effects.json"));
+        let json = serde_json::to_string(&effects[0]).unwrap();
+        let trade: Effect = with_fields(
+            &json,
+            serde_json::json!({
+                "type": "trade",
+                "type_i": 33,
+                "seller": ISSUER,
+                "offer_id": "42",
+                "sold_amount": "10.0000000",
+                "sold_asset_type": "native",
+                "bought_amount": "5.0000000",
+                "bought_asset_type": "credit_alphanum4",
+                "bought_asset_code": "USD",
+                "bought_asset_issuer": ISSUER,
+            }),
+        );
+        let trustline: Effect = with_fields(
+            &json,
+            serde_json::json!({
+                "type": "trustline_created",
+                "type_i": 20,
+                "asset_type": "credit_alphanum4",
+                "asset_code": "USD",
+                "asset_issuer": ISSUER,
+                "limit": "1000.0000000",
+            }),
+        );
+        let rows = csv(vec![effects[0].clone(), trade, trustline]);
+        let column = |row: &[String], name: &str| column(EFFECT_HEADER, row, name);
+
+        let pool_trade = &rows[1];
+        assert_eq!("liquidity_pool_trade", column(pool_trade, "type"));
+        assert_eq!("native", column(pool_trade, "sold_asset"));
+        assert_eq!("994.0159803", column(pool_trade, "sold_amount"));
+        assert_eq!("584.2430414", column(pool_trade, "bought_amount"));
+        assert_eq!(
+            "255b3c6421588046d593cfe01abc397e7d7ebcd5274c3c47984438c9ce503293",
+            column(pool_trade, "liquidity_pool_id")
+        );
+        // The state of the pool has no column.
+        assert!(column(pool_trade, "details").contains("total_shares"));
+
+        let trade = &rows[2];
+        assert_eq!(ISSUER, column(trade, "seller"));
+        assert_eq!("42", column(trade, "offer_id"));
+        assert_eq!("native", column(trade, "sold_asset"));
+        assert_eq!("10.0000000", column(trade, "sold_amount"));
+        assert_eq!(format!("USD:{}", ISSUER), column(trade, "bought_asset"));
+        assert_eq!("5.0000000", column(trade, "bought_amount"));
+        assert_eq!("", column(trade, "details"));
+
+        let trustline = &rows[3];
+        assert_eq!(format!("USD:{}", ISSUER), column(trustline, "asset"));
+        assert_eq!("1000.0000000", column(trustline, "limit"));
+    }
+
+    #[test]
+    fn test_csv_escaping() {
+        let mut output = Vec::new();
+        write_csv_line(&mut output, &["a", "b,c", "say \"hi\"", "x\ny"]).unwrap();
+        assert_eq!(
+            "a,\"b,c\",\"say \"\"hi\"\"\",\"x\ny\"\r\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_csv_formula_escaping() {
+        let mut transaction: Value =
+            fixture(include_str!("../tests/fixtures/all_transactions.json")).remove(0);
+        transaction["memo"] = Value::from("=HYPERLINK(\"https://evil.example\",\"refund\")");
+        let transaction: Transaction = serde_json::from_value(transaction).unwrap();
+        let rows = csv(vec![transaction]);
+        assert_eq!(
+            "'=HYPERLINK(\"https://evil.example\",\"refund\")",
+            column(TRANSACTION_HEADER, &rows[1], "memo")
+        );
+
+        let mut output = Vec::new();
+        write_csv_line(&mut output, &["+1", "-1", "@SUM(A1)", "a=b"]).unwrap();
+        assert_eq!(
+            "'+1,'-1,'@SUM(A1),a=b\r\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+}
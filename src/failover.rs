@@ -0,0 +1,711 @@
+//! Spread requests over several horizon hosts.
+//!
+//! A `FailoverClient` wraps one client per host. Requests are routed
+//! round-robin or to the host with the least latency, and are sent to
+//! the next host when a host fails with a server error or reports
+//! stale history. Failed hosts are skipped for a cooldown period.
+//!
+//! `FailoverClient::check_health` requests the root of each host and
+//! marks hosts whose ingestion lags behind stellar core as failed. It
+//! runs only when called, unless a health check interval is set with
+//! `with_health_check_interval`: requests and stream events then run it
+//! first when the last check is older than the interval.
+//!
+//! Streams fail over too: after an error, or when a health check finds
+//! that the host lags behind, the stream is reopened on the next healthy
+//! host from the last event id. With a health check interval, streams
+//! that send no event for the interval run the health check too.
+//!
+//! ```rust
+//! use stellar_horizon::api;
+//! use stellar_horizon::client::{HorizonClient, HorizonHttpClient};
+//! use stellar_horizon::failover::{FailoverClient, Routing};
+//! use std::time::Duration;
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = FailoverClient::<HorizonHttpClient>::from_hosts(&[
+//!     "https://horizon.internal.example.org",
+//!     "https://horizon.stellar.org",
+//! ])?
+//! .with_routing(Routing::LeastLatency)
+//! .with_health_check_interval(Duration::from_secs(60));
+//! let (_, root) = client.request(api::root::root()).await?;
+//! println!("Latest ledger = {}", root.history_latest_ledger);
+//! # Ok(())
+//! # }
+//! ```
+use crate::api;
+use crate::client::{HorizonClient, HorizonHttpClient};
+use crate::error::{Error, Result};
+use crate::headers::HeaderMap;
+use crate::horizon_error::HorizonError;
+use crate::request::{Request, ResumedRequest, StreamRequest, ValueRequest};
+use crate::stream::{EventStream, StreamEvent};
+use futures::future::{self, BoxFuture, Either};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use futures::Stream;
+use std::pin::pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The default number of ledgers ingestion can lag behind stellar core.
+pub const DEFAULT_MAX_INGEST_LAG: u32 = 10;
+
+/// The default time failed hosts are skipped.
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// How to choose the host of a request.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Routing {
+    /// Send requests to each host in turn.
+    RoundRobin,
+    /// Send requests to the host with the least latency.
+    LeastLatency,
+}
+
+/// The status of a host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostStatus {
+    /// False if the host failed recently.
+    pub healthy: bool,
+    /// The average latency of the host requests.
+    pub latency: Option<Duration>,
+    /// The ledgers ingestion lagged behind stellar core on the last
+    /// health check.
+    pub ingest_lag: Option<u32>,
+}
+
+/// Client that fails over between several hosts.
+pub struct FailoverClient<C> {
+    hosts: Arc<Vec<Host<C>>>,
+    routing: Routing,
+    max_ingest_lag: u32,
+    cooldown: Duration,
+    next: AtomicUsize,
+    health_check: Arc<HealthCheck>,
+}
+
+struct HealthCheck {
+    interval: Option<Duration>,
+    checked_at: Mutex<Option<Instant>>,
+}
+
+struct Host<C> {
+    client: C,
+    state: Mutex<HostState>,
+}
+
+#[derive(Default)]
+struct HostState {
+    failed_until: Option<Instant>,
+    latency: Option<Duration>,
+    ingest_lag: Option<u32>,
+}
+
+struct FailoverStream<C, R: StreamRequest> {
+    hosts: Arc<Vec<Host<C>>>,
+    order: Vec<usize>,
+    position: usize,
+    max_ingest_lag: u32,
+    cooldown: Duration,
+    health_check: Arc<HealthCheck>,
+    request: Arc<R>,
+    last_id: Option<String>,
+    events: Option<EventStream<R::Resource>>,
+}
+
+impl FailoverClient<HorizonHttpClient> {
+    /// Creates a new failover client with a client for each of `hosts`.
+    pub fn from_hosts(hosts: &[&str]) -> Result<FailoverClient<HorizonHttpClient>> {
+        if hosts.is_empty() {
+            return Err(Error::InvalidHost);
+        }
+        let mut clients = Vec::with_capacity(hosts.len());
+        for host in hosts {
+            clients.push(HorizonHttpClient::new_from_str(host)?);
+        }
+        Ok(FailoverClient::new(clients))
+    }
+}
+
+impl<C> FailoverClient<C>
+where
+    C: HorizonClient,
+{
+    /// Creates a new failover client sending requests with `clients`.
+    ///
+    /// Panics if `clients` is empty.
+    pub fn new(clients: Vec<C>) -> FailoverClient<C> {
+        assert!(!clients.is_empty(), "failover client without hosts");
+        let hosts = clients
+            .into_iter()
+            .map(|client| Host {
+                client,
+                state: Mutex::new(HostState::default()),
+            })
+            .collect();
+        FailoverClient {
+            hosts: Arc::new(hosts),
+            routing: Routing::RoundRobin,
+            max_ingest_lag: DEFAULT_MAX_INGEST_LAG,
+            cooldown: DEFAULT_COOLDOWN,
+            next: AtomicUsize::new(0),
+            health_check: Arc::new(HealthCheck {
+                interval: None,
+                checked_at: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Sets how to choose the host of a request.
+    pub fn with_routing(mut self, routing: Routing) -> Self {
+        self.routing = routing;
+        self
+    }
+
+    /// Sets the number of ledgers ingestion can lag behind stellar core
+    /// before a host is considered stale.
+    pub fn with_max_ingest_lag(mut self, max_ingest_lag: u32) -> Self {
+        self.max_ingest_lag = max_ingest_lag;
+        self
+    }
+
+    /// Sets the time failed hosts are skipped.
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Runs `check_health` before requests and stream events when the
+    /// last check is older than `interval`.
+    ///
+    /// Without an interval, health is only checked when `check_health`
+    /// is called.
+    pub fn with_health_check_interval(mut self, interval: Duration) -> Self {
+        self.health_check = Arc::new(HealthCheck {
+            interval: Some(interval),
+            checked_at: Mutex::new(None),
+        });
+        self
+    }
+
+    /// Returns the status of each host, in the order they were given.
+    pub fn statuses(&self) -> Vec<HostStatus> {
+        let now = Instant::now();
+        self.hosts.iter().map(|host| host.status(now)).collect()
+    }
+
+    /// Requests the root of each host, and marks as failed the hosts
+    /// that can't be reached or whose ingestion lags behind.
+    pub async fn check_health(&self) -> Vec<HostStatus> {
+        *self.health_check.checked_at.lock().unwrap() = Some(Instant::now());
+        check_hosts(&self.hosts, self.max_ingest_lag, self.cooldown).await;
+        self.statuses()
+    }
+
+    /// Returns the index of the hosts to try, healthy hosts first.
+    fn host_order(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let statuses: Vec<_> = self.hosts.iter().map(|host| host.status(now)).collect();
+        let (mut healthy, unhealthy): (Vec<usize>, Vec<usize>) =
+            (0..self.hosts.len()).partition(|&index| statuses[index].healthy);
+        match self.routing {
+            Routing::RoundRobin => {
+                if !healthy.is_empty() {
+                    let next = self.next.fetch_add(1, Ordering::Relaxed);
+                    let len = healthy.len();
+                    healthy.rotate_left(next % len);
+                }
+            }
+            Routing::LeastLatency => {
+                // Hosts without latency are tried first, to measure them.
+                healthy.sort_by_key(|&index| statuses[index].latency.unwrap_or_default());
+            }
+        }
+        healthy.extend(unhealthy);
+        healthy
+    }
+
+    async fn failover_request<R: Request>(&self, req: R) -> Result<(HeaderMap, R::Response)> {
+        if self.health_check.is_due() {
+            check_hosts(&self.hosts, self.max_ingest_lag, self.cooldown).await;
+        }
+        let mut last_error = None;
+        for index in self.host_order() {
+            let host = &self.hosts[index];
+            let started = Instant::now();
+            match host.client.request(ValueRequest(&req)).await {
+                Ok((headers, value)) => {
+                    host.record_latency(started.elapsed());
+                    return Ok((headers, serde_json::from_value(value)?));
+                }
+                Err(err) if is_failover_error(&err) => {
+                    host.mark_failed(self.cooldown);
+                    last_error = Some(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Err(last_error.unwrap_or(Error::HorizonServerError))
+    }
+}
+
+impl<C> HorizonClient for FailoverClient<C>
+where
+    C: HorizonClient + Send + Sync + 'static,
+{
+    fn request<'a, R: Request + 'a>(
+        &'a self,
+        req: R,
+    ) -> BoxFuture<'a, Result<(HeaderMap, R::Response)>> {
+        Box::pin(self.failover_request(req))
+    }
+
+    fn stream<'a, R: StreamRequest + 'static>(
+        &'a self,
+        req: R,
+    ) -> Result<Box<dyn Stream<Item = Result<R::Resource>> + 'static + Send + Unpin>> {
+        let events = self.stream_events(req)?;
+        Ok(Box::new(events.map_ok(StreamEvent::into_resource)))
+    }
+
+    fn stream_events<R: StreamRequest + 'static>(
+        &self,
+        req: R,
+    ) -> Result<EventStream<R::Resource>> {
+        let state = FailoverStream {
+            hosts: self.hosts.clone(),
+            order: self.host_order(),
+            position: 0,
+            max_ingest_lag: self.max_ingest_lag,
+            cooldown: self.cooldown,
+            health_check: self.health_check.clone(),
+            request: Arc::new(req),
+            last_id: None,
+            events: None,
+        };
+        Ok(Box::new(Box::pin(stream::unfold(state, next_event))))
+    }
+}
+
+impl HealthCheck {
+    /// Returns true if the hosts should be checked now, and records the
+    /// check so that concurrent requests don't check too.
+    fn is_due(&self) -> bool {
+        let interval = match self.interval {
+            Some(interval) => interval,
+            None => return false,
+        };
+        let mut checked_at = self.checked_at.lock().unwrap();
+        let now = Instant::now();
+        if checked_at.is_some_and(|checked_at| now.duration_since(checked_at) < interval) {
+            return false;
+        }
+        *checked_at = Some(now);
+        true
+    }
+}
+
+/// Requests the root of each host, and marks as failed the hosts that
+/// can't be reached or whose ingestion lags more than `max_ingest_lag`
+/// ledgers behind.
+async fn check_hosts<C: HorizonClient>(hosts: &[Host<C>], max_ingest_lag: u32, cooldown: Duration) {
+    let checks = hosts.iter().map(|host| async move {
+        let started = Instant::now();
+        match host.client.request(api::root::root()).await {
+            Ok((_, root)) => {
+                host.record_latency(started.elapsed());
+                let lag = root
+                    .core_latest_ledger
+                    .value()
+                    .saturating_sub(root.history_latest_ledger.value());
+                let mut state = host.state.lock().unwrap();
+                state.ingest_lag = Some(lag);
+                state.failed_until = if lag > max_ingest_lag {
+                    Some(Instant::now() + cooldown)
+                } else {
+                    None
+                };
+            }
+            Err(_) => host.mark_failed(cooldown),
+        }
+    });
+    future::join_all(checks).await;
+}
+
+impl<C> Host<C> {
+    fn status(&self, now: Instant) -> HostStatus {
+        let state = self.state.lock().unwrap();
+        HostStatus {
+            healthy: state.failed_until.is_none_or(|until| until <= now),
+            latency: state.latency,
+            ingest_lag: state.ingest_lag,
+        }
+    }
+
+    fn mark_failed(&self, cooldown: Duration) {
+        self.state.lock().unwrap().failed_until = Some(Instant::now() + cooldown);
+    }
+
+    fn record_latency(&self, latency: Duration) {
+        let mut state = self.state.lock().unwrap();
+        // Exponential moving average, to smooth out slow requests.
+        state.latency = Some(match state.latency {
+            Some(average) => (average * 4 + latency) / 5,
+            None => latency,
+        });
+    }
+}
+
+impl<C, R> FailoverStream<C, R>
+where
+    R: StreamRequest,
+{
+    fn host(&self) -> &Host<C> {
+        &self.hosts[self.order[self.position % self.order.len()]]
+    }
+
+    /// Marks the current host as failed and moves to the next one.
+    fn fail_over(&mut self) {
+        self.host().mark_failed(self.cooldown);
+        self.next_host();
+    }
+
+    /// Moves to the next healthy host, or to the next host if none is
+    /// healthy.
+    fn next_host(&mut self) {
+        let now = Instant::now();
+        let len = self.order.len();
+        let offset = (1..=len)
+            .find(|offset| {
+                let index = self.order[(self.position + offset) % len];
+                self.hosts[index].status(now).healthy
+            })
+            .unwrap_or(1);
+        self.position += offset;
+        self.events = None;
+    }
+}
+
+async fn next_event<C, R>(
+    mut state: FailoverStream<C, R>,
+) -> Option<(Result<StreamEvent<R::Resource>>, FailoverStream<C, R>)>
+where
+    C: HorizonClient,
+    R: StreamRequest + 'static,
+{
+    loop {
+        if state.health_check.is_due() {
+            check_hosts(&state.hosts, state.max_ingest_lag, state.cooldown).await;
+            if !state.host().status(Instant::now()).healthy {
+                state.next_host();
+            }
+        }
+        let events = match &mut state.events {
+            Some(events) => events,
+            None => {
+                let request = ResumedRequest {
+                    request: state.request.clone(),
+                    cursor: state.last_id.clone(),
+                };
+                match state.host().client.stream_events(request) {
+                    Ok(events) => state.events.insert(events),
+                    Err(err) => {
+                        state.fail_over();
+                        return Some((Err(err), state));
+                    }
+                }
+            }
+        };
+        let next = match state.health_check.interval {
+            Some(interval) => {
+                // Quiet streams are checked too, so that a host that
+                // stopped sending events is left.
+                let next = pin!(events.next());
+                let sleep = pin!(tokio::time::sleep(interval));
+                match future::select(next, sleep).await {
+                    Either::Left((next, _)) => next,
+                    Either::Right(_) => continue,
+                }
+            }
+            None => events.next().await,
+        };
+        return match next? {
+            Ok(event) => {
+                if let Some(id) = &event.id {
+                    state.last_id = Some(id.clone());
+                }
+                Some((Ok(event), state))
+            }
+            Err(err) => {
+                // Other errors are handled by the host stream.
+                if is_failover_error(&err) {
+                    state.fail_over();
+                }
+                Some((Err(err), state))
+            }
+        };
+    }
+}
+
+/// Returns true if the request should be sent to another host after
+/// `err`.
+fn is_failover_error(err: &Error) -> bool {
+    matches!(
+        err,
+        Error::HorizonServerError
+            | Error::HyperError(_)
+            | Error::IoError(_)
+            | Error::SSEDecoderError
+            | Error::HorizonRequestError(HorizonError::StaleHistory(_))
+            | Error::HorizonRequestError(HorizonError::Timeout(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{ledger_json, root_json, MockClient};
+    use crate::request::PageRequest;
+    use serde_json::Value;
+    use url::Url;
+
+    /// Ends streams with a server error.
+    struct FlakyClient(MockClient);
+
+    impl HorizonClient for FlakyClient {
+        fn request<'a, R: Request + 'a>(
+            &'a self,
+            req: R,
+        ) -> BoxFuture<'a, Result<(HeaderMap, R::Response)>> {
+            self.0.request(req)
+        }
+
+        fn stream<'a, R: StreamRequest + 'static>(
+            &'a self,
+            req: R,
+        ) -> Result<Box<dyn Stream<Item = Result<R::Resource>> + 'static + Send + Unpin>> {
+            self.0.stream(req)
+        }
+
+        fn stream_events<R: StreamRequest + 'static>(
+            &self,
+            req: R,
+        ) -> Result<EventStream<R::Resource>> {
+            let events = self.0.stream_events(req)?;
+            let error = stream::once(future::ready(Err(Error::HorizonServerError)));
+            Ok(Box::new(events.chain(error)))
+        }
+    }
+
+    fn host<F>(responder: F) -> FlakyClient
+    where
+        F: Fn(&Url) -> Option<Value> + Send + Sync + 'static,
+    {
+        FlakyClient(MockClient::new(responder))
+    }
+
+    fn requested_hosts(client: &FailoverClient<FlakyClient>) -> Vec<usize> {
+        client
+            .hosts
+            .iter()
+            .map(|host| host.client.0.requests().len())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_round_robin() {
+        let client = FailoverClient::new(vec![
+            host(|_| Some(ledger_json(1, 0))),
+            host(|_| Some(ledger_json(1, 0))),
+        ]);
+        for _ in 0..4 {
+            client
+                .request(api::ledgers::single(1.into()))
+                .await
+                .unwrap();
+        }
+        assert_eq!(vec![2, 2], requested_hosts(&client));
+    }
+
+    #[tokio::test]
+    async fn test_fail_over_on_server_error() {
+        let client = FailoverClient::new(vec![host(|_| None), host(|_| Some(ledger_json(1, 0)))]);
+        for _ in 0..3 {
+            let (_, ledger) = client
+                .request(api::ledgers::single(1.into()))
+                .await
+                .unwrap();
+            assert_eq!(1, ledger.sequence.value());
+        }
+        // The failed host is skipped during the cooldown.
+        assert_eq!(vec![1, 3], requested_hosts(&client));
+        let statuses = client.statuses();
+        assert!(!statuses[0].healthy);
+        assert!(statuses[1].healthy);
+    }
+
+    #[tokio::test]
+    async fn test_return_last_error_if_all_hosts_fail() {
+        let client = FailoverClient::new(vec![host(|_| None), host(|_| None)]);
+        let result = client.request(api::ledgers::single(1.into())).await;
+        assert!(matches!(result, Err(Error::HorizonServerError)));
+        assert_eq!(vec![1, 1], requested_hosts(&client));
+    }
+
+    #[tokio::test]
+    async fn test_health_check_marks_stale_hosts() {
+        let client = FailoverClient::new(vec![
+            host(|_| Some(root_json(100, 200))),
+            host(|_| Some(root_json(195, 200))),
+        ])
+        .with_routing(Routing::LeastLatency);
+        let statuses = client.check_health().await;
+        assert!(!statuses[0].healthy);
+        assert_eq!(Some(100), statuses[0].ingest_lag);
+        assert!(statuses[1].healthy);
+        assert_eq!(Some(5), statuses[1].ingest_lag);
+        assert_eq!(vec![1, 0], client.host_order());
+    }
+
+    #[tokio::test]
+    async fn test_health_check_interval() {
+        let client = FailoverClient::new(vec![
+            host(|_| Some(root_json(100, 200))),
+            host(|uri| match uri.path() {
+                "/" => Some(root_json(195, 200)),
+                _ => Some(ledger_json(1, 0)),
+            }),
+        ])
+        .with_health_check_interval(Duration::from_secs(60));
+        for _ in 0..3 {
+            client
+                .request(api::ledgers::single(1.into()))
+                .await
+                .unwrap();
+        }
+        // The stale host is only checked, once.
+        assert_eq!(vec![1, 4], requested_hosts(&client));
+        assert_eq!(Some(100), client.statuses()[0].ingest_lag);
+    }
+
+    #[tokio::test]
+    async fn test_stream_moves_to_healthy_host_after_health_check() {
+        // The first host falls behind after the first health check.
+        let checks = AtomicUsize::new(0);
+        let client = FailoverClient::new(vec![
+            MockClient::new(move |uri| match uri.path() {
+                "/" if checks.fetch_add(1, Ordering::SeqCst) == 0 => Some(root_json(195, 200)),
+                "/" => Some(root_json(100, 200)),
+                _ => Some(Value::Array(vec![ledger_json(1, 0), ledger_json(2, 0)])),
+            }),
+            MockClient::new(|uri| match uri.path() {
+                "/" => Some(root_json(195, 200)),
+                _ => {
+                    assert_eq!(
+                        Some(format!("cursor={}", 1u64 << 32)),
+                        uri.query().map(str::to_string)
+                    );
+                    Some(Value::Array(vec![ledger_json(2, 0), ledger_json(3, 0)]))
+                }
+            }),
+        ])
+        .with_health_check_interval(Duration::ZERO);
+        let sequences: Vec<_> = client
+            .stream(api::ledgers::all())
+            .unwrap()
+            .map(|ledger| ledger.unwrap().sequence.value())
+            .collect()
+            .await;
+        assert_eq!(vec![1, 2, 3], sequences);
+    }
+
+    /// Streams of stalled hosts never send events.
+    struct StallingClient {
+        inner: MockClient,
+        stalled: bool,
+    }
+
+    impl HorizonClient for StallingClient {
+        fn request<'a, R: Request + 'a>(
+            &'a self,
+            req: R,
+        ) -> BoxFuture<'a, Result<(HeaderMap, R::Response)>> {
+            self.inner.request(req)
+        }
+
+        fn stream<'a, R: StreamRequest + 'static>(
+            &'a self,
+            req: R,
+        ) -> Result<Box<dyn Stream<Item = Result<R::Resource>> + 'static + Send + Unpin>> {
+            self.inner.stream(req)
+        }
+
+        fn stream_events<R: StreamRequest + 'static>(
+            &self,
+            req: R,
+        ) -> Result<EventStream<R::Resource>> {
+            if self.stalled {
+                return Ok(Box::new(stream::pending()));
+            }
+            self.inner.stream_events(req)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_leaves_stalled_host_after_health_check() {
+        // The first host stops sending events and falls behind.
+        let checks = AtomicUsize::new(0);
+        let client = FailoverClient::new(vec![
+            StallingClient {
+                inner: MockClient::new(move |uri| match uri.path() {
+                    "/" if checks.fetch_add(1, Ordering::SeqCst) == 0 => Some(root_json(195, 200)),
+                    _ => Some(root_json(100, 200)),
+                }),
+                stalled: true,
+            },
+            StallingClient {
+                inner: MockClient::new(|uri| match uri.path() {
+                    "/" => Some(root_json(195, 200)),
+                    _ => Some(Value::Array(vec![ledger_json(1, 0)])),
+                }),
+                stalled: false,
+            },
+        ])
+        .with_health_check_interval(Duration::from_millis(50));
+        let mut ledgers = client.stream(api::ledgers::all()).unwrap();
+        let ledger = tokio::time::timeout(Duration::from_secs(5), ledgers.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(1, ledger.sequence.value());
+        assert_eq!(2, client.hosts[0].client.inner.requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_stream_resumes_on_next_host() {
+        let client = FailoverClient::new(vec![
+            host(|_| Some(Value::Array(vec![ledger_json(1, 0), ledger_json(2, 0)]))),
+            host(|uri| {
+                assert_eq!(
+                    Some(format!("cursor={}", 2u64 << 32)),
+                    uri.query().map(str::to_string)
+                );
+                Some(Value::Array(vec![ledger_json(3, 0)]))
+            }),
+        ]);
+        let events: Vec<_> = client
+            .stream(api::ledgers::all().with_cursor("now"))
+            .unwrap()
+            .take(4)
+            .collect()
+            .await;
+        let sequences: Vec<_> = events
+            .iter()
+            .map(|ledger| ledger.as_ref().map(|ledger| ledger.sequence.value()).ok())
+            .collect();
+        assert_eq!(vec![Some(1), Some(2), None, Some(3)], sequences);
+    }
+}
@@ -0,0 +1,406 @@
+//! Monitor the health of a horizon server.
+//!
+//! A `HealthMonitor` polls the horizon root and `/health` endpoints
+//! and reports the ingestion lag, whether stellar core is up and in
+//! sync, the age of the latest ingested ledger, and whether horizon
+//! supports the current protocol version.
+//!
+//! `HealthMonitor::transitions` emits a `HealthTransition` every time
+//! the health state changes, so that alerts fire when horizon becomes
+//! stale instead of when users notice. The state only holds the kinds
+//! of issues found, the measured values are in the `HealthReport`, so
+//! a growing ingestion lag is not a new transition.
+//!
+//! ```rust
+//! use stellar_horizon::client::HorizonHttpClient;
+//! use stellar_horizon::health::HealthMonitor;
+//! use futures::stream::StreamExt;
+//! use std::time::Duration;
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let mut transitions = HealthMonitor::new(client)
+//!     .with_interval(Duration::from_secs(10))
+//!     .transitions();
+//! while let Some(transition) = transitions.next().await {
+//!     println!("Horizon is now {:?}", transition.current);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::api;
+use crate::client::HorizonClient;
+use crate::error::Result;
+use crate::resources::{Health, Root};
+use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The default time between health checks.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The default number of ledgers ingestion can lag behind stellar core.
+pub const DEFAULT_MAX_INGEST_LAG: u32 = 10;
+
+/// The default age after which the latest ingested ledger is stale.
+pub const DEFAULT_MAX_LEDGER_AGE: Duration = Duration::from_secs(60);
+
+/// A problem found by a health check.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HealthIssue {
+    /// The horizon root can't be fetched.
+    RootUnavailable,
+    /// The `/health` endpoint failed.
+    HealthCheckFailed,
+    /// Horizon can't reach its database.
+    DatabaseDisconnected,
+    /// Horizon can't reach stellar core.
+    CoreDown,
+    /// Stellar core is not in sync with the network.
+    CoreNotSynced,
+    /// Ingestion lags too many ledgers behind stellar core.
+    IngestLag,
+    /// The latest ingested ledger closed too long ago.
+    StaleLedger,
+    /// Horizon or stellar core doesn't support the current protocol
+    /// version.
+    UnsupportedProtocol,
+}
+
+/// The health of a horizon server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthState {
+    /// No issues found.
+    Healthy,
+    /// The issues found.
+    Unhealthy(Vec<HealthIssue>),
+}
+
+/// The result of a health check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthReport {
+    /// When the check was done.
+    pub checked_at: DateTime<Utc>,
+    /// The horizon root, if available.
+    pub root: Option<Root>,
+    /// The `/health` response, if available.
+    pub health: Option<Health>,
+    /// The ledgers ingestion lags behind stellar core.
+    pub ingest_lag: Option<u32>,
+    /// The time since the latest ingested ledger closed.
+    pub ledger_age: Option<Duration>,
+    /// Whether horizon and stellar core support the current protocol
+    /// version.
+    pub protocol_supported: Option<bool>,
+    /// The health state.
+    pub state: HealthState,
+}
+
+/// A change of health state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthTransition {
+    /// The state before the change, `None` on the first check.
+    pub previous: Option<HealthState>,
+    /// The state after the change.
+    pub current: HealthState,
+    /// The report of the check that found the change.
+    pub report: HealthReport,
+}
+
+/// Poll the health of a horizon server.
+pub struct HealthMonitor<C> {
+    client: C,
+    interval: Duration,
+    max_ingest_lag: u32,
+    max_ledger_age: Duration,
+}
+
+impl<C> HealthMonitor<C>
+where
+    C: HorizonClient + Sync,
+{
+    /// Creates a new monitor that checks horizon with `client`.
+    pub fn new(client: C) -> HealthMonitor<C> {
+        HealthMonitor {
+            client,
+            interval: DEFAULT_INTERVAL,
+            max_ingest_lag: DEFAULT_MAX_INGEST_LAG,
+            max_ledger_age: DEFAULT_MAX_LEDGER_AGE,
+        }
+    }
+
+    /// Sets the time between health checks.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the number of ledgers ingestion can lag behind stellar core.
+    pub fn with_max_ingest_lag(mut self, max_ingest_lag: u32) -> Self {
+        self.max_ingest_lag = max_ingest_lag;
+        self
+    }
+
+    /// Sets the age after which the latest ingested ledger is stale.
+    pub fn with_max_ledger_age(mut self, max_ledger_age: Duration) -> Self {
+        self.max_ledger_age = max_ledger_age;
+        self
+    }
+
+    /// Checks the health of horizon.
+    pub async fn check(&self) -> HealthReport {
+        let (root, health) = futures::join!(
+            self.client.request(api::root::root()),
+            self.client.request(api::health::health()),
+        );
+        self.report(
+            Utc::now(),
+            root.map(|(_, root)| root),
+            health.map(|(_, health)| health),
+        )
+    }
+
+    /// Returns a stream that checks horizon every interval and yields
+    /// the changes of health state.
+    ///
+    /// The first check always yields a transition.
+    pub fn transitions(self) -> BoxStream<'static, HealthTransition>
+    where
+        C: Send + 'static,
+    {
+        let monitor = Arc::new(self);
+        let state = (monitor, None, true);
+        Box::pin(stream::unfold(
+            state,
+            |(monitor, mut previous, mut first)| async move {
+                loop {
+                    if !first {
+                        tokio::time::sleep(monitor.interval).await;
+                    }
+                    first = false;
+                    let report = monitor.check().await;
+                    if previous.as_ref() != Some(&report.state) {
+                        let transition = HealthTransition {
+                            previous: previous.replace(report.state.clone()),
+                            current: report.state.clone(),
+                            report,
+                        };
+                        return Some((transition, (monitor, previous, first)));
+                    }
+                }
+            },
+        ))
+    }
+
+    fn report(
+        &self,
+        now: DateTime<Utc>,
+        root: Result<Root>,
+        health: Result<Health>,
+    ) -> HealthReport {
+        let root = root.ok();
+        let health = health.ok();
+        let mut issues = Vec::new();
+
+        match &health {
+            Some(health) => {
+                if !health.database_connected {
+                    issues.push(HealthIssue::DatabaseDisconnected);
+                }
+                if !health.core_up {
+                    issues.push(HealthIssue::CoreDown);
+                }
+                if !health.core_synced {
+                    issues.push(HealthIssue::CoreNotSynced);
+                }
+            }
+            None => issues.push(HealthIssue::HealthCheckFailed),
+        }
+
+        let (ingest_lag, ledger_age, protocol_supported) = match &root {
+            Some(root) => {
+                let ingest_lag = root
+                    .core_latest_ledger
+                    .value()
+                    .saturating_sub(root.history_latest_ledger.value());
+                if ingest_lag > self.max_ingest_lag {
+                    issues.push(HealthIssue::IngestLag);
+                }
+                let ledger_age = (now - root.history_latest_ledger_closed_at)
+                    .to_std()
+                    .unwrap_or_default();
+                if ledger_age > self.max_ledger_age {
+                    issues.push(HealthIssue::StaleLedger);
+                }
+                let protocol_supported = is_protocol_supported(root);
+                if !protocol_supported {
+                    issues.push(HealthIssue::UnsupportedProtocol);
+                }
+                (Some(ingest_lag), Some(ledger_age), Some(protocol_supported))
+            }
+            None => {
+                issues.push(HealthIssue::RootUnavailable);
+                (None, None, None)
+            }
+        };
+
+        let state = if issues.is_empty() {
+            HealthState::Healthy
+        } else {
+            HealthState::Unhealthy(issues)
+        };
+        HealthReport {
+            checked_at: now,
+            root,
+            health,
+            ingest_lag,
+            ledger_age,
+            protocol_supported,
+            state,
+        }
+    }
+}
+
+impl HealthState {
+    /// Returns true if no issues were found.
+    pub fn is_healthy(&self) -> bool {
+        *self == HealthState::Healthy
+    }
+}
+
+fn is_protocol_supported(root: &Root) -> bool {
+    let current = root.current_protocol_version;
+    current <= root.core_supported_protocol_version
+        && root
+            .supported_protocol_version
+            .is_none_or(|supported| current <= supported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::HorizonHttpClient;
+    use crate::mock::{root_json, MockClient, MockServer};
+    use futures::stream::StreamExt;
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn health_json(core_synced: bool) -> Value {
+        json!({
+            "database_connected": true,
+            "core_up": true,
+            "core_synced": core_synced,
+        })
+    }
+
+    fn root_closed_at(history_latest_ledger: u32, core_latest_ledger: u32) -> Value {
+        let mut root = root_json(history_latest_ledger, core_latest_ledger);
+        root["history_latest_ledger_closed_at"] = json!(Utc::now());
+        root
+    }
+
+    #[tokio::test]
+    async fn test_healthy() {
+        let client = MockClient::new(|uri| match uri.path() {
+            "/health" => Some(health_json(true)),
+            _ => Some(root_closed_at(100, 102)),
+        });
+        let report = HealthMonitor::new(client).check().await;
+        assert!(report.state.is_healthy());
+        assert_eq!(Some(2), report.ingest_lag);
+        assert_eq!(Some(true), report.protocol_supported);
+        assert!(report.ledger_age.unwrap() < DEFAULT_MAX_LEDGER_AGE);
+    }
+
+    #[tokio::test]
+    async fn test_unhealthy() {
+        let client = MockClient::new(|uri| match uri.path() {
+            "/health" => Some(health_json(false)),
+            _ => {
+                let mut root = root_json(100, 200);
+                root["current_protocol_version"] = json!(19);
+                Some(root)
+            }
+        });
+        let report = HealthMonitor::new(client).check().await;
+        let HealthState::Unhealthy(issues) = report.state else {
+            panic!("expected unhealthy state");
+        };
+        assert_eq!(
+            vec![
+                HealthIssue::CoreNotSynced,
+                HealthIssue::IngestLag,
+                HealthIssue::StaleLedger,
+                HealthIssue::UnsupportedProtocol
+            ],
+            issues
+        );
+        assert_eq!(Some(100), report.ingest_lag);
+        assert_eq!(Some(false), report.protocol_supported);
+    }
+
+    #[tokio::test]
+    async fn test_unhealthy_status() {
+        // Unhealthy horizon servers answer /health with a 503.
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/health" => (503, health_json(false)),
+            _ => (200, root_closed_at(100, 100)),
+        })
+        .await;
+        let client = HorizonHttpClient::new_from_str(server.url().as_str()).unwrap();
+        let report = HealthMonitor::new(client).check().await;
+        assert_eq!(
+            HealthState::Unhealthy(vec![HealthIssue::CoreNotSynced]),
+            report.state
+        );
+        assert_eq!(Some(false), report.health.map(|health| health.core_synced));
+        assert!(server
+            .requests()
+            .iter()
+            .any(|request| request.method == "GET" && request.path == "/health"));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable() {
+        let report = HealthMonitor::new(MockClient::new(|_| None)).check().await;
+        assert_eq!(
+            HealthState::Unhealthy(vec![
+                HealthIssue::HealthCheckFailed,
+                HealthIssue::RootUnavailable
+            ]),
+            report.state
+        );
+        assert_eq!(None, report.ingest_lag);
+    }
+
+    #[tokio::test]
+    async fn test_transitions() {
+        // Ingestion falls behind on the third check, then recovers.
+        let checks = AtomicU32::new(0);
+        let client = MockClient::new(move |uri| match uri.path() {
+            "/health" => Some(health_json(true)),
+            _ => match checks.fetch_add(1, Ordering::SeqCst) {
+                2 => Some(root_closed_at(100, 150)),
+                3 => Some(root_closed_at(100, 160)),
+                _ => Some(root_closed_at(100, 100)),
+            },
+        });
+        let transitions: Vec<_> = HealthMonitor::new(client)
+            .with_interval(Duration::from_millis(1))
+            .transitions()
+            .take(3)
+            .collect()
+            .await;
+        let lagging = HealthState::Unhealthy(vec![HealthIssue::IngestLag]);
+        assert_eq!(None, transitions[0].previous);
+        assert_eq!(HealthState::Healthy, transitions[0].current);
+        assert_eq!(Some(HealthState::Healthy), transitions[1].previous);
+        assert_eq!(lagging, transitions[1].current);
+        assert_eq!(Some(50), transitions[1].report.ingest_lag);
+        // The lag growing from 50 to 60 ledgers is not a transition.
+        assert_eq!(Some(lagging), transitions[2].previous);
+        assert_eq!(HealthState::Healthy, transitions[2].current);
+        assert_eq!(Some(0), transitions[2].report.ingest_lag);
+    }
+}
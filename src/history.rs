@@ -0,0 +1,177 @@
+//! Locate historical records by time.
+//!
+//! Horizon doesn't support filtering records by time, but ledgers
+//! close in order so it's possible to binary search the ledger that
+//! closed at a given time and use its TOID as cursor.
+//!
+//! ```rust
+//! use chrono::{TimeZone, Utc};
+//! use stellar_horizon::api;
+//! use stellar_horizon::client::HorizonHttpClient;
+//! use stellar_horizon::history;
+//! use futures::stream::TryStreamExt;
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let start = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+//! let end = Utc.with_ymd_and_hms(2026, 1, 2, 0, 0, 0).unwrap();
+//! let mut payments = history::records_between(&client, api::payments::all(), start, end);
+//! while let Some(payment) = payments.try_next().await? {
+//!     println!("Payment = {:?}", payment);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::api;
+use crate::client::HorizonClient;
+use crate::error::Result;
+use crate::page::Page;
+use crate::paging;
+use crate::request::{Order, PageRequest};
+use crate::resources::{LedgerSequence, TimestampedRecord};
+use crate::toid;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Returns the first ledger closed at or after `time`.
+///
+/// The search is limited to the ledgers in the Horizon history, so
+/// the oldest ledger in history is returned if `time` is before it.
+/// If `time` is after the latest ledger, the sequence of the next
+/// ledger is returned.
+pub async fn ledger_at<C>(client: &C, time: DateTime<Utc>) -> Result<LedgerSequence>
+where
+    C: HorizonClient,
+{
+    let (_, root) = client.request(api::root::root()).await?;
+    let latest = root.history_latest_ledger.value();
+    if time > root.history_latest_ledger_closed_at {
+        return Ok(LedgerSequence::new(latest + 1));
+    }
+
+    // Invariant: ledger `high` closed at or after `time`.
+    let mut low = root.history_elder_ledger.value();
+    let mut high = latest;
+    while low < high {
+        let middle = low + (high - low) / 2;
+        let (_, ledger) = client
+            .request(api::ledgers::single(LedgerSequence::new(middle)))
+            .await?;
+        if ledger.closed_at >= time {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    Ok(LedgerSequence::new(low))
+}
+
+/// Returns the cursor to page records from the first ledger closed at
+/// or after `time`, in ascending order.
+pub async fn cursor_at<C>(client: &C, time: DateTime<Utc>) -> Result<String>
+where
+    C: HorizonClient,
+{
+    let ledger = ledger_at(client, time).await?;
+    toid::cursor_for_ledger(ledger)
+}
+
+/// Returns a stream over the records of `request` created between
+/// `start` (inclusive) and `end` (exclusive).
+///
+/// The request cursor and order are replaced.
+pub fn records_between<'a, C, R, T>(
+    client: &'a C,
+    request: R,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> BoxStream<'a, Result<T>>
+where
+    C: HorizonClient + Sync,
+    R: PageRequest<Response = Page<T>> + Clone + 'a,
+    T: TimestampedRecord + DeserializeOwned + Serialize + Clone + Send + 'a,
+{
+    let records = stream::once(cursor_at(client, start))
+        .map_ok(move |cursor| {
+            let request = request
+                .clone()
+                .with_cursor(&cursor)
+                .with_order(&Order::Ascending);
+            paging::records(client, request)
+        })
+        .try_flatten();
+    paging::take_until(records, end).boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{ledger_json, page_json, MockClient};
+    use chrono::TimeZone;
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    // Ledgers 10 to 100, closed every 5 seconds.
+    fn client() -> MockClient {
+        MockClient::new(|uri| {
+            let path = uri.path();
+            if path == "/" {
+                let mut root: serde_json::Value =
+                    serde_json::from_str(include_str!("../tests/fixtures/root.json")).unwrap();
+                root["history_elder_ledger"] = json!(10);
+                root["history_latest_ledger"] = json!(100);
+                root["history_latest_ledger_closed_at"] = json!(Utc.timestamp_opt(500, 0).unwrap());
+                Some(root)
+            } else if let Some(sequence) = path.strip_prefix("/ledgers/") {
+                let sequence: u32 = sequence.parse().unwrap();
+                Some(ledger_json(sequence, sequence as i64 * 5))
+            } else if path == "/ledgers" {
+                let query: std::collections::HashMap<_, _> =
+                    uri.query_pairs().into_owned().collect();
+                let cursor: i64 = query.get("cursor").unwrap().parse().unwrap();
+                let first = (cursor >> 32) as u32 + 1;
+                let records = (first..(first + 5).min(101))
+                    .map(|sequence| ledger_json(sequence, sequence as i64 * 5))
+                    .collect();
+                Some(page_json(records))
+            } else {
+                None
+            }
+        })
+    }
+
+    #[test]
+    fn test_ledger_at() {
+        let client = client();
+        let at = |secs| block_on(ledger_at(&client, Utc.timestamp_opt(secs, 0).unwrap()));
+        assert_eq!(LedgerSequence::new(20), at(100).unwrap());
+        assert_eq!(LedgerSequence::new(21), at(101).unwrap());
+        assert_eq!(LedgerSequence::new(10), at(0).unwrap());
+        assert_eq!(LedgerSequence::new(100), at(500).unwrap());
+        assert_eq!(LedgerSequence::new(101), at(501).unwrap());
+    }
+
+    #[test]
+    fn test_cursor_at() {
+        let client = client();
+        let cursor = block_on(cursor_at(&client, Utc.timestamp_opt(100, 0).unwrap())).unwrap();
+        assert_eq!(
+            toid::cursor_for_ledger(LedgerSequence::new(20)).unwrap(),
+            cursor
+        );
+    }
+
+    #[test]
+    fn test_records_between() {
+        let client = client();
+        let start = Utc.timestamp_opt(98, 0).unwrap();
+        let end = Utc.timestamp_opt(150, 0).unwrap();
+        let ledgers: Vec<_> =
+            block_on(records_between(&client, api::ledgers::all(), start, end).try_collect())
+                .unwrap();
+        let sequences: Vec<_> = ledgers.iter().map(|l| l.sequence.value()).collect();
+        assert_eq!((20..30).collect::<Vec<_>>(), sequences);
+    }
+}
@@ -0,0 +1,458 @@
+//! Share streams between subscribers.
+//!
+//! Each Horizon stream uses one connection. A `StreamHub` opens a
+//! single upstream stream for each distinct request and broadcasts
+//! its events to all the subscribers of that request.
+//!
+//! The upstream stream is reconnected after errors and when it ends,
+//! resuming after the last event. Errors that would happen again,
+//! like a `404 Not Found`, are sent to the subscribers as
+//! `Error::HorizonRequestError` and close the upstream stream
+//! instead. The upstream stream is also closed as soon as its last
+//! subscriber is dropped.
+//!
+//! Subscribers that fall behind by more than the hub capacity skip
+//! the oldest events and receive `Error::StreamLagged`.
+//!
+//! The hub spawns the upstream streams on the tokio runtime.
+//!
+//! ```rust
+//! use stellar_horizon::api;
+//! use stellar_horizon::client::HorizonHttpClient;
+//! use stellar_horizon::hub::StreamHub;
+//! use stellar_horizon::request::PageRequest;
+//! use futures::stream::TryStreamExt;
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let hub = StreamHub::new(client);
+//! // Both subscribers share the same connection.
+//! let mut ledgers = hub.subscribe(api::ledgers::all().with_cursor("now"))?;
+//! let mut other_ledgers = hub.subscribe(api::ledgers::all().with_cursor("now"))?;
+//! while let Some(event) = ledgers.try_next().await? {
+//!     println!("Ledger = {}", event.resource.sequence);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::client::HorizonClient;
+use crate::error::{Error, Result};
+use crate::horizon_error::HorizonError;
+use crate::request::{request_key, ResumedRequest, StreamRequest};
+use crate::stream::StreamEvent;
+use futures::future::{self, Either};
+use futures::stream::{self, BoxStream, StreamExt};
+use std::any::Any;
+use std::collections::HashMap;
+use std::pin::pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// The default number of events buffered for each subscriber.
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// The default delay before reconnecting the upstream stream.
+pub const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Broadcast streams to multiple subscribers.
+pub struct StreamHub<C> {
+    inner: Arc<StreamHubInner<C>>,
+}
+
+type UpstreamSender<T> = broadcast::Sender<std::result::Result<StreamEvent<T>, HorizonError>>;
+
+struct StreamHubInner<C> {
+    client: C,
+    capacity: usize,
+    reconnect_delay: Duration,
+    upstreams: Mutex<HashMap<String, Box<dyn Any + Send>>>,
+}
+
+impl<C> StreamHub<C>
+where
+    C: HorizonClient + Send + Sync + 'static,
+{
+    /// Creates a new hub that opens streams with `client`.
+    pub fn new(client: C) -> StreamHub<C> {
+        StreamHub::new_with_options(client, DEFAULT_CAPACITY, DEFAULT_RECONNECT_DELAY)
+    }
+
+    /// Creates a new hub buffering `capacity` events for each
+    /// subscriber, and waiting `reconnect_delay` before reconnecting.
+    pub fn new_with_options(client: C, capacity: usize, reconnect_delay: Duration) -> StreamHub<C> {
+        let inner = StreamHubInner {
+            client,
+            capacity: capacity.max(1),
+            reconnect_delay,
+            upstreams: Mutex::new(HashMap::new()),
+        };
+        StreamHub {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Subscribes to the events of `request`.
+    ///
+    /// Opens the upstream stream if no other subscriber is subscribed
+    /// to the same request. New subscribers receive the events sent
+    /// after they subscribed.
+    pub fn subscribe<R>(
+        &self,
+        request: R,
+    ) -> Result<BoxStream<'static, Result<StreamEvent<R::Resource>>>>
+    where
+        R: StreamRequest + Clone + 'static,
+        R::Resource: Clone + 'static,
+    {
+        let key = upstream_key(&request)?;
+        let mut upstreams = self.inner.upstreams.lock().unwrap();
+        let existing = upstreams
+            .get(&key)
+            .and_then(|sender| sender.downcast_ref::<UpstreamSender<R::Resource>>())
+            .map(|sender| sender.subscribe());
+        let receiver = match existing {
+            Some(receiver) => receiver,
+            None => {
+                let (sender, receiver) = broadcast::channel(self.inner.capacity);
+                upstreams.insert(key.clone(), Box::new(sender.clone()));
+                tokio::spawn(run_upstream(self.inner.clone(), key, request, sender));
+                receiver
+            }
+        };
+        Ok(subscriber_stream(receiver))
+    }
+
+    /// Returns the number of open upstream streams.
+    pub fn upstream_count(&self) -> usize {
+        self.inner.upstreams.lock().unwrap().len()
+    }
+}
+
+impl<C> Clone for StreamHub<C> {
+    fn clone(&self) -> Self {
+        StreamHub {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<C> StreamHubInner<C> {
+    /// Removes the upstream if it has no subscribers left.
+    ///
+    /// Subscribers are added while holding the same lock, so no one
+    /// can subscribe to a removed upstream.
+    fn remove_if_unused<T>(&self, key: &str, sender: &broadcast::Sender<T>) -> bool {
+        let mut upstreams = self.upstreams.lock().unwrap();
+        if sender.receiver_count() == 0 {
+            upstreams.remove(key);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Removes the upstream after it failed with a permanent error.
+    fn remove(&self, key: &str) {
+        self.upstreams.lock().unwrap().remove(key);
+    }
+}
+
+fn upstream_key<R: StreamRequest>(request: &R) -> Result<String> {
+    let uri = request_key(request)?;
+    Ok(format!("{} {}", std::any::type_name::<R::Resource>(), uri))
+}
+
+async fn run_upstream<C, R>(
+    hub: Arc<StreamHubInner<C>>,
+    key: String,
+    request: R,
+    sender: UpstreamSender<R::Resource>,
+) where
+    C: HorizonClient + Send + Sync + 'static,
+    R: StreamRequest + Clone + 'static,
+    R::Resource: Clone,
+{
+    let request = Arc::new(request);
+    let mut last_id = None;
+    loop {
+        let resumed = ResumedRequest {
+            request: request.clone(),
+            cursor: last_id.clone(),
+        };
+        let mut events = match hub.client.stream_events(resumed) {
+            Ok(events) => events,
+            Err(Error::HorizonRequestError(err)) => {
+                return close_upstream(&hub, &key, &sender, err)
+            }
+            Err(_) => {
+                if !wait_reconnect(&hub, &key, &sender).await {
+                    return;
+                }
+                continue;
+            }
+        };
+        loop {
+            let event = match future::select(events.next(), pin!(sender.closed())).await {
+                Either::Left((Some(event), _)) => event,
+                Either::Left((None, _)) => break,
+                Either::Right(_) => {
+                    if hub.remove_if_unused(&key, &sender) {
+                        return;
+                    }
+                    continue;
+                }
+            };
+            match event {
+                Ok(event) => {
+                    if event.id.is_some() {
+                        last_id.clone_from(&event.id);
+                    }
+                    if sender.send(Ok(event)).is_err() && hub.remove_if_unused(&key, &sender) {
+                        return;
+                    }
+                }
+                // Horizon rejected the request, reconnecting fails again.
+                Err(Error::HorizonRequestError(err)) => {
+                    return close_upstream(&hub, &key, &sender, err)
+                }
+                // Streams reconnect when polled after an error.
+                Err(_) => {
+                    if !wait_reconnect(&hub, &key, &sender).await {
+                        return;
+                    }
+                }
+            }
+        }
+        if !wait_reconnect(&hub, &key, &sender).await {
+            return;
+        }
+    }
+}
+
+/// Sends `err` to the subscribers and removes the upstream, so that
+/// new subscribers open a new one.
+fn close_upstream<C, T>(
+    hub: &StreamHubInner<C>,
+    key: &str,
+    sender: &broadcast::Sender<std::result::Result<T, HorizonError>>,
+    err: HorizonError,
+) {
+    hub.remove(key);
+    let _ = sender.send(Err(err));
+}
+
+/// Waits the reconnect delay, returns false if the upstream was removed
+/// because all its subscribers were dropped meanwhile.
+async fn wait_reconnect<C, T>(
+    hub: &StreamHubInner<C>,
+    key: &str,
+    sender: &broadcast::Sender<T>,
+) -> bool {
+    let sleep = tokio::time::sleep(hub.reconnect_delay);
+    future::select(pin!(sleep), pin!(sender.closed())).await;
+    !hub.remove_if_unused(key, sender)
+}
+
+fn subscriber_stream<T>(
+    receiver: broadcast::Receiver<std::result::Result<T, HorizonError>>,
+) -> BoxStream<'static, Result<T>>
+where
+    T: Clone + Send + 'static,
+{
+    stream::unfold(receiver, |mut receiver| async move {
+        let item = match receiver.recv().await {
+            Ok(item) => item.map_err(Error::HorizonRequestError),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => Err(Error::StreamLagged(skipped)),
+            Err(broadcast::error::RecvError::Closed) => return None,
+        };
+        Some((item, receiver))
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api;
+    use crate::mock::{ledger_json, MockClient};
+    use futures::stream::TryStreamExt;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // Streams `count` ledgers on the first connection, nothing afterwards.
+    fn hub(count: u32, capacity: usize) -> StreamHub<MockClient> {
+        let connections = AtomicU32::new(0);
+        let client = MockClient::new(move |_| {
+            if connections.fetch_add(1, Ordering::SeqCst) == 0 {
+                let ledgers: Vec<_> = (1..=count).map(|s| ledger_json(s, s as i64)).collect();
+                Some(json!(ledgers))
+            } else {
+                Some(json!([]))
+            }
+        });
+        StreamHub::new_with_options(client, capacity, Duration::from_millis(5))
+    }
+
+    fn sequence(event: &StreamEvent<crate::resources::Ledger>) -> u32 {
+        event.resource.sequence.value()
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_share_upstream() {
+        let hub = hub(3, 16);
+        let first = hub.subscribe(api::ledgers::all()).unwrap();
+        let second = hub.subscribe(api::ledgers::all()).unwrap();
+        assert_eq!(1, hub.upstream_count());
+
+        let first: Vec<_> = first.take(3).try_collect().await.unwrap();
+        let second: Vec<_> = second.take(3).try_collect().await.unwrap();
+        assert_eq!(
+            vec![1, 2, 3],
+            first.iter().map(sequence).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![1, 2, 3],
+            second.iter().map(sequence).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_different_requests_use_different_upstreams() {
+        let hub = hub(3, 16);
+        let _ledgers = hub.subscribe(api::ledgers::all()).unwrap();
+        let _payments = hub.subscribe(api::payments::all()).unwrap();
+        assert_eq!(2, hub.upstream_count());
+    }
+
+    #[tokio::test]
+    async fn test_lagging_subscriber() {
+        let hub = hub(5, 2);
+        let mut ledgers = hub.subscribe(api::ledgers::all()).unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        match ledgers.next().await {
+            Some(Err(Error::StreamLagged(3))) => {}
+            other => panic!("expected lagged error, got {:?}", other.map(|r| r.is_ok())),
+        }
+        let rest: Vec<_> = ledgers.take(2).try_collect().await.unwrap();
+        assert_eq!(vec![4, 5], rest.iter().map(sequence).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_upstream_closed_without_subscribers() {
+        let hub = hub(0, 16);
+        let ledgers = hub.subscribe(api::ledgers::all()).unwrap();
+        drop(ledgers);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(0, hub.upstream_count());
+    }
+
+    // Sends nothing and never ends.
+    struct QuietClient;
+
+    impl HorizonClient for QuietClient {
+        fn request<'a, R: crate::request::Request + 'a>(
+            &'a self,
+            _req: R,
+        ) -> futures::future::BoxFuture<'a, Result<(crate::headers::HeaderMap, R::Response)>>
+        {
+            Box::pin(async { Err(Error::HorizonServerError) })
+        }
+
+        fn stream<'a, R: StreamRequest + 'static>(
+            &'a self,
+            _req: R,
+        ) -> Result<Box<dyn futures::Stream<Item = Result<R::Resource>> + 'static + Send + Unpin>>
+        {
+            Ok(Box::new(stream::pending()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quiet_upstream_closed_without_subscribers() {
+        let hub = StreamHub::new_with_options(QuietClient, 16, Duration::from_secs(3600));
+        let ledgers = hub.subscribe(api::ledgers::all()).unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(1, hub.upstream_count());
+        drop(ledgers);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(0, hub.upstream_count());
+    }
+
+    #[tokio::test]
+    async fn test_resume_after_last_event() {
+        // Each connection streams the next two ledgers, then ends.
+        let client = MockClient::new(|uri| {
+            let first = uri
+                .query_pairs()
+                .find(|(key, _)| key == "cursor")
+                .map_or(1, |(_, cursor)| {
+                    (cursor.parse::<i64>().unwrap() >> 32) as u32 + 1
+                });
+            let ledgers: Vec<_> = (first..first + 2)
+                .map(|s| ledger_json(s, s as i64))
+                .collect();
+            Some(json!(ledgers))
+        });
+        let hub = StreamHub::new_with_options(client, 16, Duration::from_millis(1));
+        let ledgers: Vec<_> = hub
+            .subscribe(api::ledgers::all())
+            .unwrap()
+            .take(5)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(
+            vec![1, 2, 3, 4, 5],
+            ledgers.iter().map(sequence).collect::<Vec<_>>()
+        );
+    }
+
+    /// Rejects every stream with a `404 Not Found`.
+    #[derive(Default)]
+    struct NotFoundClient {
+        connections: AtomicU32,
+    }
+
+    impl HorizonClient for NotFoundClient {
+        fn request<'a, R: crate::request::Request + 'a>(
+            &'a self,
+            _req: R,
+        ) -> futures::future::BoxFuture<'a, Result<(crate::headers::HeaderMap, R::Response)>>
+        {
+            Box::pin(async { Err(Error::HorizonServerError) })
+        }
+
+        fn stream<'a, R: StreamRequest + 'static>(
+            &'a self,
+            _req: R,
+        ) -> Result<Box<dyn futures::Stream<Item = Result<R::Resource>> + 'static + Send + Unpin>>
+        {
+            self.connections.fetch_add(1, Ordering::SeqCst);
+            let err = HorizonError::Other(crate::horizon_error::HorizonErrorBase {
+                url: "https://stellar.org/horizon-errors/not_found".to_string(),
+                title: "Resource Missing".to_string(),
+                detail: "The resource at the url requested was not found.".to_string(),
+                status: 404,
+            });
+            Ok(Box::new(stream::iter(vec![Err(
+                Error::HorizonRequestError(err),
+            )])))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_permanent_error_sent_to_subscribers() {
+        let hub =
+            StreamHub::new_with_options(NotFoundClient::default(), 16, Duration::from_millis(1));
+        let events: Vec<_> = hub.subscribe(api::ledgers::all()).unwrap().collect().await;
+        assert_eq!(1, events.len());
+        assert!(matches!(
+            &events[0],
+            Err(Error::HorizonRequestError(HorizonError::Other(err))) if err.status == 404
+        ));
+        assert_eq!(0, hub.upstream_count());
+        assert_eq!(1, hub.inner.client.connections.load(Ordering::SeqCst));
+    }
+}
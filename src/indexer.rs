@@ -0,0 +1,548 @@
+//! Index records in SQLite.
+//!
+//! An `Indexer` streams or backfills operations, effects, transactions
+//! and trades into a SQLite database, to answer queries that Horizon
+//! doesn't support. Records are upserted by paging token, so indexing
+//! the same record twice is harmless, and the cursor of each request is
+//! saved in the same database transaction as its records.
+//!
+//! Each table has the record json, together with columns for the fields
+//! most commonly queried. Amounts are stored in stroops and times as
+//! RFC 3339 strings, so that both can be compared in SQL.
+//!
+//! Requires the `indexer` feature.
+//!
+//! ```rust
+//! use stellar_horizon::api;
+//! use stellar_horizon::client::HorizonHttpClient;
+//! use stellar_horizon::indexer::Indexer;
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let indexer = Indexer::open("horizon.db")?;
+//! indexer.backfill(&client, "operations", api::operations::all()).await?;
+//! // All native payments over 1000 XLM in January.
+//! let count: i64 = indexer.connection().query_row(
+//!     "SELECT COUNT(*) FROM operations WHERE asset = 'native' AND amount > ?1
+//!      AND created_at BETWEEN '2024-01-01' AND '2024-02-01'",
+//!     [1000 * 10_000_000i64],
+//!     |row| row.get(0),
+//! )?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::client::HorizonClient;
+use crate::cursor::CursorStore;
+use crate::error::Result;
+use crate::page::Page;
+use crate::paging;
+use crate::request::{PageRequest, StreamRequest};
+use crate::resources::{Asset, Effect, Operation, Record, Trade, Transaction};
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures::future::{self, BoxFuture};
+use futures::stream::TryStreamExt;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS cursors (
+    key TEXT PRIMARY KEY,
+    cursor TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS transactions (
+    paging_token TEXT PRIMARY KEY,
+    hash TEXT NOT NULL,
+    ledger INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    source_account TEXT NOT NULL,
+    fee_account TEXT NOT NULL,
+    fee_charged INTEGER NOT NULL,
+    operation_count INTEGER NOT NULL,
+    successful INTEGER NOT NULL,
+    memo_type TEXT NOT NULL,
+    memo TEXT,
+    json TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS transactions_hash ON transactions (hash);
+CREATE INDEX IF NOT EXISTS transactions_source_account ON transactions (source_account, created_at);
+
+CREATE TABLE IF NOT EXISTS operations (
+    paging_token TEXT PRIMARY KEY,
+    transaction_hash TEXT NOT NULL,
+    type TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    source_account TEXT NOT NULL,
+    successful INTEGER NOT NULL,
+    from_account TEXT,
+    to_account TEXT,
+    asset TEXT,
+    amount INTEGER,
+    json TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS operations_created_at ON operations (created_at);
+CREATE INDEX IF NOT EXISTS operations_transaction_hash ON operations (transaction_hash);
+CREATE INDEX IF NOT EXISTS operations_from_account ON operations (from_account, created_at);
+CREATE INDEX IF NOT EXISTS operations_to_account ON operations (to_account, created_at);
+
+CREATE TABLE IF NOT EXISTS effects (
+    paging_token TEXT PRIMARY KEY,
+    operation_id TEXT NOT NULL,
+    type TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    account TEXT NOT NULL,
+    json TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS effects_operation_id ON effects (operation_id);
+CREATE INDEX IF NOT EXISTS effects_account ON effects (account, created_at);
+
+CREATE TABLE IF NOT EXISTS trades (
+    paging_token TEXT PRIMARY KEY,
+    ledger_close_time TEXT NOT NULL,
+    base_account TEXT,
+    base_asset TEXT NOT NULL,
+    base_amount INTEGER NOT NULL,
+    counter_account TEXT,
+    counter_asset TEXT NOT NULL,
+    counter_amount INTEGER NOT NULL,
+    base_is_seller INTEGER NOT NULL,
+    json TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS trades_ledger_close_time ON trades (ledger_close_time);
+CREATE INDEX IF NOT EXISTS trades_assets ON trades (base_asset, counter_asset, ledger_close_time);
+";
+
+/// A record that can be stored in the index.
+pub trait IndexedRecord: Record + DeserializeOwned + Serialize + Clone + Send + Sync {
+    /// Inserts the record, or replaces the record with the same paging
+    /// token.
+    fn upsert(&self, tx: &rusqlite::Transaction) -> Result<()>;
+}
+
+/// Index records in a SQLite database.
+pub struct Indexer {
+    conn: Mutex<Connection>,
+}
+
+impl Indexer {
+    /// Opens the database at `path`, creating it if it doesn't exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Indexer> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Indexer::new(conn)
+    }
+
+    /// Opens a new database in memory.
+    pub fn open_in_memory() -> Result<Indexer> {
+        Indexer::new(Connection::open_in_memory()?)
+    }
+
+    fn new(conn: Connection) -> Result<Indexer> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Indexer {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Returns the database connection, to query the index.
+    pub fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap()
+    }
+
+    /// Upserts `records` in a single database transaction.
+    pub fn index<T: IndexedRecord>(&self, records: &[T]) -> Result<()> {
+        self.index_with_cursor(records, None)
+    }
+
+    /// Streams the records of `request` into the index, until the
+    /// stream ends or returns an error.
+    ///
+    /// The stream starts from the cursor saved under `key`, or from the
+    /// request cursor if no cursor was saved.
+    pub async fn stream<C, R>(&self, client: &C, key: &str, request: R) -> Result<()>
+    where
+        C: HorizonClient + Sync,
+        R: StreamRequest + PageRequest + 'static,
+        R::Resource: IndexedRecord,
+    {
+        let request = match self.load(key).await? {
+            Some(cursor) => request.with_cursor(&cursor),
+            None => request,
+        };
+        let mut events = client.stream_events(request)?;
+        while let Some(event) = events.try_next().await? {
+            self.index_with_cursor(std::slice::from_ref(&event.resource), Some(key))?;
+        }
+        Ok(())
+    }
+
+    /// Fetches all the records of `request` into the index, one page at
+    /// a time.
+    ///
+    /// Paging starts from the cursor saved under `key`, or from the
+    /// request cursor if no cursor was saved.
+    pub async fn backfill<C, R, T>(&self, client: &C, key: &str, request: R) -> Result<()>
+    where
+        C: HorizonClient + Sync,
+        R: PageRequest<Response = Page<T>> + Clone,
+        T: IndexedRecord,
+    {
+        let request = match self.load(key).await? {
+            Some(cursor) => request.with_cursor(&cursor),
+            None => request,
+        };
+        let mut pages = paging::pages(client, request);
+        while let Some(page) = pages.try_next().await? {
+            self.index_with_cursor(&page.records, Some(key))?;
+        }
+        Ok(())
+    }
+
+    /// Upserts `records` and saves the paging token of the last record
+    /// under `key`, in the same database transaction.
+    fn index_with_cursor<T: IndexedRecord>(&self, records: &[T], key: Option<&str>) -> Result<()> {
+        let mut conn = self.connection();
+        let tx = conn.transaction()?;
+        for record in records {
+            record.upsert(&tx)?;
+        }
+        if let (Some(key), Some(last)) = (key, records.last()) {
+            save_cursor(&tx, key, last.paging_token().as_str())?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_cursor(&self, key: &str) -> Result<Option<String>> {
+        let cursor = self
+            .connection()
+            .query_row("SELECT cursor FROM cursors WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        Ok(cursor)
+    }
+}
+
+impl CursorStore for Indexer {
+    fn load<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        Box::pin(future::ready(self.load_cursor(key)))
+    }
+
+    fn save<'a>(&'a self, key: &'a str, cursor: &'a str) -> BoxFuture<'a, Result<()>> {
+        let saved = save_cursor(&self.connection(), key, cursor);
+        Box::pin(future::ready(saved))
+    }
+}
+
+impl IndexedRecord for Transaction {
+    fn upsert(&self, tx: &rusqlite::Transaction) -> Result<()> {
+        tx.execute(
+            "INSERT OR REPLACE INTO transactions (paging_token, hash, ledger, created_at,
+                source_account, fee_account, fee_charged, operation_count, successful,
+                memo_type, memo, json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                self.paging_token.as_str(),
+                self.hash.to_string(),
+                self.ledger.value(),
+                timestamp(&self.created_at),
+                self.source_account,
+                self.fee_account,
+                self.fee_charged,
+                self.operation_count,
+                self.successful,
+                self.memo_type,
+                self.memo,
+                serde_json::to_string(self)?,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+impl IndexedRecord for Operation {
+    fn upsert(&self, tx: &rusqlite::Transaction) -> Result<()> {
+        let base = self.base();
+        let json = serde_json::to_value(self)?;
+        let transfer = operation_transfer(self);
+        tx.execute(
+            "INSERT OR REPLACE INTO operations (paging_token, transaction_hash, type, created_at,
+                source_account, successful, from_account, to_account, asset, amount, json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                base.paging_token.as_str(),
+                base.transaction_hash.to_string(),
+                json["type"].as_str(),
+                timestamp(&base.created_at),
+                base.source_account,
+                base.transaction_successful,
+                transfer.as_ref().map(|t| t.from),
+                transfer.as_ref().map(|t| t.to),
+                transfer
+                    .as_ref()
+                    .map(|t| t.asset.map_or_else(|| "native".to_string(), asset_name)),
+                transfer.as_ref().and_then(|t| stroops(t.amount)),
+                json.to_string(),
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+impl IndexedRecord for Effect {
+    fn upsert(&self, tx: &rusqlite::Transaction) -> Result<()> {
+        let base = self.base();
+        let json = serde_json::to_value(self)?;
+        // Effect paging tokens are the operation id followed by the
+        // effect index.
+        let operation_id = base.paging_token.as_str().split('-').next();
+        tx.execute(
+            "INSERT OR REPLACE INTO effects (paging_token, operation_id, type, created_at,
+                account, json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                base.paging_token.as_str(),
+                operation_id,
+                json["type"].as_str(),
+                timestamp(&base.created_at),
+                base.account,
+                json.to_string(),
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+impl IndexedRecord for Trade {
+    fn upsert(&self, tx: &rusqlite::Transaction) -> Result<()> {
+        tx.execute(
+            "INSERT OR REPLACE INTO trades (paging_token, ledger_close_time, base_account,
+                base_asset, base_amount, counter_account, counter_asset, counter_amount,
+                base_is_seller, json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                self.paging_token.as_str(),
+                timestamp(&self.ledger_close_time),
+                self.base_account,
+                asset_name(&self.base_asset),
+                stroops(&self.base_amount),
+                self.counter_account,
+                asset_name(&self.counter_asset),
+                stroops(&self.counter_amount),
+                self.base_is_seller,
+                serde_json::to_string(self)?,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+struct Transfer<'a> {
+    from: &'a str,
+    to: &'a str,
+    // `None` for create account operations, which transfer lumens.
+    asset: Option<&'a Asset>,
+    amount: &'a str,
+}
+
+fn operation_transfer(operation: &Operation) -> Option<Transfer<'_>> {
+    let transfer = match operation {
+        Operation::CreateAccount(op) => Transfer {
+            from: &op.funder,
+            to: &op.account,
+            asset: None,
+            amount: &op.starting_balance,
+        },
+        Operation::Payment(op) => Transfer {
+            from: &op.from,
+            to: &op.to,
+            asset: Some(&op.asset),
+            amount: &op.amount,
+        },
+        Operation::PathPaymentStrictReceive(op) => Transfer {
+            from: &op.from,
+            to: &op.to,
+            asset: Some(&op.asset),
+            amount: &op.amount,
+        },
+        Operation::PathPaymentStrictSend(op) => Transfer {
+            from: &op.from,
+            to: &op.to,
+            asset: Some(&op.asset),
+            amount: &op.amount,
+        },
+        _ => return None,
+    };
+    Some(transfer)
+}
+
+fn save_cursor(conn: &Connection, key: &str, cursor: &str) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO cursors (key, cursor) VALUES (?1, ?2)",
+        [key, cursor],
+    )?;
+    Ok(())
+}
+
+/// Returns the asset as `native` or `CODE:ISSUER`.
+fn asset_name(asset: &Asset) -> String {
+    match (&asset.asset_code, &asset.asset_issuer) {
+        (Some(code), Some(issuer)) => format!("{}:{}", code, issuer),
+        _ => "native".to_string(),
+    }
+}
+
+/// Parses an amount with up to 7 decimals into stroops.
+fn stroops(amount: &str) -> Option<i64> {
+    let (units, decimals) = amount.split_once('.').unwrap_or((amount, ""));
+    if decimals.len() > 7 || !decimals.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let units: i64 = units.parse().ok()?;
+    let decimals: i64 = format!("{:0<7}", decimals).parse().ok()?;
+    units.checked_mul(10_000_000)?.checked_add(decimals)
+}
+
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api;
+    use crate::mock::{fixture, page_json, payment_json, MockClient};
+    use futures::executor::block_on;
+    use serde_json::{json, Value};
+
+    const ACCOUNT: &str = "GA73S4WXZG7EONFCIFDSZ6VOJKFC2PMV5574YDJC4V4UBDGPAYN4SPAC";
+
+    fn count(indexer: &Indexer, table: &str) -> i64 {
+        indexer
+            .connection()
+            .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_stroops() {
+        assert_eq!(Some(10_000_000), stroops("1.0000000"));
+        assert_eq!(Some(12_345_000), stroops("1.2345"));
+        assert_eq!(Some(5), stroops("0.0000005"));
+        assert_eq!(Some(20_000_000), stroops("2"));
+        assert_eq!(None, stroops("0.00000001"));
+        assert_eq!(None, stroops("abc"));
+    }
+
+    #[test]
+    fn test_index_is_idempotent() {
+        let indexer = Indexer::open_in_memory().unwrap();
+        let transactions: Vec<Transaction> =
+            fixture(include_str!("../tests/fixtures/all_transactions.json"));
+        let effects: Vec<Effect> = fixture(include_str!("../tests/fixtures/all_effects.json"));
+        let trades: Vec<Trade> = fixture(include_str!("../tests/fixtures/all_trades.json"));
+        for _ in 0..2 {
+            indexer.index(&transactions).unwrap();
+            indexer.index(&effects).unwrap();
+            indexer.index(&trades).unwrap();
+        }
+        assert_eq!(transactions.len() as i64, count(&indexer, "transactions"));
+        assert_eq!(effects.len() as i64, count(&indexer, "effects"));
+        assert_eq!(trades.len() as i64, count(&indexer, "trades"));
+
+        let operation_id: String = indexer
+            .connection()
+            .query_row(
+                "SELECT operation_id FROM effects WHERE paging_token = '2347349951127555-4'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!("2347349951127555", operation_id);
+    }
+
+    #[test]
+    fn test_index_operations() {
+        let indexer = Indexer::open_in_memory().unwrap();
+        let operations: Vec<Operation> =
+            fixture(include_str!("../tests/fixtures/all_operations.json"));
+        indexer.index(&operations).unwrap();
+
+        let (kind, asset, amount): (String, String, i64) = indexer
+            .connection()
+            .query_row(
+                "SELECT type, asset, amount FROM operations WHERE paging_token = ?1",
+                [operations[1].paging_token().as_str()],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!("payment", kind);
+        assert_eq!("native", asset);
+        assert!(amount > 0);
+
+        let transfers: i64 = indexer
+            .connection()
+            .query_row(
+                "SELECT COUNT(*) FROM operations WHERE amount IS NOT NULL",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(5, transfers);
+    }
+
+    #[test]
+    fn test_backfill_resumes_from_cursor() {
+        let payments: Vec<Value> = (1..=3)
+            .map(|n| payment_json(ACCOUNT, ACCOUNT, n << 12))
+            .collect();
+        let records = payments.clone();
+        let client = MockClient::new(move |uri| {
+            let cursor: i64 = uri
+                .query_pairs()
+                .find(|(k, _)| k == "cursor")
+                .map_or(0, |(_, v)| v.parse().unwrap());
+            let page: Vec<_> = records
+                .iter()
+                .filter(|p| p["paging_token"].as_str().unwrap().parse::<i64>().unwrap() > cursor)
+                .take(2)
+                .cloned()
+                .collect();
+            Some(page_json(page))
+        });
+        let indexer = Indexer::open_in_memory().unwrap();
+        block_on(indexer.backfill(&client, "operations", api::operations::all())).unwrap();
+        assert_eq!(3, count(&indexer, "operations"));
+        let cursor = block_on(indexer.load("operations")).unwrap();
+        assert_eq!(Some((3i64 << 12).to_string()), cursor);
+
+        // Resuming only requests records after the saved cursor.
+        block_on(indexer.backfill(&client, "operations", api::operations::all())).unwrap();
+        let last = client.requests().pop().unwrap();
+        assert!(last
+            .query()
+            .unwrap()
+            .contains(&format!("cursor={}", 3i64 << 12)));
+    }
+
+    #[test]
+    fn test_stream() {
+        let client = MockClient::new(|_| {
+            Some(json!([
+                payment_json(ACCOUNT, ACCOUNT, 1 << 12),
+                payment_json(ACCOUNT, ACCOUNT, 2 << 12),
+            ]))
+        });
+        let indexer = Indexer::open_in_memory().unwrap();
+        block_on(indexer.stream(&client, "live", api::operations::all())).unwrap();
+        assert_eq!(2, count(&indexer, "operations"));
+        let cursor = block_on(indexer.load("live")).unwrap();
+        assert_eq!(Some((2i64 << 12).to_string()), cursor);
+    }
+}
@@ -90,13 +90,38 @@ extern crate stellar_base;
 pub mod request;
 
 pub mod api;
+pub mod auth;
+pub mod backfill;
+pub mod batch;
+pub mod cache;
 pub mod client;
+pub mod coalesce;
+pub mod cursor;
+pub mod deposit;
 pub mod error;
+pub mod export;
+pub mod failover;
 pub mod headers;
+pub mod health;
+pub mod history;
 pub mod horizon_error;
+pub mod hub;
+#[cfg(feature = "indexer")]
+pub mod indexer;
 pub mod link;
+pub mod network;
+pub mod options;
 pub mod page;
+pub mod paging;
 pub mod resources;
+pub mod stream;
+pub mod toid;
+pub mod verify;
+pub mod watcher;
+pub mod webhook;
+
+#[cfg(test)]
+mod mock;
 
 /// The crate version.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,259 @@
+//! Mock horizon client and server used in unit tests.
+use crate::client::HorizonClient;
+use crate::error::{Error, Result};
+use crate::headers::HeaderMap;
+use crate::page::Page;
+use crate::request::{Request, StreamRequest};
+use crate::stream::{EventStream, StreamEvent};
+use chrono::{TimeZone, Utc};
+use futures::future::BoxFuture;
+use futures::stream::{self, Stream, TryStreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use url::Url;
+
+type Responder = Box<dyn Fn(&Url) -> Option<Value> + Send + Sync>;
+
+/// A client that answers requests with the json returned by a closure.
+///
+/// Returning `None` from the closure results in a server error.
+/// Stream requests expect the closure to return an array of events.
+pub struct MockClient {
+    host: Url,
+    responder: Responder,
+    requests: Mutex<Vec<Url>>,
+}
+
+impl MockClient {
+    pub fn new<F>(responder: F) -> MockClient
+    where
+        F: Fn(&Url) -> Option<Value> + Send + Sync + 'static,
+    {
+        MockClient {
+            host: "https://horizon.example.org".parse().unwrap(),
+            responder: Box::new(responder),
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the uri of all requests sent to this client.
+    pub fn requests(&self) -> Vec<Url> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn respond(&self, uri: Url) -> Result<Value> {
+        self.requests.lock().unwrap().push(uri.clone());
+        (self.responder)(&uri).ok_or(Error::HorizonServerError)
+    }
+}
+
+impl HorizonClient for MockClient {
+    fn request<'a, R: Request + 'a>(
+        &'a self,
+        req: R,
+    ) -> BoxFuture<'a, Result<(HeaderMap, R::Response)>> {
+        Box::pin(async move {
+            let uri = req.uri(&self.host)?;
+            let value = self.respond(uri)?;
+            Ok((HeaderMap::new(), serde_json::from_value(value)?))
+        })
+    }
+
+    fn stream<'a, R: StreamRequest + 'static>(
+        &'a self,
+        req: R,
+    ) -> Result<Box<dyn Stream<Item = Result<R::Resource>> + 'static + Send + Unpin>> {
+        let events = self.stream_events(req)?;
+        Ok(Box::new(events.map_ok(StreamEvent::into_resource)))
+    }
+
+    fn stream_events<R: StreamRequest + 'static>(
+        &self,
+        req: R,
+    ) -> Result<EventStream<R::Resource>> {
+        let uri = req.uri(&self.host)?;
+        let events = match self.respond(uri)? {
+            Value::Array(events) => events,
+            _ => return Err(Error::SSEDecoderError),
+        };
+        // Use the paging token as event id, like horizon does.
+        let events: Vec<Result<StreamEvent<R::Resource>>> = events
+            .into_iter()
+            .map(|event| {
+                let id = event["paging_token"].as_str().map(str::to_string);
+                Ok(StreamEvent::new(id, serde_json::from_value(event)?))
+            })
+            .collect();
+        Ok(Box::new(stream::iter(events)))
+    }
+}
+
+/// Returns the records of the page in the fixture `json`.
+pub fn fixture<T: DeserializeOwned + Serialize + Clone>(json: &str) -> Vec<T> {
+    let page: Page<T> = serde_json::from_str(json).unwrap();
+    page.records
+}
+
+/// Returns the json of a page with `records`.
+pub fn page_json(records: Vec<Value>) -> Value {
+    json!({
+        "_embedded": {
+            "records": records,
+        },
+    })
+}
+
+/// Returns the json of ledger `sequence`, closed `closed_at` seconds
+/// after the unix epoch.
+///
+/// The ledger hash is its sequence, so that ledgers form a chain.
+pub fn ledger_json(sequence: u32, closed_at: i64) -> Value {
+    let mut ledger: Value =
+        serde_json::from_str(include_str!("../tests/fixtures/ledger.json")).unwrap();
+    let closed_at = Utc.timestamp_opt(closed_at, 0).unwrap();
+    ledger["sequence"] = json!(sequence);
+    ledger["hash"] = json!(format!("{:064x}", sequence));
+    ledger["prev_hash"] = json!(format!("{:064x}", sequence.saturating_sub(1)));
+    ledger["paging_token"] = json!(((sequence as i64) << 32).to_string());
+    ledger["closed_at"] = json!(closed_at);
+    ledger
+}
+
+/// Returns the json of the horizon root, with history ingested up to
+/// `history_latest_ledger` and stellar core at `core_latest_ledger`.
+pub fn root_json(history_latest_ledger: u32, core_latest_ledger: u32) -> Value {
+    let mut root: Value =
+        serde_json::from_str(include_str!("../tests/fixtures/root.json")).unwrap();
+    root["history_latest_ledger"] = json!(history_latest_ledger);
+    root["ingest_latest_ledger"] = json!(history_latest_ledger);
+    root["core_latest_ledger"] = json!(core_latest_ledger);
+    root
+}
+
+/// Returns the json of a payment of 1 XLM from `from` to `to`.
+pub fn payment_json(from: &str, to: &str, paging_token: i64) -> Value {
+    let payments: Value =
+        serde_json::from_str(include_str!("../tests/fixtures/all_payments.json")).unwrap();
+    let mut payment = payments["_embedded"]["records"][1].clone();
+    payment["id"] = json!(paging_token.to_string());
+    payment["paging_token"] = json!(paging_token.to_string());
+    payment["source_account"] = json!(from);
+    payment["from"] = json!(from);
+    payment["to"] = json!(to);
+    payment["amount"] = json!("1.0000000");
+    payment
+}
+
+/// A request received by a `MockServer`.
+#[derive(Debug, Clone)]
+pub struct ServerRequest {
+    pub method: String,
+    pub path: String,
+    /// The headers, with lowercase names.
+    pub headers: HashMap<String, String>,
+}
+
+type ServerResponder = Arc<dyn Fn(&ServerRequest) -> (u16, Value) + Send + Sync>;
+
+/// A http server that answers requests with the status and json
+/// returned by a closure, used to test `HorizonHttpClient`.
+///
+/// Each connection serves one request.
+pub struct MockServer {
+    url: Url,
+    requests: Arc<Mutex<Vec<ServerRequest>>>,
+}
+
+impl MockServer {
+    pub async fn start<F>(responder: F) -> MockServer
+    where
+        F: Fn(&ServerRequest) -> (u16, Value) + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let responder: ServerResponder = Arc::new(responder);
+        let received = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let received = received.clone();
+                let responder = responder.clone();
+                tokio::spawn(async move {
+                    let request = match read_request(&mut socket).await {
+                        Some(request) => request,
+                        None => return,
+                    };
+                    received.lock().unwrap().push(request.clone());
+                    let (status, body) = responder(&request);
+                    let body = body.to_string();
+                    let response = format!(
+                        "HTTP/1.1 {} X\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        MockServer {
+            url: url.parse().unwrap(),
+            requests,
+        }
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Returns all requests received by this server.
+    pub fn requests(&self) -> Vec<ServerRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn read_request(socket: &mut tokio::net::TcpStream) -> Option<ServerRequest> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 4096];
+    let header_end = loop {
+        let n = socket.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+        if let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+    };
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let headers: HashMap<_, _> = lines
+        .filter_map(|line| line.split_once(": "))
+        .map(|(name, value)| (name.to_lowercase(), value.to_string()))
+        .collect();
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    while buffer.len() < header_end + length {
+        let n = socket.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+    Some(ServerRequest {
+        method,
+        path,
+        headers,
+    })
+}
@@ -0,0 +1,177 @@
+//! Stellar network presets.
+//!
+//! A `Network` pairs a horizon url with the passphrase of the network
+//! it serves. Clients created with `HorizonHttpClient::for_network`
+//! check that horizon uses the expected passphrase before sending their
+//! first request, so that transactions are never submitted to the
+//! wrong network because of a misconfigured url.
+//!
+//! ```rust
+//! use stellar_horizon::client::HorizonHttpClient;
+//! use stellar_horizon::network::Network;
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::for_network(Network::Testnet)?;
+//! client.verify_network().await?;
+//! # Ok(())
+//! # }
+//! ```
+use stellar_base::network::{PUBLIC_PASSPHRASE, TEST_PASSPHRASE};
+
+/// The passphrase of the future network.
+pub const FUTURENET_PASSPHRASE: &str = "Test SDF Future Network ; October 2022";
+
+/// The horizon url of the public network.
+pub const PUBLIC_HORIZON_URL: &str = "https://horizon.stellar.org/";
+
+/// The horizon url of the test network.
+pub const TESTNET_HORIZON_URL: &str = "https://horizon-testnet.stellar.org/";
+
+/// The horizon url of the future network.
+pub const FUTURENET_HORIZON_URL: &str = "https://horizon-futurenet.stellar.org/";
+
+/// A stellar network and its horizon url.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Network {
+    /// The public network, using the SDF horizon.
+    Public,
+    /// The test network, using the SDF horizon.
+    Testnet,
+    /// The future network, using the SDF horizon.
+    Futurenet,
+    /// Any network, using any horizon.
+    Custom {
+        /// The horizon url.
+        horizon_url: String,
+        /// The network passphrase.
+        passphrase: String,
+    },
+}
+
+impl Network {
+    /// Creates a custom network served by `horizon_url`.
+    pub fn custom<U, P>(horizon_url: U, passphrase: P) -> Network
+    where
+        U: Into<String>,
+        P: Into<String>,
+    {
+        Network::Custom {
+            horizon_url: horizon_url.into(),
+            passphrase: passphrase.into(),
+        }
+    }
+
+    /// Returns the network passphrase.
+    pub fn passphrase(&self) -> &str {
+        match self {
+            Network::Public => PUBLIC_PASSPHRASE,
+            Network::Testnet => TEST_PASSPHRASE,
+            Network::Futurenet => FUTURENET_PASSPHRASE,
+            Network::Custom { passphrase, .. } => passphrase,
+        }
+    }
+
+    /// Returns the horizon url.
+    pub fn horizon_url(&self) -> &str {
+        match self {
+            Network::Public => PUBLIC_HORIZON_URL,
+            Network::Testnet => TESTNET_HORIZON_URL,
+            Network::Futurenet => FUTURENET_HORIZON_URL,
+            Network::Custom { horizon_url, .. } => horizon_url,
+        }
+    }
+
+    /// Returns the `stellar_base` network, used to sign transactions.
+    pub fn to_stellar_network(&self) -> stellar_base::Network {
+        stellar_base::Network::new(self.passphrase().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api;
+    use crate::client::{HorizonClient, HorizonHttpClient};
+    use crate::error::Error;
+    use crate::mock::{ledger_json, root_json, MockServer};
+    use stellar_base::transaction::TransactionEnvelope;
+    use stellar_base::xdr::XDRDeserialize;
+
+    #[test]
+    fn test_passphrases() {
+        assert_eq!(
+            stellar_base::Network::new_public().network_id(),
+            Network::Public.to_stellar_network().network_id()
+        );
+        assert_eq!(
+            stellar_base::Network::new_test().network_id(),
+            Network::Testnet.to_stellar_network().network_id()
+        );
+        let network = Network::custom(
+            "http://localhost:8000/",
+            "Standalone Network ; February 2017",
+        );
+        assert_eq!("Standalone Network ; February 2017", network.passphrase());
+        assert_eq!("http://localhost:8000/", network.horizon_url());
+    }
+
+    async fn client_for(passphrase: &str) -> (HorizonHttpClient, MockServer) {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/" => (200, root_json(100, 100)),
+            _ => (200, ledger_json(100, 0)),
+        })
+        .await;
+        let network = Network::custom(server.url().as_str(), passphrase);
+        (HorizonHttpClient::for_network(network).unwrap(), server)
+    }
+
+    #[tokio::test]
+    async fn test_verify_network_on_first_request() {
+        let (client, server) = client_for(TEST_PASSPHRASE).await;
+        client
+            .request(api::ledgers::single(100.into()))
+            .await
+            .unwrap();
+        client
+            .request(api::ledgers::single(100.into()))
+            .await
+            .unwrap();
+        let paths: Vec<_> = server
+            .requests()
+            .into_iter()
+            .map(|request| request.path)
+            .collect();
+        assert_eq!(vec!["/", "/ledgers/100", "/ledgers/100"], paths);
+    }
+
+    #[tokio::test]
+    async fn test_refuse_requests_to_other_network() {
+        let (client, server) = client_for(PUBLIC_PASSPHRASE).await;
+        for _ in 0..2 {
+            let result = client.request(api::ledgers::single(100.into())).await;
+            match result {
+                Err(Error::NetworkMismatch { expected, found }) => {
+                    assert_eq!(PUBLIC_PASSPHRASE, expected);
+                    assert_eq!(TEST_PASSPHRASE, found);
+                }
+                _ => panic!("expected network mismatch"),
+            }
+        }
+        let transactions: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/fixtures/all_transactions.json")).unwrap();
+        let envelope = transactions["_embedded"]["records"][0]["envelope_xdr"]
+            .as_str()
+            .unwrap();
+        let envelope = TransactionEnvelope::from_xdr_base64(envelope).unwrap();
+        let submitted = client
+            .request(api::transactions::submit(&envelope).unwrap())
+            .await;
+        assert!(matches!(submitted, Err(Error::NetworkMismatch { .. })));
+        // Only the root is requested.
+        assert!(server
+            .requests()
+            .iter()
+            .all(|request| request.method == "GET" && request.path == "/"));
+        assert!(client.verify_network().await.is_err());
+    }
+}
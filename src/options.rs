@@ -0,0 +1,369 @@
+//! Per-request options.
+//!
+//! `Request::with_options` wraps any request with `RequestOptions`: a
+//! timeout or deadline, extra headers, a cancellation token and the
+//! number of retries. The wrapped request is still a `Request`, so it
+//! can be sent with any `HorizonClient`.
+//!
+//! ```rust
+//! use stellar_horizon::api;
+//! use stellar_horizon::client::{HorizonClient, HorizonHttpClient};
+//! use stellar_horizon::options::RequestOptions;
+//! use stellar_horizon::request::Request;
+//! use std::time::Duration;
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let options = RequestOptions::new()
+//!     .with_timeout(Duration::from_secs(2))
+//!     .with_retries(0);
+//! let (_, fee_stats) = client
+//!     .request(api::aggregations::fee_stats().with_options(options))
+//!     .await?;
+//! println!("Fee stats = {:?}", fee_stats);
+//! # Ok(())
+//! # }
+//! ```
+use crate::error::{Error, Result};
+use crate::request::{Order, PageRequest, Request, StreamRequest};
+use futures::future::{self, Either, Future};
+use std::pin::pin;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+/// Options of a single request.
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    headers: hyper::HeaderMap,
+    cancellation: Option<CancellationToken>,
+    retries: Option<u32>,
+}
+
+/// A request together with its options.
+#[derive(Debug, Clone)]
+pub struct WithOptions<R> {
+    request: R,
+    options: RequestOptions,
+}
+
+impl RequestOptions {
+    /// Creates new empty options.
+    pub fn new() -> RequestOptions {
+        RequestOptions::default()
+    }
+
+    /// Fails the request with `Error::RequestTimeout` if it takes longer
+    /// than `timeout`, retries included.
+    ///
+    /// Streams end after `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Fails the request with `Error::RequestTimeout` if it's not done
+    /// by `deadline`.
+    ///
+    /// Streams end at `deadline`.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Adds a header to the request, replacing the client headers with
+    /// the same name.
+    pub fn with_header(
+        mut self,
+        name: hyper::header::HeaderName,
+        value: hyper::header::HeaderValue,
+    ) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Fails the request with `Error::RequestCancelled` when `token` is
+    /// cancelled.
+    ///
+    /// Streams end when `token` is cancelled.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Retries the request up to `retries` times after a server error,
+    /// instead of the number of retries of the client.
+    ///
+    /// Streams ignore this option.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = Some(retries);
+        self
+    }
+
+    /// Returns the timeout.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Returns the deadline.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Returns the extra headers.
+    pub fn headers(&self) -> &hyper::HeaderMap {
+        &self.headers
+    }
+
+    /// Returns the cancellation token.
+    pub fn cancellation(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+
+    /// Returns the number of retries, if it overrides the client.
+    pub fn retries(&self) -> Option<u32> {
+        self.retries
+    }
+
+    /// Returns the earliest of the deadline and the timeout, for a
+    /// request started at `start`.
+    pub(crate) fn deadline_from(&self, start: Instant) -> Option<Instant> {
+        let timeout = self.timeout.map(|timeout| start + timeout);
+        match (self.deadline, timeout) {
+            (Some(deadline), Some(timeout)) => Some(deadline.min(timeout)),
+            (deadline, timeout) => deadline.or(timeout),
+        }
+    }
+
+    /// Resolves when the request must stop, because it's past its
+    /// deadline or it was cancelled.
+    pub(crate) fn interrupted(
+        &self,
+        start: Instant,
+    ) -> impl Future<Output = Error> + Send + 'static {
+        let deadline = self.deadline_from(start);
+        let cancellation = self.cancellation.clone();
+        async move {
+            let timeout = async {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => future::pending().await,
+                }
+            };
+            let cancelled = async {
+                match cancellation {
+                    Some(cancellation) => cancellation.cancelled_owned().await,
+                    None => future::pending().await,
+                }
+            };
+            match future::select(pin!(cancelled), pin!(timeout)).await {
+                Either::Left(_) => Error::RequestCancelled,
+                Either::Right(_) => Error::RequestTimeout,
+            }
+        }
+    }
+}
+
+/// Runs `future`, unless it's interrupted according to `options`.
+pub(crate) async fn run<F, T>(options: Option<&RequestOptions>, future: F) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    let options = match options {
+        Some(options) => options,
+        None => return future.await,
+    };
+    let interrupted = options.interrupted(Instant::now());
+    match future::select(pin!(interrupted), pin!(future)).await {
+        Either::Left((err, _)) => Err(err),
+        Either::Right((result, _)) => result,
+    }
+}
+
+impl<R> WithOptions<R> {
+    /// Wraps `request` with `options`.
+    pub fn new(request: R, options: RequestOptions) -> WithOptions<R> {
+        WithOptions { request, options }
+    }
+
+    /// Returns the wrapped request.
+    pub fn request(&self) -> &R {
+        &self.request
+    }
+
+    /// Returns the request and its options.
+    pub fn into_parts(self) -> (R, RequestOptions) {
+        (self.request, self.options)
+    }
+}
+
+impl<R: Request> Request for WithOptions<R> {
+    type Response = R::Response;
+
+    fn post_body(&self) -> Result<Option<String>> {
+        self.request.post_body()
+    }
+
+    fn uri(&self, host: &Url) -> Result<Url> {
+        self.request.uri(host)
+    }
+
+    fn options(&self) -> Option<&RequestOptions> {
+        Some(&self.options)
+    }
+
+    fn accepts_status(&self, status: u16) -> bool {
+        self.request.accepts_status(status)
+    }
+}
+
+impl<R: PageRequest> PageRequest for WithOptions<R> {
+    fn with_cursor(mut self, cursor: &str) -> Self {
+        self.request = self.request.with_cursor(cursor);
+        self
+    }
+
+    fn cursor(&self) -> &Option<String> {
+        self.request.cursor()
+    }
+
+    fn with_limit(mut self, limit: u64) -> Self {
+        self.request = self.request.with_limit(limit);
+        self
+    }
+
+    fn limit(&self) -> &Option<u64> {
+        self.request.limit()
+    }
+
+    fn with_order(mut self, direction: &Order) -> Self {
+        self.request = self.request.with_order(direction);
+        self
+    }
+
+    fn order(&self) -> &Option<Order> {
+        self.request.order()
+    }
+}
+
+impl<R: StreamRequest> StreamRequest for WithOptions<R> {
+    type Resource = R::Resource;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api;
+    use crate::client::{HorizonClient, HorizonHttpClient};
+    use crate::mock::{ledger_json, MockServer};
+    use futures::stream::StreamExt;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_deadline_from() {
+        let start = Instant::now();
+        let options = RequestOptions::new();
+        assert_eq!(None, options.deadline_from(start));
+        let options = options.with_timeout(Duration::from_secs(5));
+        assert_eq!(
+            Some(start + Duration::from_secs(5)),
+            options.deadline_from(start)
+        );
+        let options = options.with_deadline(start + Duration::from_secs(2));
+        assert_eq!(
+            Some(start + Duration::from_secs(2)),
+            options.deadline_from(start)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_headers() {
+        let server = MockServer::start(|_| (200, ledger_json(100, 0))).await;
+        let client = HorizonHttpClient::new_from_str(server.url().as_str()).unwrap();
+        let options = RequestOptions::new()
+            .with_header("x-request-id".parse().unwrap(), "42".parse().unwrap())
+            .with_header("x-client-name".parse().unwrap(), "batch".parse().unwrap());
+        client
+            .request(api::ledgers::single(100.into()).with_options(options))
+            .await
+            .unwrap();
+        let request = &server.requests()[0];
+        assert_eq!("42", request.headers["x-request-id"]);
+        assert_eq!("batch", request.headers["x-client-name"]);
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        // The server accepts connections but never answers.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+        let client = HorizonHttpClient::new_from_str(&url).unwrap();
+        let options = RequestOptions::new().with_timeout(Duration::from_millis(50));
+        let result = client
+            .request(api::ledgers::single(100.into()).with_options(options))
+            .await;
+        assert!(matches!(result, Err(Error::RequestTimeout)));
+    }
+
+    #[tokio::test]
+    async fn test_cancellation() {
+        let server = MockServer::start(|_| (200, ledger_json(100, 0))).await;
+        let client = HorizonHttpClient::new_from_str(server.url().as_str()).unwrap();
+        let token = CancellationToken::new();
+        token.cancel();
+        let options = RequestOptions::new().with_cancellation(token);
+        let result = client
+            .request(api::ledgers::single(100.into()).with_options(options))
+            .await;
+        assert!(matches!(result, Err(Error::RequestCancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_stream_ends_when_cancelled() {
+        let server = MockServer::start(|_| (200, json!({}))).await;
+        let client = HorizonHttpClient::new_from_str(server.url().as_str()).unwrap();
+        let token = CancellationToken::new();
+        token.cancel();
+        let options = RequestOptions::new().with_cancellation(token);
+        let mut stream = client
+            .stream(api::ledgers::all().with_options(options))
+            .unwrap();
+        assert!(stream.next().await.is_none());
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retries_override_client() {
+        // The first three attempts fail.
+        let attempts = AtomicU32::new(0);
+        let server = MockServer::start(move |_| match attempts.fetch_add(1, Ordering::SeqCst) {
+            0..=2 => (500, json!({})),
+            _ => (200, ledger_json(100, 0)),
+        })
+        .await;
+        let client = HorizonHttpClient::builder(server.url().as_str())
+            .with_max_retries(1)
+            .build()
+            .unwrap();
+        let result = client.request(api::ledgers::single(100.into())).await;
+        assert!(matches!(result, Err(Error::HorizonServerError)));
+        let options = RequestOptions::new().with_retries(2);
+        let (_, ledger) = client
+            .request(api::ledgers::single(100.into()).with_options(options))
+            .await
+            .unwrap();
+        assert_eq!(100, ledger.sequence.value());
+        assert_eq!(4, server.requests().len());
+    }
+}
@@ -0,0 +1,149 @@
+//! Automatic pagination.
+//!
+//! Horizon returns list endpoints one page at a time. The functions
+//! in this module follow the pages cursor to return all records as a
+//! single `Stream`.
+//!
+//! ```rust
+//! use stellar_horizon::api;
+//! use stellar_horizon::client::HorizonHttpClient;
+//! use stellar_horizon::paging;
+//! use stellar_horizon::request::{Order, PageRequest};
+//! use futures::stream::TryStreamExt;
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let request = api::ledgers::all()
+//!     .with_order(&Order::Ascending)
+//!     .with_limit(200);
+//! let mut ledgers = paging::records(&client, request);
+//! while let Some(ledger) = ledgers.try_next().await? {
+//!     println!("Ledger = {}", ledger.sequence);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::client::HorizonClient;
+use crate::error::Result;
+use crate::page::Page;
+use crate::request::PageRequest;
+use crate::resources::{Record, TimestampedRecord};
+use chrono::{DateTime, Utc};
+use futures::future;
+use futures::stream::{self, BoxStream, Stream, StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Returns a stream over the pages of `request`.
+///
+/// The stream starts from the request cursor and ends with the
+/// first empty page.
+pub fn pages<'a, C, R, T>(client: &'a C, request: R) -> BoxStream<'a, Result<Page<T>>>
+where
+    C: HorizonClient + Sync,
+    R: PageRequest<Response = Page<T>> + Clone + 'a,
+    T: Record + DeserializeOwned + Serialize + Clone + Send + 'a,
+{
+    stream::try_unfold(Some(request), move |request| async move {
+        let request = match request {
+            None => return Ok(None),
+            Some(request) => request,
+        };
+        let (_, page) = client.request(request.clone()).await?;
+        let next = page
+            .records
+            .last()
+            .map(|record| request.with_cursor(record.paging_token().as_str()));
+        Ok(Some((page, next)))
+    })
+    .boxed()
+}
+
+/// Returns a stream over all the records of `request`.
+pub fn records<'a, C, R, T>(client: &'a C, request: R) -> BoxStream<'a, Result<T>>
+where
+    C: HorizonClient + Sync,
+    R: PageRequest<Response = Page<T>> + Clone + 'a,
+    T: Record + DeserializeOwned + Serialize + Clone + Send + 'a,
+{
+    pages(client, request)
+        .map_ok(|page| stream::iter(page.records.into_iter().map(Ok)))
+        .try_flatten()
+        .boxed()
+}
+
+/// Returns a stream over the records of `request` created before `end`.
+///
+/// The request should be in ascending order.
+pub fn records_until<'a, C, R, T>(
+    client: &'a C,
+    request: R,
+    end: DateTime<Utc>,
+) -> BoxStream<'a, Result<T>>
+where
+    C: HorizonClient + Sync,
+    R: PageRequest<Response = Page<T>> + Clone + 'a,
+    T: TimestampedRecord + DeserializeOwned + Serialize + Clone + Send + 'a,
+{
+    take_until(records(client, request), end).boxed()
+}
+
+/// Ends `stream` with the first record created at or after `end`.
+///
+/// Works with both paginated and streaming responses, as long as
+/// records are in ascending order. Errors are passed through.
+pub fn take_until<S, T>(stream: S, end: DateTime<Utc>) -> impl Stream<Item = Result<T>>
+where
+    S: Stream<Item = Result<T>>,
+    T: TimestampedRecord,
+{
+    stream.take_while(move |item| {
+        let keep = match item {
+            Ok(record) => record.created_at() < end,
+            Err(_) => true,
+        };
+        future::ready(keep)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api;
+    use crate::mock::{ledger_json, page_json, MockClient};
+    use crate::request::Order;
+    use chrono::TimeZone;
+    use futures::executor::block_on;
+
+    fn client() -> MockClient {
+        MockClient::new(|uri| {
+            let query: std::collections::HashMap<_, _> = uri.query_pairs().into_owned().collect();
+            let cursor = query.get("cursor").map(|c| c.parse::<i64>().unwrap() >> 32);
+            let records = match cursor {
+                None => vec![ledger_json(1, 0), ledger_json(2, 5)],
+                Some(2) => vec![ledger_json(3, 10)],
+                Some(_) => vec![],
+            };
+            Some(page_json(records))
+        })
+    }
+
+    #[test]
+    fn test_records_follow_cursor() {
+        let client = client();
+        let req = api::ledgers::all().with_order(&Order::Ascending);
+        let ledgers: Vec<_> = block_on(records(&client, req).try_collect()).unwrap();
+        let sequences: Vec<_> = ledgers.iter().map(|l| l.sequence.value()).collect();
+        assert_eq!(vec![1, 2, 3], sequences);
+        assert_eq!(3, client.requests().len());
+    }
+
+    #[test]
+    fn test_records_until() {
+        let client = client();
+        let req = api::ledgers::all().with_order(&Order::Ascending);
+        let end = Utc.timestamp_opt(5, 0).unwrap();
+        let ledgers: Vec<_> = block_on(records_until(&client, req, end).try_collect()).unwrap();
+        assert_eq!(1, ledgers.len());
+    }
+}
@@ -1,7 +1,9 @@
 //! Request traits.
 use crate::api::Join;
 use crate::error::Result;
+use crate::options::{RequestOptions, WithOptions};
 use serde::de::DeserializeOwned;
+use std::sync::Arc;
 use stellar_base::asset::{Asset, CreditAssetType};
 use url::Url;
 
@@ -25,6 +27,45 @@ pub trait Request: Send + Sync {
 
     /// Returns the request uri.
     fn uri(&self, host: &Url) -> Result<Url>;
+
+    /// Returns the options of this request, if any.
+    fn options(&self) -> Option<&RequestOptions> {
+        None
+    }
+
+    /// Returns true if horizon answers this request with a response
+    /// body even when it fails with `status`.
+    fn accepts_status(&self, _status: u16) -> bool {
+        false
+    }
+
+    /// Sends this request with `options`.
+    fn with_options(self, options: RequestOptions) -> WithOptions<Self>
+    where
+        Self: Sized,
+    {
+        WithOptions::new(self, options)
+    }
+}
+
+impl<R: Request> Request for &R {
+    type Response = R::Response;
+
+    fn post_body(&self) -> Result<Option<String>> {
+        (**self).post_body()
+    }
+
+    fn uri(&self, host: &Url) -> Result<Url> {
+        (**self).uri(host)
+    }
+
+    fn options(&self) -> Option<&RequestOptions> {
+        (**self).options()
+    }
+
+    fn accepts_status(&self, status: u16) -> bool {
+        (**self).accepts_status(status)
+    }
 }
 
 /// Horizon page request trait.
@@ -54,6 +95,85 @@ pub trait StreamRequest: Request + Unpin {
     type Resource: DeserializeOwned + Send + Sync;
 }
 
+/// Returns a key identifying `request` regardless of the host it is
+/// sent to.
+///
+/// Used by the clients that wrap a single client to find requests
+/// they have already seen.
+pub(crate) fn request_key<R: Request>(request: &R) -> Result<String> {
+    let host: Url = "http://request.invalid/".parse()?;
+    Ok(request.uri(&host)?.to_string())
+}
+
+/// Request the json value of the response of another request.
+///
+/// Used to keep responses without knowing their type.
+pub(crate) struct ValueRequest<'r, R>(pub &'r R);
+
+impl<R: Request> Request for ValueRequest<'_, R> {
+    type Response = serde_json::Value;
+
+    fn post_body(&self) -> Result<Option<String>> {
+        self.0.post_body()
+    }
+
+    fn uri(&self, host: &Url) -> Result<Url> {
+        self.0.uri(host)
+    }
+
+    fn options(&self) -> Option<&RequestOptions> {
+        self.0.options()
+    }
+
+    fn accepts_status(&self, status: u16) -> bool {
+        self.0.accepts_status(status)
+    }
+}
+
+/// A stream request resumed after the event with id `cursor`.
+///
+/// Used to reconnect streams that are not a `PageRequest`.
+pub(crate) struct ResumedRequest<R> {
+    pub request: Arc<R>,
+    pub cursor: Option<String>,
+}
+
+impl<R: Request> Request for ResumedRequest<R> {
+    type Response = R::Response;
+
+    fn post_body(&self) -> Result<Option<String>> {
+        self.request.post_body()
+    }
+
+    fn uri(&self, host: &Url) -> Result<Url> {
+        let mut uri = self.request.uri(host)?;
+        if let Some(cursor) = &self.cursor {
+            let pairs: Vec<(String, String)> = uri
+                .query_pairs()
+                .filter(|(key, _)| key != "cursor")
+                .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                .collect();
+            uri.query_pairs_mut()
+                .clear()
+                .extend_pairs(pairs)
+                .append_pair("cursor", cursor);
+        }
+        Ok(uri)
+    }
+
+    fn options(&self) -> Option<&RequestOptions> {
+        self.request.options()
+    }
+
+    fn accepts_status(&self, status: u16) -> bool {
+        self.request.accepts_status(status)
+    }
+}
+
+impl<R: StreamRequest> StreamRequest for ResumedRequest<R> {
+    type Resource = R::Resource;
+}
+
 pub(crate) trait UrlPageRequestExt: Sized {
     fn append_pagination_params<R: PageRequest>(self, req: &R) -> Self;
     fn append_asset_params(self, asset: &Asset, prefix: Option<&str>) -> Self;
@@ -1,5 +1,5 @@
 use crate::link::Link;
-use crate::resources::Asset;
+use crate::resources::{Asset, LedgerSequence, LiquidityPoolId, PagingToken};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap as Map;
@@ -23,7 +23,7 @@ pub struct Account {
     /// The domain that host this account's `stellar.toml` file.
     pub home_domain: Option<String>,
     /// The id of the last ledger that included changes to this account.
-    pub last_modified_ledger: u32,
+    pub last_modified_ledger: LedgerSequence,
     /// The time when this account was last modified.
     pub last_modified_time: Option<DateTime<Utc>>,
     /// Thresholds for different access levels.
@@ -45,7 +45,7 @@ pub struct Account {
     /// The account sponsoring this account base reserve.
     pub sponsor: Option<String>,
     /// Paging token for this account.
-    pub paging_token: String,
+    pub paging_token: PagingToken,
 }
 
 /// Links for an Account.
@@ -107,7 +107,7 @@ pub struct AccountFlags {
 pub struct Balance {
     /// The number of units the account holds.
     pub balance: String,
-    pub liquidity_pool_id: Option<String>,
+    pub liquidity_pool_id: Option<LiquidityPoolId>,
     /// The maximum amount of the asset the account is willing to accept.
     pub limit: Option<String>,
     /// The sum of all buy offers owned by this account for this asset.
@@ -117,7 +117,7 @@ pub struct Balance {
     /// The account sponsoring this trustline.
     pub sponsor: Option<String>,
     /// Ledger when the balance was last changed.
-    pub last_modified_ledger: Option<u32>,
+    pub last_modified_ledger: Option<LedgerSequence>,
     /// Flag to indicate if the account is authorized to hold asset.
     pub is_authorized: Option<bool>,
     /// Flag to indicate if the account is authorized to maintain liabilities.
@@ -139,7 +139,7 @@ pub struct AccountSigner {
     /// Signer account id.
     pub account_id: String,
     /// Signer paging token.
-    pub paging_token: String,
+    pub paging_token: PagingToken,
     /// The signer.
     pub signer: Signer,
 }
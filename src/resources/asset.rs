@@ -1,6 +1,6 @@
 use crate::link::Link;
 use crate::resources::account::AccountFlags;
-use crate::resources::Asset;
+use crate::resources::{Asset, PagingToken};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -9,7 +9,7 @@ pub struct AssetStat {
     pub links: AssetStatLinks,
     #[serde(flatten)]
     pub asset: Asset,
-    pub paging_token: String,
+    pub paging_token: PagingToken,
     pub num_accounts: i32,
     pub num_claimable_balances: i32,
     pub num_liquidity_pools: i32,
@@ -1,5 +1,6 @@
 use crate::error::{Error, Result};
 use crate::link::Link;
+use crate::resources::{ClaimableBalanceId, LedgerSequence, PagingToken};
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Deserializer, Serialize};
 use std::str::FromStr;
@@ -9,15 +10,15 @@ use stellar_base::claim::ClaimPredicate;
 pub struct ClaimableBalance {
     #[serde(rename = "_links")]
     pub links: ClaimableBalanceLinks,
-    pub id: String,
+    pub id: ClaimableBalanceId,
     pub asset: String,
     pub amount: String,
     pub sponsor: Option<String>,
-    pub last_modified_ledger: i64,
+    pub last_modified_ledger: LedgerSequence,
     pub last_modified_time: DateTime<Utc>,
     pub claimants: Vec<Claimant>,
     pub flags: ClaimableBalanceFlags,
-    pub paging_token: String,
+    pub paging_token: PagingToken,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
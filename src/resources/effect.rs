@@ -1,6 +1,8 @@
 use crate::link::Link;
 use crate::resources::trade::{BoughtAsset, SoldAsset};
-use crate::resources::{Asset, AssetAmount};
+use crate::resources::{
+    Asset, AssetAmount, ClaimableBalanceId, LiquidityPoolId, OfferId, PagingToken,
+};
 use crate::resources::{LiquidityPoolOrAsset, Predicate};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -75,7 +77,7 @@ pub struct EffectBase {
     #[serde(rename = "_links")]
     pub links: EffectLinks,
     pub id: String,
-    pub paging_token: String,
+    pub paging_token: PagingToken,
     pub account: String,
     pub account_muxed: Option<String>,
     pub account_muxed_id: Option<String>,
@@ -296,7 +298,7 @@ pub struct TradeEffect {
     pub seller: String,
     pub seller_muxed: Option<String>,
     pub seller_muxed_id: Option<String>,
-    pub offer_id: String,
+    pub offer_id: OfferId,
     pub sold_amount: String,
     #[serde(flatten, with = "SoldAsset")]
     pub sold_asset: Asset,
@@ -310,7 +312,7 @@ pub struct ClaimableBalanceCreatedEffect {
     #[serde(flatten)]
     pub base: EffectBase,
     pub asset: String,
-    pub balance_id: String,
+    pub balance_id: ClaimableBalanceId,
     pub amount: String,
 }
 
@@ -319,7 +321,7 @@ pub struct ClaimableBalanceClaimedEffect {
     #[serde(flatten)]
     pub base: EffectBase,
     pub asset: String,
-    pub balance_id: String,
+    pub balance_id: ClaimableBalanceId,
     pub amount: String,
 }
 
@@ -328,7 +330,7 @@ pub struct ClaimableBalanceClaimantCreatedEffect {
     #[serde(flatten)]
     pub base: EffectBase,
     pub asset: String,
-    pub balance_id: String,
+    pub balance_id: ClaimableBalanceId,
     pub amount: String,
     pub predicate: Predicate,
 }
@@ -361,7 +363,7 @@ pub struct TrustLineSponsorshipCreatedEffect {
     pub base: EffectBase,
     pub asset_type: String,
     pub asset: Option<String>,
-    pub liquidity_pool_id: Option<String>,
+    pub liquidity_pool_id: Option<LiquidityPoolId>,
     pub sponsor: String,
 }
 
@@ -371,7 +373,7 @@ pub struct TrustLineSponsorshipUpdatedEffect {
     pub base: EffectBase,
     pub asset_type: String,
     pub asset: Option<String>,
-    pub liquidity_pool_id: Option<String>,
+    pub liquidity_pool_id: Option<LiquidityPoolId>,
     pub new_sponsor: String,
     pub former_sponsor: String,
 }
@@ -382,7 +384,7 @@ pub struct TrustLineSponsorshipRemovedEffect {
     pub base: EffectBase,
     pub asset_type: String,
     pub asset: Option<String>,
-    pub liquidity_pool_id: Option<String>,
+    pub liquidity_pool_id: Option<LiquidityPoolId>,
     pub former_sponsor: String,
 }
 
@@ -415,7 +417,7 @@ pub struct DataSponsorshipRemovedEffect {
 pub struct ClaimableBalanceSponsorshipCreatedEffect {
     #[serde(flatten)]
     pub base: EffectBase,
-    pub balance_id: String,
+    pub balance_id: ClaimableBalanceId,
     pub sponsor: String,
 }
 
@@ -423,7 +425,7 @@ pub struct ClaimableBalanceSponsorshipCreatedEffect {
 pub struct ClaimableBalanceSponsorshipUpdatedEffect {
     #[serde(flatten)]
     pub base: EffectBase,
-    pub balance_id: String,
+    pub balance_id: ClaimableBalanceId,
     pub former_sponsor: String,
     pub new_sponsor: String,
 }
@@ -432,7 +434,7 @@ pub struct ClaimableBalanceSponsorshipUpdatedEffect {
 pub struct ClaimableBalanceSponsorshipRemovedEffect {
     #[serde(flatten)]
     pub base: EffectBase,
-    pub balance_id: String,
+    pub balance_id: ClaimableBalanceId,
     pub former_sponsor: String,
 }
 
@@ -464,13 +466,13 @@ pub struct SignerSponsorshipRemovedEffect {
 pub struct ClaimableBalanceClawedBackEffect {
     #[serde(flatten)]
     pub base: EffectBase,
-    pub balance_id: String,
+    pub balance_id: ClaimableBalanceId,
 }
 
 #[serde_as]
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct LiquidityPoolEffect {
-    pub id: String,
+    pub id: LiquidityPoolId,
     pub fee_bp: u32,
     #[serde(rename = "type")]
     pub pool_type: String,
@@ -518,14 +520,14 @@ pub struct LiquidityPoolCreatedEffect {
 pub struct LiquidityPoolRemovedEffect {
     #[serde(flatten)]
     pub base: EffectBase,
-    pub liquidity_pool_id: String,
+    pub liquidity_pool_id: LiquidityPoolId,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct LiquidityPoolClaimableAssetAmount {
     pub asset: String,
     pub amount: String,
-    pub claimable_balance_id: String,
+    pub claimable_balance_id: ClaimableBalanceId,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
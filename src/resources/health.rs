@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Health {
+    pub database_connected: bool,
+    pub core_up: bool,
+    pub core_synced: bool,
+}
@@ -0,0 +1,327 @@
+//! Strongly typed identifiers.
+//!
+//! Horizon represents most identifiers as strings, which makes it
+//! easy to pass a transaction hash where an operation id is expected.
+//! The types in this module validate identifiers when they are
+//! created and (de)serialize to the same representation Horizon uses.
+use crate::error::{Error, Result};
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// A ledger sequence number.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct LedgerSequence(u32);
+
+/// A hex-encoded transaction hash.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TransactionHash(String);
+
+/// An operation id.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OperationId(i64);
+
+/// An offer id.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OfferId(i64);
+
+/// A hex-encoded liquidity pool id.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LiquidityPoolId(String);
+
+/// A hex-encoded claimable balance id, including its type prefix.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClaimableBalanceId(String);
+
+/// An opaque cursor value used for pagination.
+///
+/// Paging tokens have a different format depending on the resource
+/// they belong to, so they are not validated.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct PagingToken(String);
+
+impl LedgerSequence {
+    /// Creates a new ledger sequence.
+    pub const fn new(sequence: u32) -> LedgerSequence {
+        LedgerSequence(sequence)
+    }
+
+    /// Returns the ledger sequence as integer.
+    pub const fn value(&self) -> u32 {
+        self.0
+    }
+}
+
+impl From<u32> for LedgerSequence {
+    fn from(sequence: u32) -> LedgerSequence {
+        LedgerSequence(sequence)
+    }
+}
+
+impl From<LedgerSequence> for u32 {
+    fn from(sequence: LedgerSequence) -> u32 {
+        sequence.0
+    }
+}
+
+impl fmt::Display for LedgerSequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for LedgerSequence {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<LedgerSequence> {
+        let sequence = u32::from_str(s).map_err(|_| Error::InvalidLedgerSequence)?;
+        Ok(LedgerSequence(sequence))
+    }
+}
+
+impl PagingToken {
+    /// Creates a new paging token.
+    pub fn new<S: Into<String>>(token: S) -> PagingToken {
+        PagingToken(token.into())
+    }
+
+    /// Returns the paging token as string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns true if the paging token is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for PagingToken {
+    fn from(token: String) -> PagingToken {
+        PagingToken(token)
+    }
+}
+
+impl From<&str> for PagingToken {
+    fn from(token: &str) -> PagingToken {
+        PagingToken(token.to_string())
+    }
+}
+
+impl AsRef<str> for PagingToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for PagingToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl PartialEq<str> for PagingToken {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<String> for PagingToken {
+    fn eq(&self, other: &String) -> bool {
+        &self.0 == other
+    }
+}
+
+macro_rules! impl_hex_id {
+    ($name:ident, $len:expr, $error:expr) => {
+        impl $name {
+            /// Returns the hex-encoded id.
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = Error;
+
+            fn from_str(s: &str) -> Result<$name> {
+                if s.len() == $len && s.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    Ok($name(s.to_ascii_lowercase()))
+                } else {
+                    Err($error)
+                }
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serializer.serialize_str(&self.0)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> std::result::Result<$name, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                let s = String::deserialize(deserializer)?;
+                $name::from_str(&s).map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+impl_hex_id!(TransactionHash, 64, Error::InvalidTransactionHash);
+impl_hex_id!(LiquidityPoolId, 64, Error::InvalidLiquidityPoolId);
+impl_hex_id!(ClaimableBalanceId, 72, Error::InvalidClaimableBalanceId);
+
+// Horizon encodes 64 bit ids as strings, since they don't fit in
+// a javascript number. Accept both representations when
+// deserializing and always serialize them as strings.
+macro_rules! impl_int64_id {
+    ($name:ident, $error:expr) => {
+        impl $name {
+            /// Creates a new id, returns an error if the id is negative.
+            pub fn new(id: i64) -> Result<$name> {
+                if id < 0 {
+                    Err($error)
+                } else {
+                    Ok($name(id))
+                }
+            }
+
+            /// Returns the id as integer.
+            pub const fn value(&self) -> i64 {
+                self.0
+            }
+        }
+
+        impl From<$name> for i64 {
+            fn from(id: $name) -> i64 {
+                id.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = Error;
+
+            fn from_str(s: &str) -> Result<$name> {
+                let id = i64::from_str(s).map_err(|_| $error)?;
+                $name::new(id)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serializer.collect_str(&self.0)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> std::result::Result<$name, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                struct IdVisitor;
+
+                impl<'de> Visitor<'de> for IdVisitor {
+                    type Value = $name;
+
+                    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        write!(f, "a non negative 64 bit integer or string")
+                    }
+
+                    fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<$name, E> {
+                        $name::from_str(v).map_err(E::custom)
+                    }
+
+                    fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<$name, E> {
+                        $name::new(v).map_err(E::custom)
+                    }
+
+                    fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<$name, E> {
+                        let v = i64::try_from(v).map_err(|_| E::custom($error))?;
+                        $name::new(v).map_err(E::custom)
+                    }
+                }
+
+                deserializer.deserialize_any(IdVisitor)
+            }
+        }
+    };
+}
+
+impl_int64_id!(OperationId, Error::InvalidOperationId);
+impl_int64_id!(OfferId, Error::InvalidOfferId);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transaction_hash_from_str() {
+        let hash = "23BF920C4A000B78268589DF224C1BA4C883A905687F5A5B3BDBA721EE1F481E"
+            .parse::<TransactionHash>()
+            .unwrap();
+        assert_eq!(
+            "23bf920c4a000b78268589df224c1ba4c883a905687f5a5b3bdba721ee1f481e",
+            hash.as_str()
+        );
+        assert!("23bf920c".parse::<TransactionHash>().is_err());
+        assert!("12345".parse::<TransactionHash>().is_err());
+    }
+
+    #[test]
+    fn test_claimable_balance_id_from_str() {
+        let id = "00000000178826fbfe339e1f5c53417c6fedfe2c05e8bec14303143ec46b38981b09c3f9";
+        assert!(id.parse::<ClaimableBalanceId>().is_ok());
+        assert!(id.parse::<LiquidityPoolId>().is_err());
+        assert!(id[8..].parse::<LiquidityPoolId>().is_ok());
+    }
+
+    #[test]
+    fn test_int64_id_serde() {
+        let id: OperationId = serde_json::from_str(r#""12884905985""#).unwrap();
+        assert_eq!(12884905985, id.value());
+        assert_eq!(r#""12884905985""#, serde_json::to_string(&id).unwrap());
+        let id: OfferId = serde_json::from_str("123").unwrap();
+        assert_eq!(123, id.value());
+        assert!(serde_json::from_str::<OfferId>(r#""-1""#).is_err());
+        assert!(serde_json::from_str::<OperationId>(r#""abc""#).is_err());
+    }
+
+    #[test]
+    fn test_ledger_sequence_serde() {
+        let sequence: LedgerSequence = serde_json::from_str("888").unwrap();
+        assert_eq!(LedgerSequence::new(888), sequence);
+        assert_eq!("888", serde_json::to_string(&sequence).unwrap());
+        assert_eq!("888", sequence.to_string());
+    }
+}
@@ -1,5 +1,5 @@
 use crate::link::Link;
-use crate::resources::LedgerId;
+use crate::resources::{LedgerSequence, PagingToken};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
@@ -13,14 +13,14 @@ pub struct Ledger {
     /// An unique identifier.
     pub id: String,
     /// The cursor value.
-    pub paging_token: String,
+    pub paging_token: PagingToken,
     /// A hex-encoded SHA-256 hash of this ledger XDR encoded form.
     pub hash: String,
     /// The hash of the ledger preceding this one.
     #[serde(rename = "prev_hash", skip_serializing_if = "Option::is_none")]
     pub previous_hash: Option<String>,
     /// The ledger sequence number.
-    pub sequence: i32,
+    pub sequence: LedgerSequence,
     /// The number of successfull transactions in this ledger.
     pub successful_transaction_count: i32,
     /// The number of failed transactions in this ledger.
@@ -101,7 +101,7 @@ pub struct FeeDistribution {
 pub struct FeeStats {
     /// The last ledger sequence number.
     #[serde_as(as = "DisplayFromStr")]
-    pub last_ledger: LedgerId,
+    pub last_ledger: LedgerSequence,
     /// The last ledger base fee.
     #[serde_as(as = "DisplayFromStr")]
     pub last_ledger_base_fee: i64,
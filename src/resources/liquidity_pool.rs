@@ -1,4 +1,5 @@
 use crate::link::Link;
+use crate::resources::{LedgerSequence, LiquidityPoolId, PagingToken};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
@@ -11,9 +12,9 @@ pub struct LiquidityPool {
     #[serde(rename = "_links")]
     pub links: LiquidityPoolLinks,
     /// An unique identifier for this liquidity pool.
-    pub id: String,
+    pub id: LiquidityPoolId,
     /// The cursor value.
-    pub paging_token: String,
+    pub paging_token: PagingToken,
     /// The fee the liquidity pool charges per transaction in basis points.
     pub fee_bp: u32,
     /// The liqudity pool type.
@@ -27,7 +28,7 @@ pub struct LiquidityPool {
     /// The assets contained in the liquidity pool.
     pub reserves: Vec<LiquidityPoolReserve>,
     /// The id of the last ledger where this liquidity pool had activity.
-    pub last_modified_ledger: u32,
+    pub last_modified_ledger: LedgerSequence,
     /// The time when this liquidity pool was last modified.
     pub last_modified_time: Option<DateTime<Utc>>,
 }
@@ -10,10 +10,13 @@ pub mod asset;
 pub mod book;
 pub mod claimable_balance;
 pub mod effect;
+pub mod health;
+pub mod id;
 pub mod ledger;
 pub mod liquidity_pool;
 pub mod offer;
 pub mod operation;
+pub mod record;
 pub mod root;
 pub mod trade;
 pub mod transaction;
@@ -23,23 +26,17 @@ pub use asset::*;
 pub use book::*;
 pub use claimable_balance::*;
 pub use effect::*;
+pub use health::*;
+pub use id::*;
 pub use ledger::*;
 pub use liquidity_pool::*;
 pub use offer::*;
 pub use operation::*;
+pub use record::*;
 pub use root::*;
 pub use trade::*;
 pub use transaction::*;
 
-/// Alias type for offer's id.
-pub type OfferId = i64;
-
-/// Alias type for ledger's id.
-pub type LedgerId = i32;
-
-/// Alias type for claimable balance id.
-pub type ClaimableBalanceId = String;
-
 /// Price as rational number.
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Price {
@@ -64,7 +61,7 @@ pub struct Asset {
 pub struct LiquidityPoolOrAsset {
     #[serde(flatten)]
     pub asset: Asset,
-    pub liquidity_pool_id: Option<String>,
+    pub liquidity_pool_id: Option<LiquidityPoolId>,
 }
 
 /// An asset along with its amount
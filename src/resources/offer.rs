@@ -1,19 +1,16 @@
 use crate::link::Link;
-use crate::resources::{Asset, LedgerId, OfferId, Price};
+use crate::resources::{Asset, LedgerSequence, OfferId, PagingToken, Price};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use serde_with::{serde_as, DisplayFromStr};
 
-#[serde_as]
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Offer {
     #[serde(rename = "_links")]
     pub links: OfferLinks,
     /// The offer id.
-    #[serde_as(as = "DisplayFromStr")]
     pub id: OfferId,
     /// The paging token for this offer.
-    pub paging_token: String,
+    pub paging_token: PagingToken,
     /// The account on the sell side.
     pub seller: String,
     /// The asset being sold.
@@ -28,7 +25,7 @@ pub struct Offer {
     /// The price being offered, as string.
     pub price: String,
     /// The ledger when this offer was last modified.
-    pub last_modified_ledger: LedgerId,
+    pub last_modified_ledger: LedgerSequence,
     /// The time when this offer was last modified.
     pub last_modified_time: Option<DateTime<Utc>>,
     /// The account sponsoring this offer base reserve.
@@ -1,12 +1,13 @@
 use crate::link::Link;
 use crate::resources::{
-    Asset, AssetAmount, Claimant, LiquidityPoolOrAsset, Price, SourceAsset, Transaction,
+    Asset, AssetAmount, ClaimableBalanceId, Claimant, LiquidityPoolId, LiquidityPoolOrAsset,
+    OfferId, OperationId, PagingToken, Price, SourceAsset, Transaction, TransactionHash,
 };
 use chrono::{DateTime, Utc};
 use serde::de::{self, Deserializer};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use serde_with::{serde_as, DefaultOnNull, DisplayFromStr, NoneAsEmptyString};
+use serde_with::{serde_as, DefaultOnNull, NoneAsEmptyString};
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
 #[serde(tag = "type")]
@@ -58,15 +59,15 @@ pub enum Payment {
 pub struct OperationBase {
     #[serde(rename = "_links")]
     pub links: OperationLinks,
-    pub id: String,
-    pub paging_token: String,
+    pub id: OperationId,
+    pub paging_token: PagingToken,
     pub transaction_successful: bool,
     pub source_account: String,
     pub source_account_muxed: Option<String>,
     pub source_account_muxed_id: Option<String>,
     pub type_i: i32,
     pub created_at: DateTime<Utc>,
-    pub transaction_hash: String,
+    pub transaction_hash: TransactionHash,
     pub transaction: Option<Transaction>,
     pub sponsor: Option<String>,
 }
@@ -166,7 +167,6 @@ pub struct CreatePassiveSellOfferOperation {
     pub selling: Asset,
 }
 
-#[serde_as]
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct ManageSellOfferOperation {
     #[serde(flatten)]
@@ -179,11 +179,9 @@ pub struct ManageSellOfferOperation {
     pub buying: Asset,
     #[serde(flatten, with = "SellingAsset")]
     pub selling: Asset,
-    #[serde_as(as = "DisplayFromStr")]
-    pub offer_id: i64,
+    pub offer_id: OfferId,
 }
 
-#[serde_as]
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct ManageBuyOfferOperation {
     #[serde(flatten)]
@@ -196,8 +194,7 @@ pub struct ManageBuyOfferOperation {
     pub buying: Asset,
     #[serde(flatten, with = "SellingAsset")]
     pub selling: Asset,
-    #[serde_as(as = "DisplayFromStr")]
-    pub offer_id: i64,
+    pub offer_id: OfferId,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -282,7 +279,7 @@ pub struct CreateClaimableBalanceOperation {
 pub struct ClaimClaimableBalanceOperation {
     #[serde(flatten)]
     pub base: OperationBase,
-    pub balance_id: String,
+    pub balance_id: ClaimableBalanceId,
     pub claimant: String,
     pub claimant_muxed: Option<String>,
     pub claimant_muxed_id: Option<String>,
@@ -309,12 +306,12 @@ pub struct RevokeSponsorshipOperation {
     #[serde(flatten)]
     pub base: OperationBase,
     pub account_id: Option<String>,
-    pub claimable_balance_id: Option<String>,
+    pub claimable_balance_id: Option<ClaimableBalanceId>,
     pub data_account_id: Option<String>,
     pub data_name: Option<String>,
-    pub offer_id: Option<String>,
+    pub offer_id: Option<OfferId>,
     pub trustline_account_id: Option<String>,
-    pub trustline_liquidity_pool_id: Option<String>,
+    pub trustline_liquidity_pool_id: Option<LiquidityPoolId>,
     pub trustline_asset: Option<String>,
     pub signer_account_id: Option<String>,
     pub signer_key: Option<String>,
@@ -336,7 +333,7 @@ pub struct ClawbackOperation {
 pub struct ClawbackClaimableBalanceOperation {
     #[serde(flatten)]
     pub base: OperationBase,
-    pub balance_id: String,
+    pub balance_id: ClaimableBalanceId,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -360,7 +357,7 @@ pub struct SetTrustLineFlagsOperation {
 pub struct LiquidityPoolDepositOperation {
     #[serde(flatten)]
     pub base: OperationBase,
-    pub liquidity_pool_id: String,
+    pub liquidity_pool_id: LiquidityPoolId,
     pub reserves_max: Vec<AssetAmount>,
     pub min_price: String,
     pub min_price_r: Price,
@@ -374,7 +371,7 @@ pub struct LiquidityPoolDepositOperation {
 pub struct LiquidityPoolWithdrawOperation {
     #[serde(flatten)]
     pub base: OperationBase,
-    pub liquidity_pool_id: String,
+    pub liquidity_pool_id: LiquidityPoolId,
     pub reserves_min: Vec<AssetAmount>,
     pub shares: String,
     pub reserves_received: Vec<AssetAmount>,
@@ -553,6 +550,28 @@ impl Payment {
             Payment::InvokeHostFunction(op) => &op.base,
         }
     }
+
+    /// Returns the accounts that send or receive funds in the payment,
+    /// including the operation source account.
+    pub fn participants(&self) -> Vec<&str> {
+        let mut accounts = vec![self.base().source_account.as_str()];
+        match self {
+            Payment::CreateAccount(op) => accounts.extend([op.funder.as_str(), &op.account]),
+            Payment::Payment(op) => accounts.extend([op.from.as_str(), &op.to]),
+            Payment::PathPaymentStrictReceive(op) => accounts.extend([op.from.as_str(), &op.to]),
+            Payment::PathPaymentStrictSend(op) => accounts.extend([op.from.as_str(), &op.to]),
+            Payment::AccountMerge(op) => accounts.extend([op.account.as_str(), &op.into]),
+            Payment::InvokeHostFunction(op) => {
+                for change in &op.asset_balance_changes {
+                    accounts.extend(change.from.as_deref());
+                    accounts.extend(change.to.as_deref());
+                }
+            }
+        }
+        accounts.sort_unstable();
+        accounts.dedup();
+        accounts
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
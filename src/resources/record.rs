@@ -0,0 +1,90 @@
+use crate::resources::{
+    Account, AssetStat, ClaimableBalance, Effect, Ledger, LiquidityPool, Offer, Operation,
+    PagingToken, Payment, Trade, Transaction,
+};
+use chrono::{DateTime, Utc};
+
+/// A resource returned as part of a `Page`.
+pub trait Record {
+    /// Returns the record paging token.
+    fn paging_token(&self) -> &PagingToken;
+}
+
+/// A record that happened at a point in time.
+pub trait TimestampedRecord: Record {
+    /// Returns when the ledger containing this record was closed.
+    fn created_at(&self) -> DateTime<Utc>;
+}
+
+macro_rules! impl_record {
+    ($name:ty) => {
+        impl Record for $name {
+            fn paging_token(&self) -> &PagingToken {
+                &self.paging_token
+            }
+        }
+    };
+}
+
+impl_record!(Account);
+impl_record!(AssetStat);
+impl_record!(ClaimableBalance);
+impl_record!(Ledger);
+impl_record!(LiquidityPool);
+impl_record!(Offer);
+impl_record!(Trade);
+impl_record!(Transaction);
+
+impl Record for Operation {
+    fn paging_token(&self) -> &PagingToken {
+        &self.base().paging_token
+    }
+}
+
+impl Record for Payment {
+    fn paging_token(&self) -> &PagingToken {
+        &self.base().paging_token
+    }
+}
+
+impl Record for Effect {
+    fn paging_token(&self) -> &PagingToken {
+        &self.base().paging_token
+    }
+}
+
+impl TimestampedRecord for Ledger {
+    fn created_at(&self) -> DateTime<Utc> {
+        self.closed_at
+    }
+}
+
+impl TimestampedRecord for Trade {
+    fn created_at(&self) -> DateTime<Utc> {
+        self.ledger_close_time
+    }
+}
+
+impl TimestampedRecord for Transaction {
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+}
+
+impl TimestampedRecord for Operation {
+    fn created_at(&self) -> DateTime<Utc> {
+        self.base().created_at
+    }
+}
+
+impl TimestampedRecord for Payment {
+    fn created_at(&self) -> DateTime<Utc> {
+        self.base().created_at
+    }
+}
+
+impl TimestampedRecord for Effect {
+    fn created_at(&self) -> DateTime<Utc> {
+        self.base().created_at
+    }
+}
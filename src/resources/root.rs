@@ -1,4 +1,5 @@
 use crate::link::Link;
+use crate::resources::LedgerSequence;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -8,14 +9,16 @@ pub struct Root {
     pub links: RootLinks,
     pub horizon_version: String,
     pub core_version: String,
-    pub ingest_latest_ledger: u32,
-    pub history_latest_ledger: i32,
+    pub ingest_latest_ledger: LedgerSequence,
+    pub history_latest_ledger: LedgerSequence,
     pub history_latest_ledger_closed_at: DateTime<Utc>,
-    pub history_elder_ledger: i32,
-    pub core_latest_ledger: i32,
+    pub history_elder_ledger: LedgerSequence,
+    pub core_latest_ledger: LedgerSequence,
     pub network_passphrase: String,
     pub current_protocol_version: i32,
     pub core_supported_protocol_version: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supported_protocol_version: Option<i32>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -1,5 +1,5 @@
 use crate::link::Link;
-use crate::resources::Asset;
+use crate::resources::{Asset, LiquidityPoolId, OfferId, PagingToken};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
@@ -13,19 +13,19 @@ pub struct Trade {
     /// A unique identifier for this trade.
     pub id: String,
     /// A cursor value for use in pagination.
-    pub paging_token: String,
+    pub paging_token: PagingToken,
     /// When the ledger with this trade was closed.
     pub ledger_close_time: DateTime<Utc>,
     /// The sell offer ID.
-    pub offer_id: Option<String>,
+    pub offer_id: Option<OfferId>,
     /// The type of trade that was executed.
     pub trade_type: String,
     /// The fee that was charged by the liquidity pool in basis points.
     pub liquidity_pool_fee_bp: Option<u32>,
     /// The ID for the liquidity pool that acted as the base party.
-    pub base_liquidity_pool_id: Option<String>,
+    pub base_liquidity_pool_id: Option<LiquidityPoolId>,
     /// The base offer ID. If this offer was immediately and fully consumed, this will be a synthetic ID.
-    pub base_offer_id: Option<String>,
+    pub base_offer_id: Option<OfferId>,
     /// The account ID of the base party for this trade.
     pub base_account: Option<String>,
     /// The amount of the `base_asset` that was moved from `base_account` to `counter_account`.
@@ -34,9 +34,9 @@ pub struct Trade {
     #[serde(flatten, with = "BaseAsset")]
     pub base_asset: Asset,
     /// The ID for the liquidity pool that acted as the counter party.
-    pub counter_liquidity_pool_id: Option<String>,
+    pub counter_liquidity_pool_id: Option<LiquidityPoolId>,
     /// The counter offer ID. If this offer was immediately and fully consumed, this will be a synthetic ID.
-    pub counter_offer_id: Option<String>,
+    pub counter_offer_id: Option<OfferId>,
     /// The account ID of the counter party for this trade.
     pub counter_account: Option<String>,
     /// The amount of the `counter_asset` that was moved from `counter_account` to `base_account`.
@@ -1,4 +1,5 @@
 use crate::link::Link;
+use crate::resources::{LedgerSequence, PagingToken, TransactionHash};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
@@ -11,15 +12,15 @@ pub struct Transaction {
     #[serde(rename = "_links")]
     pub links: TransactionLinks,
     /// An unique identifier for this transaction.
-    pub id: String,
+    pub id: TransactionHash,
     /// A cursor value for use in pagination.
-    pub paging_token: String,
+    pub paging_token: PagingToken,
     /// Indicates if this transaction was successful or not.
     pub successful: bool,
     /// A hex-encoded SHA-256 hash of this transaction’s XDR-encoded form.
-    pub hash: String,
+    pub hash: TransactionHash,
     /// The sequence number of the ledger that this transaction was included in.
-    pub ledger: i32,
+    pub ledger: LedgerSequence,
     /// The date this transaction was created.
     pub created_at: DateTime<Utc>,
     /// The account that originates the transaction.
@@ -74,7 +75,7 @@ pub struct Transaction {
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct FeeBumpTransaction {
     /// The transaction hash.
-    pub hash: String,
+    pub hash: TransactionHash,
     /// An array of signatures used to sign this transaction.
     pub signatures: Vec<String>,
 }
@@ -84,7 +85,7 @@ pub struct FeeBumpTransaction {
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct InnerTransaction {
     /// The transaction hash.
-    pub hash: String,
+    pub hash: TransactionHash,
     /// An array of signatures used to sign this transaction.
     pub signatures: Vec<String>,
     /// The transaction max fee.
@@ -0,0 +1,107 @@
+//! Stream events.
+//!
+//! Horizon sends each streamed resource as a server sent event. The
+//! event id is the cursor of the resource: starting a new stream with
+//! it as cursor resumes right after the resource.
+//!
+//! ```rust
+//! use stellar_horizon::api;
+//! use stellar_horizon::client::{HorizonClient, HorizonHttpClient};
+//! use stellar_horizon::request::PageRequest;
+//! use futures::stream::TryStreamExt;
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let request = api::payments::all().with_cursor("now");
+//! let mut events = client.stream_events(request)?;
+//! while let Some(event) = events.try_next().await? {
+//!     println!("Payment = {:?}", event.resource);
+//!     // Save the cursor to resume from here after a restart.
+//!     if let Some(cursor) = event.cursor() {
+//!         println!("Cursor = {}", cursor);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use futures::Stream;
+
+/// A boxed stream of events, as returned by `HorizonClient::stream_events`.
+pub type EventStream<T> = Box<dyn Stream<Item = Result<StreamEvent<T>>> + 'static + Send + Unpin>;
+
+/// A streamed resource, together with its event metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamEvent<T> {
+    /// The server sent event id, if any.
+    pub id: Option<String>,
+    /// When the event was received.
+    pub received_at: DateTime<Utc>,
+    /// The streamed resource.
+    pub resource: T,
+}
+
+impl<T> StreamEvent<T> {
+    /// Creates a new event received now.
+    pub fn new(id: Option<String>, resource: T) -> StreamEvent<T> {
+        StreamEvent {
+            id,
+            received_at: Utc::now(),
+            resource,
+        }
+    }
+
+    /// Returns the cursor to resume the stream after this event.
+    pub fn cursor(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Returns the streamed resource, dropping the event metadata.
+    pub fn into_resource(self) -> T {
+        self.resource
+    }
+
+    /// Maps the resource of this event, keeping its metadata.
+    pub fn map<U, F>(self, f: F) -> StreamEvent<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        StreamEvent {
+            id: self.id,
+            received_at: self.received_at,
+            resource: f(self.resource),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api;
+    use crate::client::HorizonClient;
+    use crate::mock::{ledger_json, MockClient};
+    use futures::executor::block_on;
+    use futures::stream::TryStreamExt;
+    use serde_json::json;
+
+    #[test]
+    fn test_stream_events_have_cursor() {
+        let client = MockClient::new(|_| Some(json!([ledger_json(1, 0), ledger_json(2, 5)])));
+        let events = client.stream_events(api::ledgers::all()).unwrap();
+        let events: Vec<_> = block_on(events.try_collect()).unwrap();
+        let cursors: Vec<_> = events.iter().map(|e| e.cursor().unwrap()).collect();
+        assert_eq!(vec!["4294967296", "8589934592"], cursors);
+        assert_eq!(2, events[1].resource.sequence.value());
+    }
+
+    #[test]
+    fn test_stream_event_map() {
+        let event = StreamEvent::new(Some("123".to_string()), 1);
+        let received_at = event.received_at;
+        let event = event.map(|n| n + 1);
+        assert_eq!(Some("123"), event.cursor());
+        assert_eq!(received_at, event.received_at);
+        assert_eq!(2, event.into_resource());
+    }
+}
@@ -0,0 +1,233 @@
+//! Total order ids.
+//!
+//! Horizon uses total order ids (TOIDs) as paging tokens for
+//! ledgers, transactions, operations and effects. A TOID is a 64 bit
+//! integer that encodes a ledger sequence, the order of a transaction
+//! in the ledger and the index of an operation in the transaction.
+//!
+//! ```rust
+//! use stellar_horizon::api;
+//! use stellar_horizon::request::PageRequest;
+//! use stellar_horizon::resources::LedgerSequence;
+//! use stellar_horizon::toid;
+//!
+//! # fn run() -> stellar_horizon::error::Result<()> {
+//! // Request all payments starting from ledger 1000.
+//! let cursor = toid::cursor_for_ledger(LedgerSequence::new(1000))?;
+//! let request = api::payments::all().with_cursor(&cursor);
+//! # Ok(())
+//! # }
+//! ```
+use crate::error::{Error, Result};
+use crate::resources::{LedgerSequence, OperationId, PagingToken};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+const LEDGER_SHIFT: u32 = 32;
+const TRANSACTION_SHIFT: u32 = 12;
+
+/// The maximum transaction order that fits in a TOID.
+pub const TRANSACTION_MASK: u32 = (1 << 20) - 1;
+
+/// The maximum operation index that fits in a TOID.
+pub const OPERATION_MASK: u32 = (1 << 12) - 1;
+
+/// A total order id.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Toid {
+    ledger_sequence: LedgerSequence,
+    transaction_order: u32,
+    operation_index: u32,
+}
+
+impl Toid {
+    /// Creates a new TOID.
+    ///
+    /// Returns an error if any of the components doesn't fit in its
+    /// section of the TOID.
+    pub fn new(
+        ledger_sequence: LedgerSequence,
+        transaction_order: u32,
+        operation_index: u32,
+    ) -> Result<Toid> {
+        if ledger_sequence.value() > i32::MAX as u32
+            || transaction_order > TRANSACTION_MASK
+            || operation_index > OPERATION_MASK
+        {
+            return Err(Error::InvalidToid);
+        }
+        Ok(Toid {
+            ledger_sequence,
+            transaction_order,
+            operation_index,
+        })
+    }
+
+    /// Returns the TOID of the ledger itself, which sorts before any
+    /// transaction or operation in the ledger.
+    pub fn ledger_start(ledger_sequence: LedgerSequence) -> Result<Toid> {
+        Toid::new(ledger_sequence, 0, 0)
+    }
+
+    /// Returns the TOID that sorts after any transaction or operation
+    /// in the ledger.
+    pub fn after_ledger(ledger_sequence: LedgerSequence) -> Result<Toid> {
+        Toid::new(ledger_sequence, TRANSACTION_MASK, OPERATION_MASK)
+    }
+
+    /// Creates a TOID from its paging token.
+    ///
+    /// Effects paging tokens have the effect index appended to the
+    /// operation TOID, the effect index is ignored.
+    pub fn from_paging_token(token: &PagingToken) -> Result<Toid> {
+        let toid = token.as_str().split('-').next().unwrap_or_default();
+        Toid::from_str(toid)
+    }
+
+    /// Returns the TOID ledger sequence.
+    pub fn ledger_sequence(&self) -> LedgerSequence {
+        self.ledger_sequence
+    }
+
+    /// Returns the TOID transaction order, starting at 1.
+    pub fn transaction_order(&self) -> u32 {
+        self.transaction_order
+    }
+
+    /// Returns the TOID operation index, starting at 1.
+    pub fn operation_index(&self) -> u32 {
+        self.operation_index
+    }
+
+    /// Returns the TOID as integer.
+    pub fn value(&self) -> i64 {
+        ((self.ledger_sequence.value() as i64) << LEDGER_SHIFT)
+            | ((self.transaction_order as i64) << TRANSACTION_SHIFT)
+            | (self.operation_index as i64)
+    }
+
+    /// Returns the TOID as paging token.
+    pub fn to_paging_token(&self) -> PagingToken {
+        PagingToken::new(self.to_string())
+    }
+}
+
+/// Returns the cursor to start paging records from the beginning of
+/// `ledger_sequence`, in ascending order.
+///
+/// The cursor works for ledgers, transactions, operations, payments
+/// and effects requests.
+///
+/// Returns an error if the ledger sequence doesn't fit in a TOID.
+pub fn cursor_for_ledger(ledger_sequence: LedgerSequence) -> Result<String> {
+    let start = Toid::ledger_start(ledger_sequence)?.value();
+    // Horizon returns records strictly after the cursor.
+    Ok((start - 1).max(0).to_string())
+}
+
+impl TryFrom<i64> for Toid {
+    type Error = Error;
+
+    fn try_from(value: i64) -> Result<Toid> {
+        if value < 0 {
+            return Err(Error::InvalidToid);
+        }
+        let ledger_sequence = (value >> LEDGER_SHIFT) as u32;
+        let transaction_order = ((value >> TRANSACTION_SHIFT) as u32) & TRANSACTION_MASK;
+        let operation_index = (value as u32) & OPERATION_MASK;
+        Toid::new(
+            LedgerSequence::new(ledger_sequence),
+            transaction_order,
+            operation_index,
+        )
+    }
+}
+
+impl From<Toid> for i64 {
+    fn from(toid: Toid) -> i64 {
+        toid.value()
+    }
+}
+
+impl From<OperationId> for Toid {
+    fn from(id: OperationId) -> Toid {
+        // Operation ids are non negative, so they are always valid TOIDs.
+        Toid::try_from(id.value()).expect("operation id is a valid toid")
+    }
+}
+
+impl TryFrom<&PagingToken> for Toid {
+    type Error = Error;
+
+    fn try_from(token: &PagingToken) -> Result<Toid> {
+        Toid::from_paging_token(token)
+    }
+}
+
+impl FromStr for Toid {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Toid> {
+        let value = i64::from_str(s).map_err(|_| Error::InvalidToid)?;
+        Toid::try_from(value)
+    }
+}
+
+impl fmt::Display for Toid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toid_encode_decode() {
+        let toid = Toid::new(LedgerSequence::new(3), 1, 1).unwrap();
+        assert_eq!(12884905985, toid.value());
+        assert_eq!(toid, Toid::try_from(12884905985).unwrap());
+        assert_eq!(toid, "12884905985".parse().unwrap());
+        assert_eq!(LedgerSequence::new(3), toid.ledger_sequence());
+        assert_eq!(1, toid.transaction_order());
+        assert_eq!(1, toid.operation_index());
+    }
+
+    #[test]
+    fn test_toid_invalid() {
+        assert!(Toid::new(LedgerSequence::new(3), TRANSACTION_MASK + 1, 0).is_err());
+        assert!(Toid::new(LedgerSequence::new(3), 0, OPERATION_MASK + 1).is_err());
+        assert!(Toid::new(LedgerSequence::new(u32::MAX), 0, 0).is_err());
+        assert!(Toid::try_from(-1).is_err());
+        assert!("abc".parse::<Toid>().is_err());
+    }
+
+    #[test]
+    fn test_toid_from_paging_token() {
+        let token = PagingToken::new("2347349951127555-4");
+        let toid = Toid::from_paging_token(&token).unwrap();
+        assert_eq!(2347349951127555, toid.value());
+        assert_eq!(LedgerSequence::new(546535), toid.ledger_sequence());
+
+        let token = PagingToken::new("12884905984");
+        let toid = Toid::from_paging_token(&token).unwrap();
+        assert_eq!(token, toid.to_paging_token());
+    }
+
+    #[test]
+    fn test_cursor_for_ledger() {
+        let cursor = cursor_for_ledger(LedgerSequence::new(3)).unwrap();
+        let ledger_start = Toid::ledger_start(LedgerSequence::new(3)).unwrap();
+        let previous_ledger_end = Toid::after_ledger(LedgerSequence::new(2)).unwrap();
+        assert_eq!(previous_ledger_end.to_string(), cursor);
+        assert_eq!(ledger_start.value() - 1, previous_ledger_end.value());
+        assert_eq!("0", cursor_for_ledger(LedgerSequence::new(0)).unwrap());
+        assert!(cursor_for_ledger(LedgerSequence::new(i32::MAX as u32)).is_ok());
+        assert!(matches!(
+            cursor_for_ledger(LedgerSequence::new(i32::MAX as u32 + 1)),
+            Err(Error::InvalidToid)
+        ));
+    }
+}
@@ -0,0 +1,270 @@
+//! Verify streams and paginated reads.
+//!
+//! Records can go missing, for example across stream reconnects. The
+//! functions in this module wrap a stream of records to check that
+//! paging tokens are in order and that ledgers form a chain, and to
+//! backfill the ledgers missing from a stream.
+//!
+//! ```rust
+//! use stellar_horizon::api;
+//! use stellar_horizon::client::{HorizonClient, HorizonHttpClient};
+//! use stellar_horizon::request::PageRequest;
+//! use stellar_horizon::verify;
+//! use futures::stream::TryStreamExt;
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let ledgers = client.stream(api::ledgers::all().with_cursor("now"))?;
+//! // Fetch ledgers missing from the stream, then check the chain.
+//! let ledgers = verify::fill_ledger_gaps(&client, ledgers);
+//! let mut ledgers = verify::check_ledgers(ledgers);
+//! while let Some(ledger) = ledgers.try_next().await? {
+//!     println!("Ledger = {}", ledger.sequence);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::api;
+use crate::client::HorizonClient;
+use crate::error::{Error, Result};
+use crate::paging;
+use crate::request::{Order, PageRequest};
+use crate::resources::{Ledger, LedgerSequence, PagingToken, Record};
+use crate::toid;
+use futures::future;
+use futures::stream::{self, BoxStream, Stream, StreamExt, TryStreamExt};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// Checks that the paging tokens of `stream` are strictly increasing,
+/// or strictly decreasing if `order` is descending.
+///
+/// Returns `Error::PagingTokenOutOfOrder` for records out of order.
+pub fn check_order<S, T>(stream: S, order: Order) -> impl Stream<Item = Result<T>>
+where
+    S: Stream<Item = Result<T>>,
+    T: Record,
+{
+    stream.scan(None, move |previous, item| {
+        future::ready(Some(check_record_order(previous, item, order)))
+    })
+}
+
+/// Checks that the ledgers of `stream` have contiguous sequence numbers
+/// and that each ledger previous hash is the hash of the ledger before it.
+///
+/// Returns `Error::LedgerGap` if a ledger is missing or out of order,
+/// and `Error::LedgerHashMismatch` if the hashes don't match.
+pub fn check_ledgers<S>(stream: S) -> impl Stream<Item = Result<Ledger>>
+where
+    S: Stream<Item = Result<Ledger>>,
+{
+    stream.scan(None, |previous, item| {
+        future::ready(Some(check_ledger_chain(previous, item)))
+    })
+}
+
+/// Fetches the ledgers missing from `stream`, returning them before
+/// the ledger that follows them.
+///
+/// Only forward gaps are filled, use `check_ledgers` to detect other
+/// inconsistencies.
+pub fn fill_ledger_gaps<'a, C, S>(client: &'a C, stream: S) -> BoxStream<'a, Result<Ledger>>
+where
+    C: HorizonClient + Sync,
+    S: Stream<Item = Result<Ledger>> + Send + 'a,
+{
+    let state = (Box::pin(stream), None::<LedgerSequence>, VecDeque::new());
+    stream::try_unfold(
+        state,
+        move |(mut stream, previous, mut pending)| async move {
+            let mut ledger = match pending.pop_front() {
+                Some(ledger) => ledger,
+                None => match stream.try_next().await? {
+                    None => return Ok(None),
+                    Some(ledger) => ledger,
+                },
+            };
+            if let Some(previous) = previous {
+                let expected = LedgerSequence::new(previous.value() + 1);
+                if ledger.sequence > expected {
+                    let missing = fetch_ledgers(client, expected..ledger.sequence).await?;
+                    pending.push_front(ledger);
+                    for missing in missing.into_iter().rev() {
+                        pending.push_front(missing);
+                    }
+                    ledger = pending.pop_front().expect("pending is not empty");
+                }
+            }
+            let sequence = ledger.sequence;
+            Ok(Some((ledger, (stream, Some(sequence), pending))))
+        },
+    )
+    .boxed()
+}
+
+fn check_record_order<T: Record>(
+    previous: &mut Option<PagingToken>,
+    item: Result<T>,
+    order: Order,
+) -> Result<T> {
+    let record = item?;
+    let current = record.paging_token();
+    if let Some(previous) = previous.as_ref() {
+        let expected = match order {
+            Order::Ascending => Ordering::Greater,
+            Order::Descending => Ordering::Less,
+        };
+        if compare_paging_tokens(current, previous) != expected {
+            return Err(Error::PagingTokenOutOfOrder {
+                previous: previous.clone(),
+                current: current.clone(),
+            });
+        }
+    }
+    *previous = Some(current.clone());
+    Ok(record)
+}
+
+fn check_ledger_chain(
+    previous: &mut Option<(LedgerSequence, String)>,
+    item: Result<Ledger>,
+) -> Result<Ledger> {
+    let ledger = item?;
+    if let Some((sequence, hash)) = previous.as_ref() {
+        let expected = LedgerSequence::new(sequence.value() + 1);
+        if ledger.sequence != expected {
+            return Err(Error::LedgerGap {
+                expected,
+                found: ledger.sequence,
+            });
+        }
+        if ledger.previous_hash.as_ref() != Some(hash) {
+            return Err(Error::LedgerHashMismatch {
+                sequence: ledger.sequence,
+            });
+        }
+    }
+    *previous = Some((ledger.sequence, ledger.hash.clone()));
+    Ok(ledger)
+}
+
+/// Compares paging tokens, numerically if they are TOIDs.
+///
+/// Effects paging tokens are a TOID followed by the effect index.
+pub(crate) fn compare_paging_tokens(a: &PagingToken, b: &PagingToken) -> Ordering {
+    match (numeric_paging_token(a), numeric_paging_token(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => a.as_str().cmp(b.as_str()),
+    }
+}
+
+fn numeric_paging_token(token: &PagingToken) -> Option<(i64, u64)> {
+    let mut parts = token.as_str().splitn(2, '-');
+    let toid = parts.next()?.parse().ok()?;
+    let index = match parts.next() {
+        None => 0,
+        Some(index) => index.parse().ok()?,
+    };
+    Some((toid, index))
+}
+
+async fn fetch_ledgers<C>(client: &C, ledgers: Range<LedgerSequence>) -> Result<Vec<Ledger>>
+where
+    C: HorizonClient + Sync,
+{
+    let request = api::ledgers::all()
+        .with_cursor(&toid::cursor_for_ledger(ledgers.start)?)
+        .with_order(&Order::Ascending);
+    paging::records(client, request)
+        .try_take_while(|ledger: &Ledger| future::ready(Ok(ledger.sequence < ledgers.end)))
+        .try_collect()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{ledger_json, page_json, MockClient};
+    use futures::executor::block_on;
+
+    fn ledger(sequence: u32) -> Ledger {
+        serde_json::from_value(ledger_json(sequence, sequence as i64 * 5)).unwrap()
+    }
+
+    fn ledgers(sequences: &[u32]) -> impl Stream<Item = Result<Ledger>> {
+        stream::iter(sequences.iter().map(|s| Ok(ledger(*s))).collect::<Vec<_>>())
+    }
+
+    fn sequences(ledgers: &[Ledger]) -> Vec<u32> {
+        ledgers.iter().map(|l| l.sequence.value()).collect()
+    }
+
+    #[test]
+    fn test_compare_paging_tokens() {
+        let cmp = |a: &str, b: &str| compare_paging_tokens(&a.into(), &b.into());
+        assert_eq!(Ordering::Less, cmp("9", "10"));
+        assert_eq!(Ordering::Less, cmp("10-2", "10-10"));
+        assert_eq!(Ordering::Greater, cmp("11-1", "10-10"));
+        assert_eq!(Ordering::Equal, cmp("10", "10"));
+    }
+
+    #[test]
+    fn test_check_order() {
+        let checked: Vec<_> =
+            block_on(check_order(ledgers(&[1, 2, 4]), Order::Ascending).collect());
+        assert!(checked.iter().all(|r| r.is_ok()));
+
+        let checked: Vec<_> =
+            block_on(check_order(ledgers(&[3, 2, 1]), Order::Descending).collect());
+        assert!(checked.iter().all(|r| r.is_ok()));
+
+        let checked: Vec<_> =
+            block_on(check_order(ledgers(&[1, 3, 2]), Order::Ascending).collect());
+        assert!(matches!(
+            checked[2],
+            Err(Error::PagingTokenOutOfOrder { .. })
+        ));
+
+        let checked: Vec<_> = block_on(check_order(ledgers(&[1, 1]), Order::Ascending).collect());
+        assert!(checked[1].is_err());
+    }
+
+    #[test]
+    fn test_check_ledgers() {
+        let checked: Vec<_> = block_on(check_ledgers(ledgers(&[1, 2, 3])).collect());
+        assert!(checked.iter().all(|r| r.is_ok()));
+
+        let checked: Vec<_> = block_on(check_ledgers(ledgers(&[1, 2, 4])).collect());
+        match &checked[2] {
+            Err(Error::LedgerGap { expected, found }) => {
+                assert_eq!(LedgerSequence::new(3), *expected);
+                assert_eq!(LedgerSequence::new(4), *found);
+            }
+            _ => panic!("expected ledger gap"),
+        }
+
+        let mut forked = ledger(2);
+        forked.previous_hash = Some("abcd".to_string());
+        let stream = stream::iter(vec![Ok(ledger(1)), Ok(forked)]);
+        let checked: Vec<_> = block_on(check_ledgers(stream).collect());
+        assert!(matches!(checked[1], Err(Error::LedgerHashMismatch { .. })));
+    }
+
+    #[test]
+    fn test_fill_ledger_gaps() {
+        let client = MockClient::new(|uri| {
+            let query: std::collections::HashMap<_, _> = uri.query_pairs().into_owned().collect();
+            let cursor: i64 = query.get("cursor").unwrap().parse().unwrap();
+            let first = (cursor >> 32) as u32 + 1;
+            let records = (first..first + 2)
+                .map(|sequence| ledger_json(sequence, sequence as i64 * 5))
+                .collect();
+            Some(page_json(records))
+        });
+        let filled = fill_ledger_gaps(&client, ledgers(&[1, 2, 6, 7, 9]));
+        let filled: Vec<_> = block_on(check_ledgers(filled).try_collect()).unwrap();
+        assert_eq!((1..10).collect::<Vec<_>>(), sequences(&filled));
+    }
+}
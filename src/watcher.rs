@@ -0,0 +1,505 @@
+//! Watch many accounts at once.
+//!
+//! An `AccountWatcher` streams the payments or effects of a set of
+//! accounts that can change at runtime. While the set is small, it
+//! opens one stream per account. When the set grows past a threshold,
+//! it switches to a single stream of all the network payments or
+//! effects, filtered on the client.
+//!
+//! The watcher spawns its streams on the tokio runtime.
+//!
+//! ```rust
+//! use stellar_base::PublicKey;
+//! use stellar_horizon::client::HorizonHttpClient;
+//! use stellar_horizon::resources::Payment;
+//! use stellar_horizon::watcher::AccountWatcher;
+//! use futures::stream::TryStreamExt;
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let watcher = AccountWatcher::<_, Payment>::new(client);
+//! let mut events = watcher.events().unwrap();
+//! let account = PublicKey::from_account_id("GA73S4WXZG7EONFCIFDSZ6VOJKFC2PMV5574YDJC4V4UBDGPAYN4SPAC")?;
+//! watcher.add(&account);
+//! while let Some(event) = events.try_next().await? {
+//!     println!("Account = {}, Payment = {:?}", event.account.account_id(), event.event.resource);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::api;
+use crate::client::HorizonClient;
+use crate::error::Result;
+use crate::request::{PageRequest, StreamRequest};
+use crate::resources::{Effect, PagingToken, Payment, Record};
+use crate::stream::StreamEvent;
+use crate::verify::compare_paging_tokens;
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use stellar_base::crypto::PublicKey;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// The default number of accounts watched with per-account streams.
+pub const DEFAULT_THRESHOLD: usize = 10;
+
+/// The default number of events buffered before applying backpressure
+/// to the streams.
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+// Stream key of the global stream. Account ids are never empty.
+const GLOBAL_STREAM: &str = "";
+
+/// A record that can be watched by account.
+pub trait WatchedRecord: Record + DeserializeOwned + Clone + Send + Sync + 'static {
+    /// The request to stream all records.
+    type AllRequest: StreamRequest<Resource = Self> + PageRequest + 'static;
+    /// The request to stream the records of one account.
+    type AccountRequest: StreamRequest<Resource = Self> + PageRequest + 'static;
+
+    /// Returns the request to stream all records.
+    fn all_request() -> Self::AllRequest;
+
+    /// Returns the request to stream the records of `account`.
+    fn account_request(account: &PublicKey) -> Self::AccountRequest;
+
+    /// Returns the account ids the record belongs to.
+    fn accounts(&self) -> Vec<&str>;
+}
+
+/// How the watcher streams records.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WatchMode {
+    /// One stream for each account.
+    PerAccount,
+    /// One stream of all records, filtered on the client.
+    Global,
+}
+
+/// An event of a watched account.
+#[derive(Debug, Clone)]
+pub struct AccountEvent<T> {
+    /// The watched account.
+    pub account: PublicKey,
+    /// The event.
+    pub event: StreamEvent<T>,
+}
+
+/// Watch the records of a dynamic set of accounts.
+pub struct AccountWatcher<C, T> {
+    inner: Arc<AccountWatcherInner<C, T>>,
+    events: Mutex<Option<mpsc::Receiver<Result<AccountEvent<T>>>>>,
+}
+
+struct AccountWatcherInner<C, T> {
+    client: C,
+    cursor: String,
+    threshold: usize,
+    sender: mpsc::Sender<Result<AccountEvent<T>>>,
+    state: Mutex<WatcherState>,
+}
+
+#[derive(Default)]
+struct WatcherState {
+    accounts: HashMap<String, PublicKey>,
+    streams: HashMap<String, JoinHandle<()>>,
+    cursors: HashMap<String, PagingToken>,
+}
+
+impl<C, T> AccountWatcher<C, T>
+where
+    C: HorizonClient + Send + Sync + 'static,
+    T: WatchedRecord,
+{
+    /// Creates a new watcher that streams records from now on.
+    pub fn new(client: C) -> AccountWatcher<C, T> {
+        AccountWatcher::new_with_options(client, "now", DEFAULT_THRESHOLD, DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new watcher.
+    ///
+    /// Streams start from `cursor`, and switch to a global stream
+    /// when more than `threshold` accounts are watched. Up to
+    /// `capacity` events are buffered.
+    pub fn new_with_options(
+        client: C,
+        cursor: &str,
+        threshold: usize,
+        capacity: usize,
+    ) -> AccountWatcher<C, T> {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        let inner = AccountWatcherInner {
+            client,
+            cursor: cursor.to_string(),
+            threshold,
+            sender,
+            state: Mutex::new(WatcherState::default()),
+        };
+        AccountWatcher {
+            inner: Arc::new(inner),
+            events: Mutex::new(Some(receiver)),
+        }
+    }
+
+    /// Returns the stream of events of the watched accounts.
+    ///
+    /// Returns `None` if the stream was already taken.
+    pub fn events(&self) -> Option<BoxStream<'static, Result<AccountEvent<T>>>> {
+        let receiver = self.events.lock().unwrap().take()?;
+        let events = stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|event| (event, receiver))
+        });
+        Some(events.boxed())
+    }
+
+    /// Starts watching `account`.
+    pub fn add(&self, account: &PublicKey) {
+        let account_id = account.account_id();
+        let mut state = self.inner.state.lock().unwrap();
+        if state
+            .accounts
+            .insert(account_id.clone(), *account)
+            .is_some()
+        {
+            return;
+        }
+        match state.mode() {
+            WatchMode::Global => {}
+            WatchMode::PerAccount if state.accounts.len() > self.inner.threshold => {
+                // Resume from the oldest cursor seen, some records may be
+                // returned twice.
+                let cursor = state
+                    .oldest_cursor()
+                    .unwrap_or_else(|| self.inner.cursor.clone());
+                state.stop_all();
+                self.start_global(&mut state, cursor);
+            }
+            WatchMode::PerAccount => {
+                let cursor = self.inner.cursor.clone();
+                self.start_account(&mut state, account, cursor);
+            }
+        }
+    }
+
+    /// Stops watching `account`.
+    pub fn remove(&self, account: &PublicKey) {
+        let account_id = account.account_id();
+        let mut state = self.inner.state.lock().unwrap();
+        if state.accounts.remove(&account_id).is_none() {
+            return;
+        }
+        match state.mode() {
+            WatchMode::PerAccount => state.stop(&account_id),
+            WatchMode::Global if state.accounts.len() <= self.inner.threshold => {
+                let cursor = state
+                    .cursors
+                    .get(GLOBAL_STREAM)
+                    .map(|cursor| cursor.to_string())
+                    .unwrap_or_else(|| self.inner.cursor.clone());
+                state.stop_all();
+                let accounts: Vec<_> = state.accounts.values().copied().collect();
+                for account in accounts {
+                    self.start_account(&mut state, &account, cursor.clone());
+                }
+            }
+            WatchMode::Global => {}
+        }
+    }
+
+    /// Returns the watched accounts.
+    pub fn accounts(&self) -> Vec<PublicKey> {
+        let state = self.inner.state.lock().unwrap();
+        state.accounts.values().copied().collect()
+    }
+
+    /// Returns how the watcher is currently streaming records.
+    pub fn mode(&self) -> WatchMode {
+        self.inner.state.lock().unwrap().mode()
+    }
+
+    fn start_account(&self, state: &mut WatcherState, account: &PublicKey, cursor: String) {
+        let key = account.account_id();
+        let account = *account;
+        let handle = tokio::spawn(run_stream(
+            self.inner.clone(),
+            key.clone(),
+            cursor,
+            move || T::account_request(&account),
+        ));
+        state.streams.insert(key, handle);
+    }
+
+    fn start_global(&self, state: &mut WatcherState, cursor: String) {
+        let key = GLOBAL_STREAM.to_string();
+        let handle = tokio::spawn(run_stream(
+            self.inner.clone(),
+            key.clone(),
+            cursor,
+            T::all_request,
+        ));
+        state.streams.insert(key, handle);
+    }
+}
+
+impl<C, T> Drop for AccountWatcher<C, T> {
+    fn drop(&mut self) {
+        self.inner.state.lock().unwrap().stop_all();
+    }
+}
+
+impl WatcherState {
+    fn mode(&self) -> WatchMode {
+        if self.streams.contains_key(GLOBAL_STREAM) {
+            WatchMode::Global
+        } else {
+            WatchMode::PerAccount
+        }
+    }
+
+    fn oldest_cursor(&self) -> Option<String> {
+        self.cursors
+            .values()
+            .min_by(|a, b| compare_paging_tokens(a, b))
+            .map(|cursor| cursor.to_string())
+    }
+
+    fn stop(&mut self, key: &str) {
+        if let Some(handle) = self.streams.remove(key) {
+            handle.abort();
+        }
+        self.cursors.remove(key);
+    }
+
+    fn stop_all(&mut self) {
+        for (_, handle) in self.streams.drain() {
+            handle.abort();
+        }
+        self.cursors.clear();
+    }
+}
+
+impl<C, T> AccountWatcherInner<C, T>
+where
+    T: WatchedRecord,
+{
+    /// Saves the stream cursor and returns the accounts the record
+    /// should be sent to.
+    fn route(&self, key: &str, record: &T) -> Vec<PublicKey> {
+        let mut state = self.state.lock().unwrap();
+        if !state.streams.contains_key(key) {
+            // The stream was stopped.
+            return Vec::new();
+        }
+        state
+            .cursors
+            .insert(key.to_string(), record.paging_token().clone());
+        if key == GLOBAL_STREAM {
+            record
+                .accounts()
+                .into_iter()
+                .filter_map(|account| state.accounts.get(account).copied())
+                .collect()
+        } else {
+            state.accounts.get(key).copied().into_iter().collect()
+        }
+    }
+}
+
+async fn run_stream<C, T, R, F>(
+    inner: Arc<AccountWatcherInner<C, T>>,
+    key: String,
+    mut cursor: String,
+    request: F,
+) where
+    C: HorizonClient + Send + Sync + 'static,
+    T: WatchedRecord,
+    R: StreamRequest<Resource = T> + PageRequest + 'static,
+    F: Fn() -> R + Send,
+{
+    loop {
+        let events = inner.client.stream_events(request().with_cursor(&cursor));
+        match events {
+            Ok(mut events) => {
+                while let Some(event) = events.next().await {
+                    match event {
+                        Ok(event) => {
+                            cursor = event.resource.paging_token().to_string();
+                            for account in inner.route(&key, &event.resource) {
+                                let event = AccountEvent {
+                                    account,
+                                    event: event.clone(),
+                                };
+                                if inner.sender.send(Ok(event)).await.is_err() {
+                                    return;
+                                }
+                            }
+                        }
+                        Err(err) => {
+                            if inner.sender.send(Err(err)).await.is_err() {
+                                return;
+                            }
+                            tokio::time::sleep(RECONNECT_DELAY).await;
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                if inner.sender.send(Err(err)).await.is_err() {
+                    return;
+                }
+            }
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+impl WatchedRecord for Payment {
+    type AllRequest = api::payments::AllPaymentsRequest;
+    type AccountRequest = api::payments::PaymentsForAccountRequest;
+
+    fn all_request() -> Self::AllRequest {
+        api::payments::all()
+    }
+
+    fn account_request(account: &PublicKey) -> Self::AccountRequest {
+        api::payments::for_account(account)
+    }
+
+    fn accounts(&self) -> Vec<&str> {
+        self.participants()
+    }
+}
+
+impl WatchedRecord for Effect {
+    type AllRequest = api::effects::AllEffectsRequest;
+    type AccountRequest = api::effects::EffectsForAccountRequest;
+
+    fn all_request() -> Self::AllRequest {
+        api::effects::all()
+    }
+
+    fn account_request(account: &PublicKey) -> Self::AccountRequest {
+        api::effects::for_account(account)
+    }
+
+    fn accounts(&self) -> Vec<&str> {
+        vec![self.base().account.as_str()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{payment_json, MockClient};
+    use futures::stream::TryStreamExt;
+    use serde_json::json;
+
+    const A: &str = "GAAZI4TCR3TY5OJHCTJC2A4QSY6CJWJH5IAJTGKIN2ER7LBNVKOCCWN7";
+    const B: &str = "GALPCCZN4YXA3YMJHKL6CVIECKPLJJCTVMSNYWBTKJW4K5HQLYLDMZTB";
+    const X: &str = "GAP2KHWUMOHY7IO37UJY7SEBIITJIDZS5DRIIQRPEUT4VUKHZQGIRWS4";
+
+    // Streams the payments after the request cursor, of one account
+    // or of all accounts.
+    fn client() -> MockClient {
+        MockClient::new(|uri| {
+            let payments = [(A, X, 1), (X, B, 2), (X, X, 3), (B, A, 4)];
+            let query: HashMap<_, _> = uri.query_pairs().into_owned().collect();
+            let cursor: i64 = query
+                .get("cursor")
+                .and_then(|cursor| cursor.parse().ok())
+                .unwrap_or(0);
+            let account = uri
+                .path()
+                .strip_prefix("/accounts/")
+                .and_then(|path| path.strip_suffix("/payments"))
+                .map(str::to_string);
+            let payments: Vec<_> = payments
+                .iter()
+                .filter(|(from, to, token)| {
+                    *token > cursor && account.as_ref().is_none_or(|a| a == from || a == to)
+                })
+                .map(|(from, to, token)| payment_json(from, to, *token))
+                .collect();
+            Some(json!(payments))
+        })
+    }
+
+    fn key(account: &str) -> PublicKey {
+        PublicKey::from_account_id(account).unwrap()
+    }
+
+    async fn collect(
+        watcher: &AccountWatcher<MockClient, Payment>,
+        count: usize,
+    ) -> Vec<(String, String)> {
+        let events: Vec<_> = watcher
+            .events()
+            .unwrap()
+            .take(count)
+            .try_collect()
+            .await
+            .unwrap();
+        let mut events: Vec<_> = events
+            .into_iter()
+            .map(|e| {
+                let token = e.event.resource.paging_token().to_string();
+                (e.account.account_id(), token)
+            })
+            .collect();
+        events.sort();
+        events
+    }
+
+    #[tokio::test]
+    async fn test_watch_per_account() {
+        let watcher = AccountWatcher::new_with_options(client(), "0", 10, 16);
+        watcher.add(&key(A));
+        watcher.add(&key(B));
+        assert_eq!(WatchMode::PerAccount, watcher.mode());
+
+        let events = collect(&watcher, 4).await;
+        let expected = vec![
+            (A.to_string(), "1".to_string()),
+            (A.to_string(), "4".to_string()),
+            (B.to_string(), "2".to_string()),
+            (B.to_string(), "4".to_string()),
+        ];
+        assert_eq!(expected, events);
+    }
+
+    #[tokio::test]
+    async fn test_watch_global() {
+        let client = client();
+        let watcher = AccountWatcher::new_with_options(client, "0", 1, 16);
+        watcher.add(&key(A));
+        watcher.add(&key(B));
+        assert_eq!(WatchMode::Global, watcher.mode());
+
+        let events = collect(&watcher, 4).await;
+        let expected = vec![
+            (A.to_string(), "1".to_string()),
+            (A.to_string(), "4".to_string()),
+            (B.to_string(), "2".to_string()),
+            (B.to_string(), "4".to_string()),
+        ];
+        assert_eq!(expected, events);
+    }
+
+    #[tokio::test]
+    async fn test_switch_mode() {
+        let watcher: AccountWatcher<_, Payment> =
+            AccountWatcher::new_with_options(client(), "0", 1, 16);
+        watcher.add(&key(A));
+        assert_eq!(WatchMode::PerAccount, watcher.mode());
+        watcher.add(&key(B));
+        assert_eq!(WatchMode::Global, watcher.mode());
+        watcher.remove(&key(A));
+        assert_eq!(WatchMode::PerAccount, watcher.mode());
+        assert_eq!(vec![key(B)], watcher.accounts());
+    }
+}
@@ -0,0 +1,695 @@
+//! Deliver account activity to webhooks.
+//!
+//! A `WebhookDispatcher` consumes the events of an `AccountWatcher` and
+//! POSTs each of them as json to a webhook url. Events are first saved
+//! in a `DeliveryQueue`, so that they survive restarts, then delivered.
+//! Failed deliveries are retried with exponential backoff, and moved to
+//! the dead letters after too many attempts.
+//!
+//! Each request is signed with HMAC-SHA256 over the timestamp and the
+//! body, receivers check it with `verify_signature`. Deliveries may be
+//! repeated or reordered, receivers should use the delivery id header to
+//! discard duplicates.
+//!
+//! ```rust
+//! use stellar_base::PublicKey;
+//! use stellar_horizon::client::HorizonHttpClient;
+//! use stellar_horizon::resources::Payment;
+//! use stellar_horizon::watcher::AccountWatcher;
+//! use stellar_horizon::webhook::{FileDeliveryQueue, WebhookDispatcher};
+//!
+//! # async fn run() -> stellar_horizon::error::Result<()> {
+//! let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
+//! let watcher = AccountWatcher::<_, Payment>::new(client);
+//! let account = PublicKey::from_account_id("GA73S4WXZG7EONFCIFDSZ6VOJKFC2PMV5574YDJC4V4UBDGPAYN4SPAC")?;
+//! watcher.add(&account);
+//! let queue = FileDeliveryQueue::new("deliveries.json");
+//! let dispatcher = WebhookDispatcher::new("https://example.com/hook".parse()?, b"secret", queue);
+//! dispatcher.run(watcher.events().unwrap()).await?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::error::{Error, Result};
+use crate::resources::Record;
+use crate::stream::StreamEvent;
+use crate::watcher::AccountEvent;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::future::{self, BoxFuture};
+use futures::stream::{Stream, StreamExt};
+use hmac::{Hmac, Mac};
+use http_body_util::Full;
+use hyper_tls::HttpsConnector;
+use hyper_util::client::legacy::{connect::HttpConnector, Client};
+use hyper_util::rt::TokioExecutor;
+use serde::Serialize;
+use sha2::Sha256;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use url::Url;
+
+/// The header with the delivery id.
+pub const ID_HEADER: &str = "x-webhook-id";
+
+/// The header with the unix timestamp of the request.
+pub const TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+
+/// The header with the request signature.
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+/// The default number of attempts before dead-lettering a delivery.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 8;
+
+/// The default delay before the first retry.
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The default maximum delay between retries.
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(600);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// How often `run` retries deliveries when there are no new events.
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+type HmacSha256 = Hmac<Sha256>;
+
+type HttpClient = Client<HttpsConnector<HttpConnector>, Full<Bytes>>;
+
+/// A webhook request waiting to be delivered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Delivery {
+    /// The delivery id, unique for each event.
+    pub id: String,
+    /// The json body of the request.
+    pub payload: serde_json::Value,
+    /// The number of failed attempts.
+    pub attempts: u32,
+    /// The time of the next attempt.
+    pub next_attempt_at: DateTime<Utc>,
+    /// The error of the last failed attempt.
+    pub last_error: Option<String>,
+}
+
+/// Store deliveries until they are delivered.
+pub trait DeliveryQueue: Send + Sync {
+    /// Adds `delivery` to the queue, replacing the delivery with the same id.
+    fn push<'a>(&'a self, delivery: &'a Delivery) -> BoxFuture<'a, Result<()>>;
+    /// Returns the pending deliveries, in the order they were added.
+    fn pending(&self) -> BoxFuture<'_, Result<Vec<Delivery>>>;
+    /// Removes the delivery with `id`.
+    fn remove<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<()>>;
+    /// Moves `delivery` from the pending deliveries to the dead letters.
+    fn dead_letter<'a>(&'a self, delivery: &'a Delivery) -> BoxFuture<'a, Result<()>>;
+    /// Returns the deliveries that failed too many times.
+    fn dead_letters(&self) -> BoxFuture<'_, Result<Vec<Delivery>>>;
+
+    /// Applies `updates` in order.
+    ///
+    /// The default implementation applies each update separately,
+    /// queues that persist their state should save it only once.
+    fn apply<'a>(&'a self, updates: &'a [DeliveryUpdate]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            for update in updates {
+                match update {
+                    DeliveryUpdate::Push(delivery) => self.push(delivery).await?,
+                    DeliveryUpdate::Remove(id) => self.remove(id).await?,
+                    DeliveryUpdate::DeadLetter(delivery) => self.dead_letter(delivery).await?,
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// A change to a `DeliveryQueue`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeliveryUpdate {
+    /// Adds the delivery, replacing the delivery with the same id.
+    Push(Delivery),
+    /// Removes the delivery with this id.
+    Remove(String),
+    /// Moves the delivery to the dead letters.
+    DeadLetter(Delivery),
+}
+
+/// A `DeliveryQueue` that keeps deliveries in memory.
+#[derive(Debug, Default)]
+pub struct MemoryDeliveryQueue {
+    state: Mutex<QueueState>,
+}
+
+/// A `DeliveryQueue` that saves deliveries to a json file.
+///
+/// The file is replaced atomically on each change, so that it's never
+/// left half written.
+#[derive(Debug)]
+pub struct FileDeliveryQueue {
+    path: PathBuf,
+    lock: tokio::sync::Mutex<()>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct QueueState {
+    pending: Vec<Delivery>,
+    dead_letters: Vec<Delivery>,
+}
+
+/// POST events to a webhook.
+pub struct WebhookDispatcher<Q> {
+    url: Url,
+    secret: Vec<u8>,
+    queue: Q,
+    http: HttpClient,
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    stream_errors: AtomicU64,
+}
+
+#[derive(Serialize)]
+struct AccountEventPayload<'a, T> {
+    account: String,
+    event: &'a StreamEvent<T>,
+}
+
+impl QueueState {
+    fn push(&mut self, delivery: &Delivery) {
+        match self.pending.iter_mut().find(|d| d.id == delivery.id) {
+            Some(existing) => *existing = delivery.clone(),
+            None => self.pending.push(delivery.clone()),
+        }
+    }
+
+    fn remove(&mut self, id: &str) {
+        self.pending.retain(|d| d.id != id);
+    }
+
+    fn dead_letter(&mut self, delivery: &Delivery) {
+        self.remove(&delivery.id);
+        self.dead_letters.push(delivery.clone());
+    }
+
+    fn apply(&mut self, update: &DeliveryUpdate) {
+        match update {
+            DeliveryUpdate::Push(delivery) => self.push(delivery),
+            DeliveryUpdate::Remove(id) => self.remove(id),
+            DeliveryUpdate::DeadLetter(delivery) => self.dead_letter(delivery),
+        }
+    }
+}
+
+impl MemoryDeliveryQueue {
+    /// Creates a new, empty, queue.
+    pub fn new() -> MemoryDeliveryQueue {
+        Default::default()
+    }
+
+    fn update<F: FnOnce(&mut QueueState)>(&self, f: F) -> BoxFuture<'_, Result<()>> {
+        f(&mut self.state.lock().unwrap());
+        Box::pin(future::ready(Ok(())))
+    }
+}
+
+impl DeliveryQueue for MemoryDeliveryQueue {
+    fn push<'a>(&'a self, delivery: &'a Delivery) -> BoxFuture<'a, Result<()>> {
+        self.update(|state| state.push(delivery))
+    }
+
+    fn pending(&self) -> BoxFuture<'_, Result<Vec<Delivery>>> {
+        let pending = self.state.lock().unwrap().pending.clone();
+        Box::pin(future::ready(Ok(pending)))
+    }
+
+    fn remove<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<()>> {
+        self.update(|state| state.remove(id))
+    }
+
+    fn dead_letter<'a>(&'a self, delivery: &'a Delivery) -> BoxFuture<'a, Result<()>> {
+        self.update(|state| state.dead_letter(delivery))
+    }
+
+    fn dead_letters(&self) -> BoxFuture<'_, Result<Vec<Delivery>>> {
+        let dead_letters = self.state.lock().unwrap().dead_letters.clone();
+        Box::pin(future::ready(Ok(dead_letters)))
+    }
+
+    fn apply<'a>(&'a self, updates: &'a [DeliveryUpdate]) -> BoxFuture<'a, Result<()>> {
+        self.update(|state| updates.iter().for_each(|update| state.apply(update)))
+    }
+}
+
+impl FileDeliveryQueue {
+    /// Creates a new queue that saves deliveries to `path`.
+    ///
+    /// The file is created on the first change.
+    pub fn new<P: Into<PathBuf>>(path: P) -> FileDeliveryQueue {
+        FileDeliveryQueue {
+            path: path.into(),
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    async fn read(&self) -> Result<QueueState> {
+        match tokio::fs::read(&self.path).await {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(QueueState::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn write(&self, state: &QueueState) -> Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec(state)?).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+
+    fn update<'a, F>(&'a self, f: F) -> BoxFuture<'a, Result<()>>
+    where
+        F: FnOnce(&mut QueueState) + Send + 'a,
+    {
+        Box::pin(async move {
+            let _guard = self.lock.lock().await;
+            let mut state = self.read().await?;
+            f(&mut state);
+            self.write(&state).await
+        })
+    }
+
+    async fn state(&self) -> Result<QueueState> {
+        let _guard = self.lock.lock().await;
+        self.read().await
+    }
+}
+
+impl DeliveryQueue for FileDeliveryQueue {
+    fn push<'a>(&'a self, delivery: &'a Delivery) -> BoxFuture<'a, Result<()>> {
+        self.update(|state| state.push(delivery))
+    }
+
+    fn pending(&self) -> BoxFuture<'_, Result<Vec<Delivery>>> {
+        Box::pin(async move { Ok(self.state().await?.pending) })
+    }
+
+    fn remove<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<()>> {
+        self.update(|state| state.remove(id))
+    }
+
+    fn dead_letter<'a>(&'a self, delivery: &'a Delivery) -> BoxFuture<'a, Result<()>> {
+        self.update(|state| state.dead_letter(delivery))
+    }
+
+    fn dead_letters(&self) -> BoxFuture<'_, Result<Vec<Delivery>>> {
+        Box::pin(async move { Ok(self.state().await?.dead_letters) })
+    }
+
+    fn apply<'a>(&'a self, updates: &'a [DeliveryUpdate]) -> BoxFuture<'a, Result<()>> {
+        self.update(|state| updates.iter().for_each(|update| state.apply(update)))
+    }
+}
+
+impl<Q> WebhookDispatcher<Q>
+where
+    Q: DeliveryQueue,
+{
+    /// Creates a new dispatcher that POSTs to `url`, signing requests
+    /// with `secret`.
+    pub fn new(url: Url, secret: &[u8], queue: Q) -> WebhookDispatcher<Q> {
+        let http = Client::builder(TokioExecutor::new()).build(HttpsConnector::new());
+        WebhookDispatcher {
+            url,
+            secret: secret.to_vec(),
+            queue,
+            http,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            stream_errors: AtomicU64::new(0),
+        }
+    }
+
+    /// Dead-letter deliveries after `max_attempts` failed attempts.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Wait `initial` before the first retry, doubling the delay after
+    /// each failed attempt up to `max`.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Returns the delivery queue.
+    pub fn queue(&self) -> &Q {
+        &self.queue
+    }
+
+    /// Returns the number of errors returned by the event streams.
+    pub fn stream_errors(&self) -> u64 {
+        self.stream_errors.load(Ordering::Relaxed)
+    }
+
+    /// Delivers the events of `events` until the stream ends, or the
+    /// queue returns an error.
+    ///
+    /// Stream errors, like the transient errors of an `AccountWatcher`
+    /// while it reconnects, are counted in `stream_errors` and skipped.
+    /// Failed deliveries are retried while waiting for events. Deliveries
+    /// still pending when the stream ends stay in the queue.
+    pub async fn run<S, T>(&self, events: S) -> Result<()>
+    where
+        S: Stream<Item = Result<AccountEvent<T>>>,
+        T: Record + Serialize,
+    {
+        let mut events = Box::pin(events);
+        loop {
+            self.deliver_due().await?;
+            match tokio::time::timeout(RETRY_INTERVAL, events.next()).await {
+                Err(_) => {}
+                Ok(None) => return Ok(()),
+                Ok(Some(Ok(event))) => self.enqueue_event(&event).await?,
+                Ok(Some(Err(_))) => {
+                    self.stream_errors.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// Adds an account event to the queue.
+    ///
+    /// The delivery id is the account id followed by the record paging
+    /// token.
+    pub async fn enqueue_event<T>(&self, event: &AccountEvent<T>) -> Result<()>
+    where
+        T: Record + Serialize,
+    {
+        let account = event.account.account_id();
+        let id = format!("{}-{}", account, event.event.resource.paging_token());
+        let payload = AccountEventPayload {
+            account,
+            event: &event.event,
+        };
+        self.enqueue(&id, &payload).await
+    }
+
+    /// Adds a delivery of `payload` to the queue.
+    pub async fn enqueue<T: Serialize>(&self, id: &str, payload: &T) -> Result<()> {
+        let delivery = Delivery {
+            id: id.to_string(),
+            payload: serde_json::to_value(payload)?,
+            attempts: 0,
+            next_attempt_at: Utc::now(),
+            last_error: None,
+        };
+        self.queue.push(&delivery).await
+    }
+
+    /// Attempts the deliveries that are due, returns the number of
+    /// successful deliveries.
+    ///
+    /// The queue is updated once, after all the attempts.
+    pub async fn deliver_due(&self) -> Result<usize> {
+        let now = Utc::now();
+        let mut delivered = 0;
+        let mut updates = Vec::new();
+        for mut delivery in self.queue.pending().await? {
+            if delivery.next_attempt_at > now {
+                continue;
+            }
+            match self.post(&delivery).await {
+                Ok(()) => {
+                    updates.push(DeliveryUpdate::Remove(delivery.id));
+                    delivered += 1;
+                }
+                Err(err) => {
+                    delivery.attempts += 1;
+                    delivery.last_error = Some(err.to_string());
+                    if delivery.attempts >= self.max_attempts {
+                        updates.push(DeliveryUpdate::DeadLetter(delivery));
+                    } else {
+                        let backoff = self.backoff(delivery.attempts);
+                        delivery.next_attempt_at = now + backoff;
+                        updates.push(DeliveryUpdate::Push(delivery));
+                    }
+                }
+            }
+        }
+        if !updates.is_empty() {
+            self.queue.apply(&updates).await?;
+        }
+        Ok(delivered)
+    }
+
+    fn backoff(&self, attempts: u32) -> chrono::Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff);
+        chrono::Duration::from_std(backoff).unwrap_or(chrono::Duration::MAX)
+    }
+
+    async fn post(&self, delivery: &Delivery) -> Result<()> {
+        let body = serde_json::to_vec(&delivery.payload)?;
+        let timestamp = Utc::now().timestamp();
+        let request = http::Request::post(self.url.as_str())
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .header(ID_HEADER, &delivery.id)
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, sign(&self.secret, timestamp, &body))
+            .body(Full::new(Bytes::from(body)))?;
+        let response = tokio::time::timeout(REQUEST_TIMEOUT, self.http.request(request))
+            .await
+            .map_err(|_| Error::WebhookDeliveryFailed("timeout".to_string()))?
+            .map_err(|err| Error::WebhookDeliveryFailed(err.to_string()))?;
+        if !response.status().is_success() {
+            let status = response.status().to_string();
+            return Err(Error::WebhookDeliveryFailed(status));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the signature of a request `body` sent at `timestamp`.
+pub fn sign(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+    let mac = signature_mac(secret, timestamp, body);
+    format!("v1={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Returns true if `signature` is the signature of `body` sent at
+/// `timestamp`.
+///
+/// Receivers should also reject old timestamps, to prevent replays.
+pub fn verify_signature(secret: &[u8], timestamp: i64, body: &[u8], signature: &str) -> bool {
+    let signature = match signature.strip_prefix("v1=").map(hex::decode) {
+        Some(Ok(signature)) => signature,
+        _ => return false,
+    };
+    signature_mac(secret, timestamp, body)
+        .verify_slice(&signature)
+        .is_ok()
+}
+
+fn signature_mac(secret: &[u8], timestamp: i64, body: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("hmac accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::payment_json;
+    use crate::resources::Payment;
+    use futures::stream;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use stellar_base::PublicKey;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const ACCOUNT: &str = "GA73S4WXZG7EONFCIFDSZ6VOJKFC2PMV5574YDJC4V4UBDGPAYN4SPAC";
+    const SECRET: &[u8] = b"secret";
+
+    type Received = Arc<Mutex<Vec<(HashMap<String, String>, Vec<u8>)>>>;
+
+    // A webhook receiver answering with `status` and recording requests.
+    async fn receiver(status: u16) -> (Url, Received) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let received = Received::default();
+        let requests = received.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = Vec::new();
+                let mut chunk = [0; 4096];
+                let header_end = loop {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    buffer.extend_from_slice(&chunk[..n]);
+                    if let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break end + 4;
+                    }
+                };
+                let headers: HashMap<_, _> = String::from_utf8_lossy(&buffer[..header_end])
+                    .lines()
+                    .skip(1)
+                    .filter_map(|line| line.split_once(": "))
+                    .map(|(name, value)| (name.to_lowercase(), value.to_string()))
+                    .collect();
+                let length: usize = headers["content-length"].parse().unwrap();
+                while buffer.len() < header_end + length {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    buffer.extend_from_slice(&chunk[..n]);
+                }
+                let body = buffer[header_end..header_end + length].to_vec();
+                requests.lock().unwrap().push((headers, body));
+                let response = format!(
+                    "HTTP/1.1 {} X\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                    status
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url.parse().unwrap(), received)
+    }
+
+    fn payment_event(paging_token: i64) -> AccountEvent<Payment> {
+        let payment = serde_json::from_value(payment_json(ACCOUNT, ACCOUNT, paging_token)).unwrap();
+        AccountEvent {
+            account: PublicKey::from_account_id(ACCOUNT).unwrap(),
+            event: StreamEvent::new(Some(paging_token.to_string()), payment),
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signature = sign(SECRET, 1000, b"body");
+        assert!(signature.starts_with("v1="));
+        assert!(verify_signature(SECRET, 1000, b"body", &signature));
+        assert!(!verify_signature(SECRET, 1001, b"body", &signature));
+        assert!(!verify_signature(b"other", 1000, b"body", &signature));
+        assert!(!verify_signature(SECRET, 1000, b"body", "v1=zz"));
+    }
+
+    #[tokio::test]
+    async fn test_run_delivers_signed_events() {
+        let (url, received) = receiver(200).await;
+        let dispatcher = WebhookDispatcher::new(url, SECRET, MemoryDeliveryQueue::new());
+        let events = stream::iter(vec![Ok(payment_event(10)), Ok(payment_event(11))]);
+        dispatcher.run(events).await.unwrap();
+        dispatcher.deliver_due().await.unwrap();
+
+        assert!(dispatcher.queue().pending().await.unwrap().is_empty());
+        let received = received.lock().unwrap();
+        assert_eq!(2, received.len());
+        let (headers, body) = &received[0];
+        assert_eq!(format!("{}-10", ACCOUNT), headers[ID_HEADER]);
+        let timestamp = headers[TIMESTAMP_HEADER].parse().unwrap();
+        assert!(verify_signature(
+            SECRET,
+            timestamp,
+            body,
+            &headers[SIGNATURE_HEADER]
+        ));
+        let payload: serde_json::Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(json!(ACCOUNT), payload["account"]);
+        assert_eq!(json!("10"), payload["event"]["id"]);
+    }
+
+    #[tokio::test]
+    async fn test_run_skips_stream_errors() {
+        let (url, received) = receiver(200).await;
+        let dispatcher = WebhookDispatcher::new(url, SECRET, MemoryDeliveryQueue::new());
+        let events = stream::iter(vec![Err(Error::HorizonServerError), Ok(payment_event(10))]);
+        dispatcher.run(events).await.unwrap();
+        dispatcher.deliver_due().await.unwrap();
+
+        assert_eq!(1, dispatcher.stream_errors());
+        let received = received.lock().unwrap();
+        assert_eq!(1, received.len());
+        assert_eq!(format!("{}-10", ACCOUNT), received[0].0[ID_HEADER]);
+    }
+
+    #[tokio::test]
+    async fn test_retries_then_dead_letters() {
+        let (url, received) = receiver(500).await;
+        let dispatcher = WebhookDispatcher::new(url, SECRET, MemoryDeliveryQueue::new())
+            .with_max_attempts(3)
+            .with_backoff(Duration::ZERO, Duration::ZERO);
+        dispatcher.enqueue("a", &json!({"n": 1})).await.unwrap();
+
+        assert_eq!(0, dispatcher.deliver_due().await.unwrap());
+        let pending = dispatcher.queue().pending().await.unwrap();
+        assert_eq!(1, pending[0].attempts);
+        assert!(pending[0].last_error.as_ref().unwrap().contains("500"));
+
+        dispatcher.deliver_due().await.unwrap();
+        dispatcher.deliver_due().await.unwrap();
+        assert!(dispatcher.queue().pending().await.unwrap().is_empty());
+        let dead_letters = dispatcher.queue().dead_letters().await.unwrap();
+        assert_eq!(3, dead_letters[0].attempts);
+        assert_eq!(3, received.lock().unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn test_backoff() {
+        let dispatcher = WebhookDispatcher::new(
+            "http://127.0.0.1/".parse().unwrap(),
+            SECRET,
+            MemoryDeliveryQueue::new(),
+        )
+        .with_backoff(Duration::from_secs(1), Duration::from_secs(5));
+        let backoffs: Vec<_> = (1..=5)
+            .map(|n| dispatcher.backoff(n).num_seconds())
+            .collect();
+        assert_eq!(vec![1, 2, 4, 5, 5], backoffs);
+    }
+
+    #[tokio::test]
+    async fn test_file_delivery_queue() {
+        let path = std::env::temp_dir().join(format!("deliveries-{}.json", std::process::id()));
+        let delivery = |id: &str| Delivery {
+            id: id.to_string(),
+            payload: json!({}),
+            attempts: 0,
+            next_attempt_at: Utc::now(),
+            last_error: None,
+        };
+        let queue = FileDeliveryQueue::new(&path);
+        queue.push(&delivery("a")).await.unwrap();
+        queue.push(&delivery("b")).await.unwrap();
+        queue.push(&delivery("c")).await.unwrap();
+        queue.remove("a").await.unwrap();
+        queue.dead_letter(&delivery("b")).await.unwrap();
+        let mut retried = delivery("d");
+        retried.attempts = 1;
+        let updates = [
+            DeliveryUpdate::Push(delivery("d")),
+            DeliveryUpdate::Push(retried),
+            DeliveryUpdate::Remove("c".to_string()),
+        ];
+        queue.apply(&updates).await.unwrap();
+
+        let queue = FileDeliveryQueue::new(&path);
+        let pending = queue.pending().await.unwrap();
+        assert_eq!(vec!["d"], pending.iter().map(|d| &d.id).collect::<Vec<_>>());
+        assert_eq!(1, pending[0].attempts);
+        let dead_letters = queue.dead_letters().await.unwrap();
+        assert_eq!(
+            vec!["b"],
+            dead_letters.iter().map(|d| &d.id).collect::<Vec<_>>()
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -12,6 +12,7 @@ use stellar_horizon::api::aggregations::Resolution;
 use stellar_horizon::client::{HorizonClient, HorizonHttpClient};
 use stellar_horizon::headers::{rate_limit_limit, rate_limit_remaining, rate_limit_reset};
 use stellar_horizon::request::{Order, PageRequest};
+use stellar_horizon::resources::{ClaimableBalanceId, LiquidityPoolId};
 use tokio_stream::StreamExt;
 
 fn new_client() -> HorizonHttpClient {
@@ -101,7 +102,18 @@ async fn test_single_account() {
     let root_key = new_root_key().public_key();
     let req = api::accounts::single(&root_key);
     let (_, response) = client.request(req).await.unwrap();
-    assert_eq!(root_key.account_id(), response.paging_token);
+    assert_eq!(root_key.account_id(), response.paging_token.as_str());
+}
+
+#[tokio::test]
+async fn test_stream_single_account() {
+    let client = new_client();
+    let root_key = new_root_key().public_key();
+    let req = api::accounts::single(&root_key);
+    let mut stream = client.stream(req).unwrap().take(1);
+    while let Some(event) = stream.next().await {
+        assert_eq!(root_key.account_id(), event.unwrap().account_id);
+    }
 }
 
 #[tokio::test]
@@ -191,10 +203,12 @@ async fn test_stream_transactions_for_ledger() {
 #[ignore]
 #[tokio::test]
 async fn test_transactions_for_claimable_balance() {
-    let claimable_balance_id =
-        "00000000178826fbfe339e1f5c53417c6fedfe2c05e8bec14303143ec46b38981b09c3f9";
+    let claimable_balance_id: ClaimableBalanceId =
+        "00000000178826fbfe339e1f5c53417c6fedfe2c05e8bec14303143ec46b38981b09c3f9"
+            .parse()
+            .unwrap();
     let client = new_client();
-    let req = api::transactions::for_claimable_balance(claimable_balance_id)
+    let req = api::transactions::for_claimable_balance(&claimable_balance_id)
         .with_order(&Order::Descending)
         .with_include_failed(true)
         .with_limit(5);
@@ -206,11 +220,13 @@ async fn test_transactions_for_claimable_balance() {
 #[ignore]
 #[tokio::test]
 async fn test_stream_transactions_for_claimable_balance() {
-    let claimable_balance_id =
-        "00000000178826fbfe339e1f5c53417c6fedfe2c05e8bec14303143ec46b38981b09c3f9";
+    let claimable_balance_id: ClaimableBalanceId =
+        "00000000178826fbfe339e1f5c53417c6fedfe2c05e8bec14303143ec46b38981b09c3f9"
+            .parse()
+            .unwrap();
     let client = new_client();
     let req =
-        api::transactions::for_claimable_balance(claimable_balance_id).with_include_failed(true);
+        api::transactions::for_claimable_balance(&claimable_balance_id).with_include_failed(true);
     let mut stream = client.stream(req).unwrap().take(1);
     let mut count = 0;
     while let Some(_) = stream.next().await {
@@ -221,11 +237,14 @@ async fn test_stream_transactions_for_claimable_balance() {
 
 #[tokio::test]
 async fn test_transactions_for_liquidity_pool() {
-    let liquidity_pool_id = "0016ed5f76feb9f407a3676be3c96448c44e61298e8e5ba0f23011350212fc16";
+    let liquidity_pool_id: LiquidityPoolId =
+        "0016ed5f76feb9f407a3676be3c96448c44e61298e8e5ba0f23011350212fc16"
+            .parse()
+            .unwrap();
 
     let client = new_client();
 
-    let req = api::transactions::for_liquidity_pool(liquidity_pool_id);
+    let req = api::transactions::for_liquidity_pool(&liquidity_pool_id);
     let (_, response) = client.request(req).await.unwrap();
 
     assert!(!response.records.is_empty());
@@ -233,14 +252,17 @@ async fn test_transactions_for_liquidity_pool() {
 
 #[tokio::test]
 async fn test_stream_transactions_for_liquidity_pool() {
-    let liquidity_pool_id = "0016ed5f76feb9f407a3676be3c96448c44e61298e8e5ba0f23011350212fc16";
+    let liquidity_pool_id: LiquidityPoolId =
+        "0016ed5f76feb9f407a3676be3c96448c44e61298e8e5ba0f23011350212fc16"
+            .parse()
+            .unwrap();
 
     let client = new_client();
 
-    let req = api::transactions::for_liquidity_pool(liquidity_pool_id);
+    let req = api::transactions::for_liquidity_pool(&liquidity_pool_id);
     let mut stream = client.stream(req).unwrap().take(1);
     while let Some(event) = stream.next().await {
-        assert!(!event.unwrap().id.is_empty());
+        assert!(!event.unwrap().id.as_str().is_empty());
     }
 }
 
@@ -277,22 +299,28 @@ async fn test_trades_for_account() {
 
 #[tokio::test]
 async fn test_trades_for_liquidity_pool() {
-    let liquidity_pool_id = "0016ed5f76feb9f407a3676be3c96448c44e61298e8e5ba0f23011350212fc16";
+    let liquidity_pool_id: LiquidityPoolId =
+        "0016ed5f76feb9f407a3676be3c96448c44e61298e8e5ba0f23011350212fc16"
+            .parse()
+            .unwrap();
 
     let client = new_client();
 
-    let req = api::trades::for_liquidity_pool(liquidity_pool_id);
+    let req = api::trades::for_liquidity_pool(&liquidity_pool_id);
     let (_, response) = client.request(req).await.unwrap();
     assert!(!response.records.is_empty());
 }
 
 #[tokio::test]
 async fn test_stream_trades_for_liquidity_pool() {
-    let liquidity_pool_id = "0016ed5f76feb9f407a3676be3c96448c44e61298e8e5ba0f23011350212fc16";
+    let liquidity_pool_id: LiquidityPoolId =
+        "0016ed5f76feb9f407a3676be3c96448c44e61298e8e5ba0f23011350212fc16"
+            .parse()
+            .unwrap();
 
     let client = new_client();
 
-    let req = api::trades::for_liquidity_pool(liquidity_pool_id);
+    let req = api::trades::for_liquidity_pool(&liquidity_pool_id);
 
     let mut stream = client.stream(req).unwrap().take(1);
     let mut count = 0;
@@ -423,6 +451,20 @@ async fn test_all_offers() {
     assert!(!response.records.is_empty());
 }
 
+#[tokio::test]
+async fn test_stream_all_offers() {
+    let client = new_client();
+
+    let req = api::offers::all()
+        .with_selling(Asset::new_native())
+        .with_buying(new_credit_asset());
+
+    let mut stream = client.stream(req).unwrap().take(1);
+    while let Some(event) = stream.next().await {
+        assert!(!event.unwrap().paging_token.is_empty());
+    }
+}
+
 #[tokio::test]
 async fn test_offers_for_account() {
     let client = new_client();
@@ -503,7 +545,7 @@ async fn test_single_claimable_balance() {
     assert!(!response.records.is_empty());
 
     let claimable_balance_id = response.records.first().unwrap().id.clone();
-    let req = api::claimable_balances::single(claimable_balance_id.clone());
+    let req = api::claimable_balances::single(&claimable_balance_id);
     let (_, response) = client.request(req).await.unwrap();
     assert_eq!(claimable_balance_id, response.id);
 }
@@ -541,9 +583,7 @@ async fn test_single_operation() {
     let response_id = &response.records.first().unwrap().base().id;
 
     let (_, response) = client
-        .request(
-            api::operations::single(response_id.to_string()).with_join(api::Join::Transactions),
-        )
+        .request(api::operations::single(*response_id).with_join(api::Join::Transactions))
         .await
         .unwrap();
     assert_eq!(&response.base().id, response_id);
@@ -595,15 +635,32 @@ async fn test_stream_operations_for_ledger() {
     assert_eq!(3, count);
 }
 
+#[tokio::test]
+async fn test_stream_operations_for_transaction() {
+    let client = new_client();
+
+    let tx_req = api::transactions::all().with_limit(1);
+    let (_, tx_response) = client.request(tx_req).await.unwrap();
+    let tx_hash = &tx_response.records.first().unwrap().id;
+
+    let req = api::operations::for_transaction(tx_hash);
+    let mut stream = client.stream(req).unwrap().take(1);
+    while let Some(event) = stream.next().await {
+        assert!(!event.unwrap().base().paging_token.is_empty());
+    }
+}
+
 // Ignore test as horizon tends to fail on this one
 #[ignore]
 #[tokio::test]
 async fn test_operations_for_claimable_balance() {
-    let claimable_balance_id =
-        "00000000ffec74e0fe7d94bdb1b257dbeef1695cbf3611f538908357a5ee57924edc452f";
+    let claimable_balance_id: ClaimableBalanceId =
+        "00000000ffec74e0fe7d94bdb1b257dbeef1695cbf3611f538908357a5ee57924edc452f"
+            .parse()
+            .unwrap();
     let client = new_client();
 
-    let req = api::operations::for_claimbable_balance(claimable_balance_id);
+    let req = api::operations::for_claimbable_balance(&claimable_balance_id);
     let (_, response) = client.request(req).await.unwrap();
     assert!(!response.records.is_empty());
 }
@@ -612,11 +669,13 @@ async fn test_operations_for_claimable_balance() {
 #[ignore]
 #[tokio::test]
 async fn test_stream_operations_for_claimable_balance() {
-    let claimable_balance_id =
-        "00000000ffec74e0fe7d94bdb1b257dbeef1695cbf3611f538908357a5ee57924edc452f";
+    let claimable_balance_id: ClaimableBalanceId =
+        "00000000ffec74e0fe7d94bdb1b257dbeef1695cbf3611f538908357a5ee57924edc452f"
+            .parse()
+            .unwrap();
     let client = new_client();
 
-    let req = api::operations::for_claimbable_balance(claimable_balance_id);
+    let req = api::operations::for_claimbable_balance(&claimable_balance_id);
     let mut stream = client.stream(req).unwrap().take(1);
     let mut count = 0;
     while let Some(_event) = stream.try_next().await.unwrap() {
@@ -627,20 +686,26 @@ async fn test_stream_operations_for_claimable_balance() {
 
 #[tokio::test]
 async fn test_operations_for_liquidity_pool() {
-    let liquidity_pool_id = "6d30e1f5721962d8bad07d90c606a3963ddbe23c8751cdbdc87224d188f4593c";
+    let liquidity_pool_id: LiquidityPoolId =
+        "6d30e1f5721962d8bad07d90c606a3963ddbe23c8751cdbdc87224d188f4593c"
+            .parse()
+            .unwrap();
     let client = new_client();
 
-    let req = api::operations::for_liquidity_pool(liquidity_pool_id);
+    let req = api::operations::for_liquidity_pool(&liquidity_pool_id);
     let (_, response) = client.request(req).await.unwrap();
     assert!(!response.records.is_empty());
 }
 
 #[tokio::test]
 async fn test_stream_operations_for_liquidity_pool() {
-    let liquidity_pool_id = "6d30e1f5721962d8bad07d90c606a3963ddbe23c8751cdbdc87224d188f4593c";
+    let liquidity_pool_id: LiquidityPoolId =
+        "6d30e1f5721962d8bad07d90c606a3963ddbe23c8751cdbdc87224d188f4593c"
+            .parse()
+            .unwrap();
     let client = new_client();
 
-    let req = api::operations::for_liquidity_pool(liquidity_pool_id);
+    let req = api::operations::for_liquidity_pool(&liquidity_pool_id);
     let mut stream = client.stream(req).unwrap().take(3);
     let mut count = 0;
     while let Some(_event) = stream.try_next().await.unwrap() {
@@ -693,6 +758,18 @@ async fn test_stream_payments_for_account() {
     assert_eq!(3, count);
 }
 
+#[tokio::test]
+async fn test_stream_payments_for_ledger() {
+    let client = new_client();
+    let (_, root) = client.request(api::root::root()).await.unwrap();
+
+    let req = api::payments::for_ledger(root.history_latest_ledger);
+    let mut stream = client.stream(req).unwrap().take(1);
+    while let Some(event) = stream.next().await {
+        assert!(!event.unwrap().base().paging_token.is_empty());
+    }
+}
+
 #[tokio::test]
 async fn test_all_effects() {
     let client = new_client();
@@ -723,11 +800,26 @@ async fn test_effects_for_transaction() {
     let (_, tx_response) = client.request(tx_req).await.unwrap();
     let tx_hash = &tx_response.records.first().unwrap().id;
 
-    let req = api::effects::for_transaction(tx_hash.to_string());
+    let req = api::effects::for_transaction(tx_hash);
     let (_, response) = client.request(req).await.unwrap();
     assert!(!response.records.is_empty());
 }
 
+#[tokio::test]
+async fn test_stream_effects_for_transaction() {
+    let client = new_client();
+
+    let tx_req = api::transactions::all().with_limit(1);
+    let (_, tx_response) = client.request(tx_req).await.unwrap();
+    let tx_hash = &tx_response.records.first().unwrap().id;
+
+    let req = api::effects::for_transaction(tx_hash);
+    let mut stream = client.stream(req).unwrap().take(1);
+    while let Some(event) = stream.next().await {
+        assert!(!event.unwrap().base().paging_token.is_empty());
+    }
+}
+
 #[tokio::test]
 async fn test_effects_for_operation() {
     let client = new_client();
@@ -736,11 +828,26 @@ async fn test_effects_for_operation() {
     let (_, op_response) = client.request(op_req).await.unwrap();
     let op_id = &op_response.records.first().unwrap().base().id;
 
-    let req = api::effects::for_operation(op_id.to_string());
+    let req = api::effects::for_operation(*op_id);
     let (_, response) = client.request(req).await.unwrap();
     assert!(!response.records.is_empty());
 }
 
+#[tokio::test]
+async fn test_stream_effects_for_operation() {
+    let client = new_client();
+
+    let op_req = api::operations::all().with_limit(1);
+    let (_, op_response) = client.request(op_req).await.unwrap();
+    let op_id = &op_response.records.first().unwrap().base().id;
+
+    let req = api::effects::for_operation(*op_id);
+    let mut stream = client.stream(req).unwrap().take(1);
+    while let Some(event) = stream.next().await {
+        assert!(!event.unwrap().base().paging_token.is_empty());
+    }
+}
+
 #[tokio::test]
 async fn test_effects_for_ledger() {
     let client = new_client();
@@ -791,9 +898,12 @@ async fn test_stream_effects_for_account() {
 async fn test_effects_for_liquidity_pool() {
     let client = new_client();
 
-    let liquidity_pool_id = "006881bb9a17b0c0f4000cb12eaeb2b954390707b03a676b87f824dc6af9f207";
+    let liquidity_pool_id: LiquidityPoolId =
+        "006881bb9a17b0c0f4000cb12eaeb2b954390707b03a676b87f824dc6af9f207"
+            .parse()
+            .unwrap();
 
-    let req = api::effects::for_liquidity_pool(liquidity_pool_id);
+    let req = api::effects::for_liquidity_pool(&liquidity_pool_id);
     let (_, response) = client.request(req).await.unwrap();
     assert!(!response.records.is_empty());
 }
@@ -802,9 +912,12 @@ async fn test_effects_for_liquidity_pool() {
 async fn test_stream_effects_for_liquidity_pool() {
     let client = new_client();
 
-    let liquidity_pool_id = "006881bb9a17b0c0f4000cb12eaeb2b954390707b03a676b87f824dc6af9f207";
+    let liquidity_pool_id: LiquidityPoolId =
+        "006881bb9a17b0c0f4000cb12eaeb2b954390707b03a676b87f824dc6af9f207"
+            .parse()
+            .unwrap();
 
-    let req = api::effects::for_liquidity_pool(liquidity_pool_id);
+    let req = api::effects::for_liquidity_pool(&liquidity_pool_id);
     let mut stream = client.stream(req).unwrap().take(3);
     let mut count = 0;
     while let Some(_event) = stream.try_next().await.unwrap() {
@@ -826,9 +939,12 @@ async fn test_all_liquidity_pools() {
 async fn test_single_liquidity_pool() {
     let client = new_client();
 
-    let liquidity_pool_id = "0016ed5f76feb9f407a3676be3c96448c44e61298e8e5ba0f23011350212fc16";
+    let liquidity_pool_id: LiquidityPoolId =
+        "0016ed5f76feb9f407a3676be3c96448c44e61298e8e5ba0f23011350212fc16"
+            .parse()
+            .unwrap();
 
-    let req = api::liquidity_pools::single(liquidity_pool_id.to_string());
+    let req = api::liquidity_pools::single(&liquidity_pool_id);
     let (_, response) = client.request(req).await.unwrap();
     assert_eq!(liquidity_pool_id, response.id);
 }
@@ -842,9 +958,12 @@ async fn test_extra_headers() {
     );
     let client =
         HorizonHttpClient::with_extra_headers("https://horizon.stellar.org", headers).unwrap();
-    let liquidity_pool_id = "0016ed5f76feb9f407a3676be3c96448c44e61298e8e5ba0f23011350212fc16";
+    let liquidity_pool_id: LiquidityPoolId =
+        "0016ed5f76feb9f407a3676be3c96448c44e61298e8e5ba0f23011350212fc16"
+            .parse()
+            .unwrap();
 
-    let req = api::liquidity_pools::single(liquidity_pool_id.to_string());
+    let req = api::liquidity_pools::single(&liquidity_pool_id);
     let (_, response) = client.request(req).await.unwrap();
     assert_eq!(liquidity_pool_id, response.id);
 }
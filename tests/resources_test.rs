@@ -51,6 +51,7 @@ impl_serde_test!(
     Page<Offer>,
     "./fixtures/all_offers_with_sponsor.json"
 );
+impl_serde_test!(test_health_serde, Health, "./fixtures/health.json");
 impl_serde_test!(test_root_serde, Root, "./fixtures/root.json");
 impl_serde_test!(test_trades_serde, Page<Trade>, "./fixtures/all_trades.json");
 impl_serde_test!(